@@ -1,10 +1,72 @@
 use crate::error::{Result, SearchEngineError};
-use crate::schema::SchemaManager;
-use crate::types::{CollectionStats, FieldValue, IndexDocument, SchemaDefinition};
+use crate::schema::{
+    BOOST_FIELD, KEYWORD_LOWERCASE_TOKENIZER, STEMMERS, SchemaManager, field_tokenizer_name,
+    keyword_lowercase_analyzer, pre_tokenized_string, simple_analyzer, stemming_analyzer,
+    with_stop_words,
+};
+use crate::types::{
+    CollectionStats, FieldType, FieldValue, IndexDocument, IndexMetrics, MIN_HEAP_SIZE_PER_THREAD,
+    SchemaDefinition,
+};
 use chrono::Utc;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
-use tantivy::{Index, IndexWriter, ReloadPolicy, doc};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use tantivy::collector::{Count, DocSetCollector};
+use tantivy::directory::{Directory, META_LOCK};
+use tantivy::query::{Query, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::store::Compressor;
+use tantivy::{Index, IndexReader, IndexSettings, IndexWriter, ReloadPolicy, TantivyDocument};
+
+/// How long a measured index size is reused before the directory is walked again
+///
+/// Commits and merges clear the cached size; the TTL catches segment files
+/// Tantivy writes or removes between them.
+const INDEX_SIZE_TTL: Duration = Duration::from_secs(5);
+
+/// Index size on disk, cached between directory walks
+#[derive(Debug, Default)]
+struct IndexSizeCache {
+    /// Size in bytes and when it was measured
+    measured: Option<(u64, Instant)>,
+    /// Number of directory walks done to measure the size
+    walks: usize,
+}
+
+/// Counters behind `Collection::index_metrics`
+#[derive(Debug, Default)]
+struct WriteCounters {
+    documents_indexed: AtomicU64,
+    commits: AtomicU64,
+    last_commit_micros: AtomicU64,
+}
+
+/// Exclusive access to the index writer of an open collection
+pub(crate) struct WriterGuard<'a>(RwLockWriteGuard<'a, Option<IndexWriter>>);
+
+impl Deref for WriterGuard<'_> {
+    type Target = IndexWriter;
+
+    fn deref(&self) -> &IndexWriter {
+        self.0
+            .as_ref()
+            .expect("writer guard is only built for an open writer")
+    }
+}
+
+impl DerefMut for WriterGuard<'_> {
+    fn deref_mut(&mut self) -> &mut IndexWriter {
+        self.0
+            .as_mut()
+            .expect("writer guard is only built for an open writer")
+    }
+}
 
 /// Collection represents a single searchable collection with its own schema
 #[derive(Clone)]
@@ -12,10 +74,20 @@ pub struct Collection {
     pub name: String,
     pub schema_manager: Arc<SchemaManager>,
     pub index: Index,
-    pub writer: Arc<RwLock<IndexWriter>>,
+    /// Index writer, shared by every clone; `None` once the collection is closed
+    pub writer: Arc<RwLock<Option<IndexWriter>>>,
+    pub reader: Arc<IndexReader>,
     pub data_path: PathBuf,
+    /// Memory budget of the index writer, in bytes
+    pub heap_size: usize,
+    /// Documents added, updated or deleted since the last commit
+    pub uncommitted_docs: Arc<AtomicUsize>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: Arc<RwLock<chrono::DateTime<chrono::Utc>>>,
+    index_size: Arc<Mutex<IndexSizeCache>>,
+    write_counters: Arc<WriteCounters>,
+    /// Set once the collection is frozen; writes are then rejected
+    read_only: Arc<AtomicBool>,
 }
 
 impl Collection {
@@ -25,19 +97,34 @@ impl Collection {
         schema_def: SchemaDefinition,
         data_dir: P,
         heap_size: usize,
+        num_threads: usize,
+        enable_compression: bool,
     ) -> Result<Self> {
+        let (heap_size, num_threads) = Self::writer_budget(&schema_def, heap_size, num_threads);
         let schema_manager = Arc::new(SchemaManager::new(schema_def)?);
         let collection_path = data_dir.as_ref().join(&name);
 
         // Create directory if it doesn't exist
         std::fs::create_dir_all(&collection_path)?;
 
-        // Create Tantivy index
-        let index =
-            Index::create_in_dir(&collection_path, schema_manager.tantivy_schema().clone())?;
+        // Create Tantivy index, compressing the doc store if enabled
+        let settings = IndexSettings {
+            docstore_compression: if enable_compression {
+                Compressor::Lz4
+            } else {
+                Compressor::None
+            },
+            ..IndexSettings::default()
+        };
+        let index = Index::builder()
+            .schema(schema_manager.tantivy_schema().clone())
+            .settings(settings)
+            .create_in_dir(&collection_path)?;
+        Self::register_tokenizers(&index, &schema_manager)?;
 
-        // Create index writer
-        let writer = index.writer(heap_size)?;
+        // Create index writer and reader
+        let writer = index.writer_with_num_threads(num_threads, heap_size)?;
+        let reader = Self::build_reader(&index)?;
 
         let now = Utc::now();
 
@@ -45,10 +132,16 @@ impl Collection {
             name,
             schema_manager,
             index,
-            writer: Arc::new(RwLock::new(writer)),
+            writer: Arc::new(RwLock::new(Some(writer))),
+            reader: Arc::new(reader),
             data_path: collection_path,
+            heap_size,
+            uncommitted_docs: Arc::new(AtomicUsize::new(0)),
             created_at: now,
             updated_at: Arc::new(RwLock::new(now)),
+            index_size: Arc::default(),
+            write_counters: Arc::default(),
+            read_only: Arc::default(),
         };
 
         // Save schema definition to disk
@@ -58,7 +151,12 @@ impl Collection {
     }
 
     /// Open an existing collection
-    pub fn open<P: AsRef<Path>>(name: String, data_dir: P, heap_size: usize) -> Result<Self> {
+    pub fn open<P: AsRef<Path>>(
+        name: String,
+        data_dir: P,
+        heap_size: usize,
+        num_threads: usize,
+    ) -> Result<Self> {
         let collection_path = data_dir.as_ref().join(&name);
 
         if !collection_path.exists() {
@@ -68,15 +166,19 @@ impl Collection {
             )));
         }
 
-        // Load schema definition
+        // Load schema definition and open Tantivy index
         let schema_def = Self::load_schema_definition(&collection_path)?;
-        let schema_manager = Arc::new(SchemaManager::new(schema_def)?);
-
-        // Open Tantivy index
+        let (heap_size, num_threads) = Self::writer_budget(&schema_def, heap_size, num_threads);
         let index = Index::open_in_dir(&collection_path)?;
+        let schema_manager = Arc::new(SchemaManager::for_index_schema(
+            schema_def,
+            &index.schema(),
+        )?);
+        Self::register_tokenizers(&index, &schema_manager)?;
 
-        // Create index writer
-        let writer = index.writer(heap_size)?;
+        // Create index writer and reader
+        let writer = index.writer_with_num_threads(num_threads, heap_size)?;
+        let reader = Self::build_reader(&index)?;
 
         // Load metadata
         let metadata = Self::load_metadata(&collection_path)?;
@@ -85,40 +187,392 @@ impl Collection {
             name,
             schema_manager,
             index,
-            writer: Arc::new(RwLock::new(writer)),
+            writer: Arc::new(RwLock::new(Some(writer))),
+            reader: Arc::new(reader),
             data_path: collection_path,
+            heap_size,
+            uncommitted_docs: Arc::new(AtomicUsize::new(0)),
             created_at: metadata.created_at,
             updated_at: Arc::new(RwLock::new(metadata.updated_at)),
+            index_size: Arc::default(),
+            write_counters: Arc::default(),
+            read_only: Arc::new(AtomicBool::new(metadata.read_only)),
         })
     }
 
+    /// Writer heap size and thread count, applying the schema's heap size override
+    ///
+    /// The heap may be too small for the engine's thread count, so the threads
+    /// are capped to what it can supply.
+    fn writer_budget(
+        schema_def: &SchemaDefinition,
+        heap_size: usize,
+        num_threads: usize,
+    ) -> (usize, usize) {
+        let heap_size = schema_def.heap_size.unwrap_or(heap_size);
+        (
+            heap_size,
+            num_threads.min(heap_size / MIN_HEAP_SIZE_PER_THREAD).max(1),
+        )
+    }
+
+    /// Register the tokenizers Tantivy does not provide out of the box
+    ///
+    /// `simple` splits on non-alphanumeric characters and lowercases, without
+    /// the long-token filter of `default`. The `STEMMERS` cover languages other
+    /// than English the way `en_stem` does, and `keyword_lowercase` indexes
+    /// normalized keyword fields. The schema's custom tokenizers are registered
+    /// as well, so they must be in place before documents are added.
+    fn register_tokenizers(index: &Index, schema_manager: &SchemaManager) -> Result<()> {
+        index.tokenizers().register("simple", simple_analyzer());
+        index
+            .tokenizers()
+            .register(KEYWORD_LOWERCASE_TOKENIZER, keyword_lowercase_analyzer());
+        for (name, language) in STEMMERS {
+            index
+                .tokenizers()
+                .register(name, stemming_analyzer(*language));
+        }
+        for (name, analyzer) in schema_manager.custom_tokenizers()? {
+            index.tokenizers().register(&name, analyzer);
+        }
+
+        // Fields with stop words index through their own copy of their tokenizer
+        for (field_name, field_type) in &schema_manager.schema_definition().fields {
+            if let FieldType::Text {
+                tokenizer,
+                stop_words: Some(words),
+                ..
+            } = field_type
+                && !words.is_empty()
+            {
+                let analyzer = index.tokenizers().get(tokenizer).ok_or_else(|| {
+                    SearchEngineError::SchemaError(format!("Unknown tokenizer '{}'", tokenizer))
+                })?;
+                index.tokenizers().register(
+                    &field_tokenizer_name(field_name, tokenizer, Some(words)),
+                    with_stop_words(analyzer, words),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the reader shared by searches on this collection
+    ///
+    /// The reader is reloaded explicitly on `commit`, so it uses a manual
+    /// reload policy rather than watching the index directory.
+    fn build_reader(index: &Index) -> Result<IndexReader> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        Ok(reader)
+    }
+
+    /// Lock the index writer for exclusive use
+    ///
+    /// A thread that panicked while holding the writer may have left it half
+    /// way through an operation, so a poisoned lock is reported as an error
+    /// rather than recovered. A closed collection has no writer left to lock.
+    pub(crate) fn lock_writer(&self) -> Result<WriterGuard<'_>> {
+        let writer = self.writer_slot()?;
+        if writer.is_none() {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' is closed",
+                self.name
+            )));
+        }
+        Ok(WriterGuard(writer))
+    }
+
+    fn writer_slot(&self) -> Result<RwLockWriteGuard<'_, Option<IndexWriter>>> {
+        self.writer.write().map_err(|_| {
+            SearchEngineError::IndexError(format!(
+                "Index writer of collection '{}' is poisoned by a panicked thread",
+                self.name
+            ))
+        })
+    }
+
+    /// Commit pending changes and release the index writer
+    ///
+    /// The writer is shared by every clone of the collection, so afterwards
+    /// none of them can write and the directory's writer lock is free for a
+    /// new writer. Closing an already closed collection does nothing.
+    pub(crate) fn close(&self) -> Result<()> {
+        let mut slot = self.writer_slot()?;
+        if let Some(writer) = slot.as_mut() {
+            writer.commit()?;
+        }
+        if let Some(writer) = slot.take() {
+            writer.wait_merging_threads()?;
+        }
+        Ok(())
+    }
+
+    /// Record that the collection changed now
+    ///
+    /// The timestamp is always valid, so a poisoned lock is recovered.
+    fn touch(&self) {
+        *self
+            .updated_at
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Utc::now();
+    }
+
+    /// Whether the collection has been frozen against writes
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Make the collection read-only, committing pending changes first
+    ///
+    /// Searches keep working; adding, updating and deleting documents fail
+    /// with a `CollectionError`. The flag is kept in `metadata.json`, so the
+    /// collection stays frozen when it is reopened.
+    pub fn freeze(&self) -> Result<()> {
+        self.commit()?;
+        self.read_only.store(true, Ordering::Relaxed);
+        self.save_metadata()
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' is read-only",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
     /// Add a document to the collection
     pub fn add_document(&self, doc: IndexDocument) -> Result<()> {
-        let mut tantivy_doc = tantivy::schema::document::TantivyDocument::default();
+        self.ensure_writable()?;
+        let tantivy_doc = self.to_tantivy_document(&doc)?;
+
+        // Add document to index
+        {
+            let writer = self.lock_writer()?;
+            writer.add_document(tantivy_doc)?;
+            self.uncommitted_docs.fetch_add(1, Ordering::Relaxed);
+        }
+        self.write_counters
+            .documents_indexed
+            .fetch_add(1, Ordering::Relaxed);
+
+        self.touch();
+
+        Ok(())
+    }
+
+    /// Update a document by ID
+    pub fn update_document(&self, doc: IndexDocument) -> Result<()> {
+        self.ensure_writable()?;
+        let id_field = self.schema_manager.id_field();
+        let term = tantivy::Term::from_field_text(id_field, &doc.id);
+
+        let tantivy_doc = self.to_tantivy_document(&doc)?;
+
+        // Update document in index
+        {
+            let writer = self.lock_writer()?;
+            writer.delete_term(term);
+            writer.add_document(tantivy_doc)?;
+            self.uncommitted_docs.fetch_add(1, Ordering::Relaxed);
+        }
+        self.write_counters
+            .documents_indexed
+            .fetch_add(1, Ordering::Relaxed);
+
+        self.touch();
+
+        Ok(())
+    }
+
+    /// Update a document, failing if no document with its ID exists
+    ///
+    /// Unlike `update_document`, this never inserts a new document. Existence
+    /// is checked against committed documents only.
+    pub fn update_existing_document(&self, doc: IndexDocument) -> Result<()> {
+        if !self.contains_document(&doc.id)? {
+            return Err(SearchEngineError::DocumentNotFound { id: doc.id });
+        }
+        self.update_document(doc)
+    }
+
+    /// Apply a patch to every committed document matching a query
+    ///
+    /// Matching documents are read back from the doc store, the patched fields
+    /// replace their values, and the documents are reindexed under their IDs in
+    /// one writer session. Fields missing from the patch keep their stored
+    /// values, so a field that is neither stored nor patched is rejected rather
+    /// than silently dropped. Returns the number of documents updated.
+    pub fn update_by_query(
+        &self,
+        query: &dyn Query,
+        patch: &HashMap<String, FieldValue>,
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+        let schema = self.index.schema();
+        let schema_manager = &self.schema_manager;
+        let unstored = schema_manager
+            .schema_definition()
+            .fields
+            .keys()
+            .filter(|name| !patch.contains_key(*name))
+            .find(|name| {
+                let field = schema_manager
+                    .get_field(name)
+                    .or_else(|| schema_manager.get_geo_fields(name).map(|(lat, _)| lat));
+                field.is_some_and(|field| !schema.get_field_entry(field).is_stored())
+            });
+        if let Some(name) = unstored {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Field '{}' is not stored, so documents of collection '{}' cannot be updated by query",
+                name, self.name
+            )));
+        }
+
+        // Build every patched document before writing, so an invalid patch changes nothing
+        let searcher = self.reader.searcher();
+        let id_field = schema_manager.id_field();
+        let mut updates = Vec::new();
+        for address in searcher.search(query, &DocSetCollector)? {
+            let stored: TantivyDocument = searcher.doc(address)?;
+            let id = stored
+                .get_first(id_field)
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| SearchEngineError::IndexError("Document ID not found".to_string()))?
+                .to_string();
+            let mut fields = schema_manager.document_from_tantivy(&stored, None)?;
+            // Boosts live in a fast field, not in the doc store
+            if schema_manager.boost_field().is_some()
+                && let Some(boost) = searcher
+                    .segment_reader(address.segment_ord)
+                    .fast_fields()
+                    .f64(BOOST_FIELD)?
+                    .first(address.doc_id)
+            {
+                fields.insert(BOOST_FIELD.to_string(), FieldValue::F64(boost));
+            }
+            fields.extend(
+                patch
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+
+            let doc = IndexDocument { id, fields };
+            let term = tantivy::Term::from_field_text(id_field, &doc.id);
+            updates.push((term, self.to_tantivy_document(&doc)?));
+        }
+
+        let updated = updates.len();
+        {
+            let writer = self.lock_writer()?;
+            for (term, tantivy_doc) in updates {
+                writer.delete_term(term);
+                writer.add_document(tantivy_doc)?;
+            }
+            self.uncommitted_docs.fetch_add(updated, Ordering::Relaxed);
+        }
+        self.write_counters
+            .documents_indexed
+            .fetch_add(updated as u64, Ordering::Relaxed);
+
+        self.touch();
+
+        Ok(updated)
+    }
+
+    /// Check whether a committed document with the given ID exists
+    pub fn contains_document(&self, doc_id: &str) -> Result<bool> {
+        let term = tantivy::Term::from_field_text(self.schema_manager.id_field(), doc_id);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        let count = self.reader.searcher().search(&query, &Count)?;
+        Ok(count > 0)
+    }
+
+    /// Convert a document into a Tantivy document, keyed on the primary key field
+    fn to_tantivy_document(&self, doc: &IndexDocument) -> Result<TantivyDocument> {
+        self.schema_manager.validate_required_fields(doc)?;
+        let mut tantivy_doc = TantivyDocument::default();
 
         // Add document ID
-        let id_field = self
-            .schema_manager
-            .get_field("_id")
-            .ok_or_else(|| SearchEngineError::IndexError("ID field not found".to_string()))?;
-        tantivy_doc.add_text(id_field, doc.id.clone());
+        let id_field_name = self.schema_manager.id_field_name();
+        tantivy_doc.add_text(self.schema_manager.id_field(), doc.id.clone());
 
         // Add document fields
         for (field_name, field_value) in &doc.fields {
+            // Document boosts are stored in a dedicated fast field
+            if field_name == BOOST_FIELD {
+                let boost_field = self.schema_manager.boost_field().ok_or_else(|| {
+                    SearchEngineError::SchemaError(format!(
+                        "Collection '{}' does not support document boosts",
+                        self.name
+                    ))
+                })?;
+                // Boosts multiply f32 scores, so they are kept at f32 precision
+                // in the f64 column Tantivy provides
+                match field_value {
+                    FieldValue::F64(boost) if (*boost as f32).is_finite() => {
+                        tantivy_doc.add_f64(boost_field, f64::from(*boost as f32))
+                    }
+                    _ => {
+                        return Err(SearchEngineError::SchemaError(format!(
+                            "Field '{}' must be a finite f32 value",
+                            BOOST_FIELD
+                        )));
+                    }
+                }
+                continue;
+            }
+
+            // The primary key is taken from the document ID
+            if field_name == id_field_name {
+                match field_value {
+                    FieldValue::Text(value) if *value == doc.id => continue,
+                    _ => {
+                        return Err(SearchEngineError::SchemaError(format!(
+                            "Primary key field '{}' must match the document ID '{}'",
+                            field_name, doc.id
+                        )));
+                    }
+                }
+            }
+
             // Validate field value
             self.schema_manager
                 .validate_field_value(field_name, field_value)?;
 
+            // Geo points are split over their latitude and longitude fields
+            if let FieldValue::GeoPoint { lat, lon } = field_value {
+                let (lat_field, lon_field) = self
+                    .schema_manager
+                    .get_geo_fields(field_name)
+                    .ok_or_else(|| SearchEngineError::FieldNotFound {
+                        field: field_name.to_string(),
+                    })?;
+                tantivy_doc.add_f64(lat_field, *lat);
+                tantivy_doc.add_f64(lon_field, *lon);
+                continue;
+            }
+
             let field = self.schema_manager.get_field(field_name).ok_or_else(|| {
-                SearchEngineError::SchemaError(format!(
-                    "Field '{}' not found in schema",
-                    field_name
-                ))
+                SearchEngineError::FieldNotFound {
+                    field: field_name.to_string(),
+                }
             })?;
 
             match field_value {
                 FieldValue::Text(s) => tantivy_doc.add_text(field, s),
+                FieldValue::PreTokenized(tokens) => {
+                    tantivy_doc.add_pre_tokenized_text(field, pre_tokenized_string(tokens))
+                }
                 FieldValue::I64(i) => tantivy_doc.add_i64(field, *i),
+                FieldValue::U64(u) => tantivy_doc.add_u64(field, *u),
+                FieldValue::Bool(b) => tantivy_doc.add_bool(field, *b),
                 FieldValue::F64(f) => tantivy_doc.add_f64(field, *f),
                 FieldValue::Date(d) => tantivy_doc
                     .add_date(field, tantivy::DateTime::from_timestamp_secs(d.timestamp())),
@@ -129,132 +583,283 @@ impl Collection {
                     tantivy_doc.add_facet(field, facet)
                 }
                 FieldValue::Bytes(b) => tantivy_doc.add_bytes(field, b),
-                // _ => {
-                //     return Err(SearchEngineError::IndexError(format!(
-                //         "Unsupported value type for field '{}'",
-                //         field_name
-                //     )));
-                // }
+                FieldValue::Json(object) => tantivy_doc
+                    .add_field_value(field, &tantivy::schema::OwnedValue::from(object.clone())),
+                FieldValue::GeoPoint { .. } => unreachable!("geo points are added above"),
             }
         }
 
-        // Add document to index
+        Ok(tantivy_doc)
+    }
+
+    /// Delete a document by ID
+    pub fn delete_document(&self, doc_id: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let id_field = self.schema_manager.id_field();
+        let term = tantivy::Term::from_field_text(id_field, doc_id);
+
         {
-            let writer = self.writer.write().unwrap();
-            writer.add_document(tantivy_doc)?;
+            let writer = self.lock_writer()?;
+            writer.delete_term(term);
+            self.uncommitted_docs.fetch_add(1, Ordering::Relaxed);
         }
 
-        // Update timestamp
-        *self.updated_at.write().unwrap() = Utc::now();
+        self.touch();
 
         Ok(())
     }
 
-    /// Update a document by ID
-    pub fn update_document(&self, doc: IndexDocument) -> Result<()> {
-        let id_field = self
-            .schema_manager
-            .get_field("_id")
-            .ok_or_else(|| SearchEngineError::IndexError("ID field not found".to_string()))?;
+    /// Delete a document by ID, failing if it does not exist
+    ///
+    /// Existence is checked against committed documents only.
+    pub fn delete_existing_document(&self, doc_id: &str) -> Result<()> {
+        if !self.contains_document(doc_id)? {
+            return Err(SearchEngineError::DocumentNotFound {
+                id: doc_id.to_string(),
+            });
+        }
+        self.delete_document(doc_id)
+    }
 
-        let term = tantivy::Term::from_field_text(id_field, &doc.id);
+    /// Commit changes to the index
+    pub fn commit(&self) -> Result<()> {
+        {
+            let mut writer = self.lock_writer()?;
+            let started = Instant::now();
+            writer.commit()?;
+            self.record_commit(started.elapsed());
+            // Writes hold the writer lock too, so none are missed by the reset
+            self.uncommitted_docs.store(0, Ordering::Relaxed);
+        }
+        self.invalidate_index_size();
 
-        let mut tantivy_doc = tantivy::schema::document::TantivyDocument::default();
-        tantivy_doc.add_text(id_field, doc.id.clone());
+        // Reload the shared reader so searches see the new commit
+        self.reader.reload()?;
 
-        // Add document fields
-        for (field_name, field_value) in &doc.fields {
-            self.schema_manager
-                .validate_field_value(field_name, field_value)?;
+        // Update timestamp and save metadata
+        self.touch();
+        self.save_metadata()?;
 
-            let field = self.schema_manager.get_field(field_name).ok_or_else(|| {
-                SearchEngineError::SchemaError(format!(
-                    "Field '{}' not found in schema",
-                    field_name
-                ))
-            })?;
+        Ok(())
+    }
 
-            let tantivy_value = self
-                .schema_manager
-                .field_value_to_tantivy(field_name, field_value)?;
-
-            match tantivy_value {
-                tantivy::schema::OwnedValue::Str(s) => tantivy_doc.add_text(field, s),
-                tantivy::schema::OwnedValue::I64(i) => tantivy_doc.add_i64(field, i),
-                tantivy::schema::OwnedValue::F64(f) => tantivy_doc.add_f64(field, f),
-                tantivy::schema::OwnedValue::Date(d) => tantivy_doc.add_date(field, d),
-                tantivy::schema::OwnedValue::Facet(f) => tantivy_doc.add_facet(field, f),
-                tantivy::schema::OwnedValue::Bytes(b) => tantivy_doc.add_bytes(field, &b),
-                _ => {
-                    return Err(SearchEngineError::IndexError(format!(
-                        "Unsupported value type for field '{}'",
-                        field_name
-                    )));
-                }
+    /// Make pending changes searchable, without a full commit's bookkeeping
+    ///
+    /// Tantivy only searches committed segments, so this still commits the
+    /// writer, but it does nothing when no changes are pending and leaves the
+    /// update time and metadata file to the next full `commit`.
+    pub fn soft_commit(&self) -> Result<()> {
+        {
+            let mut writer = self.lock_writer()?;
+            if self.uncommitted_docs.load(Ordering::Relaxed) == 0 {
+                return Ok(());
             }
+            let started = Instant::now();
+            writer.commit()?;
+            self.record_commit(started.elapsed());
+            self.uncommitted_docs.store(0, Ordering::Relaxed);
         }
+        self.invalidate_index_size();
 
-        // Update document in index
-        {
-            let writer = self.writer.write().unwrap();
-            writer.delete_term(term);
-            writer.add_document(tantivy_doc)?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Merge all committed segments into one
+    ///
+    /// Uncommitted documents are not included. Blocks until the merge is
+    /// done, then reloads the reader so searches use the merged segment.
+    pub fn merge_segments(&self) -> Result<()> {
+        let segment_ids = self.index.searchable_segment_ids()?;
+        if segment_ids.len() < 2 {
+            return Ok(());
         }
 
-        // Update timestamp
-        *self.updated_at.write().unwrap() = Utc::now();
+        // Only hold the writer lock while scheduling the merge
+        let merge = self.lock_writer()?.merge(&segment_ids);
+        merge.wait()?;
+        self.invalidate_index_size();
 
+        self.reader.reload()?;
         Ok(())
     }
 
-    /// Delete a document by ID
-    pub fn delete_document(&self, doc_id: &str) -> Result<()> {
-        let id_field = self
-            .schema_manager
-            .get_field("_id")
-            .ok_or_else(|| SearchEngineError::IndexError("ID field not found".to_string()))?;
-
-        let term = tantivy::Term::from_field_text(id_field, doc_id);
+    /// Read the index files searches need, so the first query is fast
+    ///
+    /// Reloads the reader, then opens the term dictionary and field norms of
+    /// every indexed field and the column of every fast field in each segment.
+    /// Opening them reads their files, which warms the OS page cache; the
+    /// opened handles themselves are dropped.
+    pub fn warmup(&self) -> Result<()> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let schema = self.index.schema();
 
-        {
-            let writer = self.writer.write().unwrap();
-            writer.delete_term(term);
+        for segment_reader in searcher.segment_readers() {
+            for (field, field_entry) in schema.fields() {
+                if field_entry.is_indexed() {
+                    segment_reader.inverted_index(field)?;
+                    segment_reader.fieldnorms_readers().get_field(field)?;
+                }
+                if field_entry.is_fast() {
+                    for handle in segment_reader
+                        .fast_fields()
+                        .dynamic_column_handles(field_entry.name())?
+                    {
+                        handle.open()?;
+                    }
+                }
+            }
         }
 
-        // Update timestamp
-        *self.updated_at.write().unwrap() = Utc::now();
-
         Ok(())
     }
 
-    /// Commit changes to the index
-    pub fn commit(&self) -> Result<()> {
+    /// Copy the committed documents into a new collection with `schema_def`
+    ///
+    /// The new collection is created under `data_dir` with this collection's
+    /// name and creation time. Documents are rebuilt from their stored values,
+    /// so every field must be stored unless the collection is empty; fields
+    /// missing from the new schema are dropped and new fields stay absent.
+    pub(crate) fn reindex_into<P: AsRef<Path>>(
+        &self,
+        schema_def: SchemaDefinition,
+        data_dir: P,
+        heap_size: usize,
+        num_threads: usize,
+    ) -> Result<()> {
+        let searcher = self.reader.searcher();
+        let old_schema = self.index.schema();
+        if searcher.num_docs() > 0
+            && let Some((_, entry)) = old_schema
+                .fields()
+                .find(|(_, entry)| entry.name() != BOOST_FIELD && !entry.is_stored())
         {
-            let mut writer = self.writer.write().unwrap();
-            writer.commit()?;
+            return Err(SearchEngineError::SchemaError(format!(
+                "Field '{}' is not stored, so the documents of collection '{}' cannot be reindexed",
+                entry.name(),
+                self.name
+            )));
         }
 
-        // Reload searcher
-        let reader = self
+        let enable_compression = self.index.settings().docstore_compression != Compressor::None;
+        let mut target = Self::create(
+            self.name.clone(),
+            schema_def,
+            data_dir,
+            heap_size,
+            num_threads,
+            enable_compression,
+        )?;
+        target.created_at = self.created_at;
+        let new_schema = target.index.schema();
+
+        {
+            let writer = target.lock_writer()?;
+            for segment_reader in searcher.segment_readers() {
+                let store_reader = segment_reader.get_store_reader(0)?;
+                // Boosts live in a fast field, not in the doc store
+                let boosts = segment_reader.fast_fields().f64(BOOST_FIELD).ok();
+                let new_boost_field = target.schema_manager.boost_field();
+
+                for doc_id in segment_reader.doc_ids_alive() {
+                    let old_doc: TantivyDocument = store_reader.get(doc_id)?;
+                    let mut new_doc = TantivyDocument::default();
+                    for (field, value) in old_doc.field_values() {
+                        if let Ok(new_field) =
+                            new_schema.get_field(old_schema.get_field_name(field))
+                        {
+                            new_doc.add_field_value(new_field, value);
+                        }
+                    }
+                    if let (Some(boosts), Some(boost_field)) = (&boosts, new_boost_field)
+                        && let Some(boost) = boosts.first(doc_id)
+                    {
+                        new_doc.add_f64(boost_field, boost);
+                    }
+                    writer.add_document(new_doc)?;
+                }
+            }
+        }
+
+        target.commit()
+    }
+
+    /// Write a `.tar.gz` snapshot of the committed collection to `dest`
+    ///
+    /// The archive holds `meta.json`, the files of the committed segments,
+    /// `schema.json` and `metadata.json` under a top-level directory named after
+    /// the collection. Tantivy's meta lock is held while archiving so that a
+    /// concurrent merge cannot garbage-collect the segment files being copied.
+    pub fn backup<P: AsRef<Path>>(&self, dest: P) -> Result<()> {
+        self.commit()?;
+
+        let _meta_lock = self
             .index
-            .reader_builder()
-            .reload_policy(ReloadPolicy::Manual)
-            .try_into()?;
-        reader.reload()?;
+            .directory()
+            .acquire_lock(&META_LOCK)
+            .map_err(tantivy::TantivyError::from)?;
+        let metas = self.index.load_metas()?;
 
-        // Update timestamp and save metadata
-        *self.updated_at.write().unwrap() = Utc::now();
-        self.save_metadata()?;
+        let archive = std::fs::File::create(dest)?;
+        let mut builder = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+        let root = Path::new(&self.name);
+
+        let mut meta_json = serde_json::to_vec_pretty(&metas)?;
+        meta_json.push(b'\n');
+        let mut header = tar::Header::new_gnu();
+        header.set_size(meta_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, root.join("meta.json"), meta_json.as_slice())?;
+
+        for segment in &metas.segments {
+            for file in segment.list_files() {
+                let path = self.data_path.join(&file);
+                // Not every segment component exists, e.g. `.del` without deletes
+                if path.exists() {
+                    builder.append_path_with_name(&path, root.join(&file))?;
+                }
+            }
+        }
+        for file in ["schema.json", "metadata.json"] {
+            builder.append_path_with_name(self.data_path.join(file), root.join(file))?;
+        }
 
+        builder.into_inner()?.finish()?;
         Ok(())
     }
 
+    /// Documents indexed and commits made since the collection was opened
+    pub fn index_metrics(&self) -> IndexMetrics {
+        let counters = &self.write_counters;
+        let commits = counters.commits.load(Ordering::Relaxed);
+        IndexMetrics {
+            documents_indexed: counters.documents_indexed.load(Ordering::Relaxed),
+            commits,
+            last_commit_micros: (commits > 0)
+                .then(|| counters.last_commit_micros.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn record_commit(&self, duration: Duration) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.write_counters
+            .last_commit_micros
+            .store(micros, Ordering::Relaxed);
+        self.write_counters.commits.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Get collection statistics
     pub fn get_stats(&self) -> Result<CollectionStats> {
-        let reader = self.index.reader()?;
-        let searcher = reader.searcher();
+        let searcher = self.reader.searcher();
 
         let num_docs = searcher.num_docs() as usize;
+        let num_deleted_docs = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.num_deleted_docs() as usize)
+            .sum();
 
         // Calculate index size (approximate)
         let index_size = self.calculate_index_size()?;
@@ -263,11 +868,56 @@ impl Collection {
             name: self.name.clone(),
             document_count: num_docs,
             index_size_bytes: index_size,
+            num_segments: searcher.segment_readers().len(),
+            num_deleted_docs,
+            heap_size_bytes: self.heap_size,
+            commit_opstamp: self.index.load_metas()?.opstamp,
             created_at: self.created_at,
-            updated_at: *self.updated_at.read().unwrap(),
+            updated_at: *self
+                .updated_at
+                .read()
+                .unwrap_or_else(PoisonError::into_inner),
         })
     }
 
+    /// Rename a collection's directory, along with the name recorded in its
+    /// `schema.json` and `metadata.json`
+    ///
+    /// The collection must be closed first, so no writer holds the directory's
+    /// lock while it moves. The directory is renamed back if the files cannot
+    /// be rewritten.
+    pub(crate) fn rename_on_disk<P: AsRef<Path>>(
+        data_dir: P,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<()> {
+        let old_path = data_dir.as_ref().join(old_name);
+        let new_path = data_dir.as_ref().join(new_name);
+        std::fs::rename(&old_path, &new_path)?;
+
+        let rewrite = || -> Result<()> {
+            let mut schema_def = Self::load_schema_definition(&new_path)?;
+            schema_def.name = new_name.to_string();
+            std::fs::write(
+                new_path.join("schema.json"),
+                serde_json::to_string_pretty(&schema_def)?,
+            )?;
+
+            let mut metadata = Self::load_metadata(&new_path)?;
+            metadata.name = new_name.to_string();
+            std::fs::write(
+                new_path.join("metadata.json"),
+                serde_json::to_string_pretty(&metadata)?,
+            )?;
+            Ok(())
+        };
+        if let Err(e) = rewrite() {
+            std::fs::rename(&new_path, &old_path)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
     /// Save schema definition to disk
     fn save_schema_definition(&self) -> Result<()> {
         let schema_path = self.data_path.join("schema.json");
@@ -290,7 +940,11 @@ impl Collection {
         let metadata = CollectionMetadata {
             name: self.name.clone(),
             created_at: self.created_at,
-            updated_at: *self.updated_at.read().unwrap(),
+            updated_at: *self
+                .updated_at
+                .read()
+                .unwrap_or_else(PoisonError::into_inner),
+            read_only: self.is_read_only(),
         };
         let metadata_json = serde_json::to_string_pretty(&metadata)?;
         std::fs::write(metadata_path, metadata_json)?;
@@ -313,6 +967,7 @@ impl Collection {
                     .to_string(),
                 created_at: now,
                 updated_at: now,
+                read_only: false,
             });
         }
 
@@ -321,7 +976,7 @@ impl Collection {
         Ok(metadata)
     }
 
-    /// Calculate approximate index size
+    /// Approximate index size, walking the directory when the cached size is stale
     fn calculate_index_size(&self) -> Result<u64> {
         fn dir_size(path: &Path) -> std::io::Result<u64> {
             let mut size = 0;
@@ -341,8 +996,28 @@ impl Collection {
             Ok(size)
         }
 
-        let total_size = dir_size(&self.data_path)?;
-        Ok(total_size)
+        let mut cache = self
+            .index_size
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((size, measured_at)) = cache.measured
+            && measured_at.elapsed() < INDEX_SIZE_TTL
+        {
+            return Ok(size);
+        }
+
+        let size = dir_size(&self.data_path)?;
+        cache.measured = Some((size, Instant::now()));
+        cache.walks += 1;
+        Ok(size)
+    }
+
+    /// Forget the cached index size, so the next stats call measures it again
+    fn invalidate_index_size(&self) {
+        self.index_size
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .measured = None;
     }
 }
 
@@ -352,4 +1027,676 @@ struct CollectionMetadata {
     name: String,
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    read_only: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchEngine;
+    use crate::types::{FieldType, QueryExpression, SearchQuery, TokenizerDefinition};
+    use std::collections::HashMap;
+    use tantivy::schema::Value;
+    use tempfile::TempDir;
+
+    fn sku_schema() -> SchemaDefinition {
+        let mut fields = HashMap::new();
+        fields.insert(
+            "sku".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "keyword".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        fields.insert(
+            "name".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        SchemaDefinition {
+            name: "products".to_string(),
+            fields,
+            primary_key: Some("sku".to_string()),
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
+        }
+    }
+
+    fn named_doc(id: &str, name: &str) -> IndexDocument {
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), FieldValue::Text(name.to_string()));
+        IndexDocument {
+            id: id.to_string(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_open_schema_with_bare_facet() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema_def = sku_schema();
+        schema_def.fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: false,
+                max_depth: None,
+            },
+        );
+        let collection = Collection::create(
+            "products".to_string(),
+            schema_def,
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        let mut doc = named_doc("A1", "widget");
+        doc.fields.insert(
+            "category".to_string(),
+            FieldValue::Facet("/tools".to_string()),
+        );
+        collection.add_document(doc).unwrap();
+        collection.close().unwrap();
+        drop(collection);
+
+        // Facets were a unit variant before they could be stored
+        let baseline = r#"{
+            "name": "products",
+            "fields": {
+                "sku": { "Text": { "stored": true, "indexed": true, "tokenizer": "keyword" } },
+                "name": { "Text": { "stored": true, "indexed": true, "tokenizer": "default" } },
+                "category": "Facet"
+            },
+            "primary_key": "sku"
+        }"#;
+        std::fs::write(
+            temp_dir.path().join("products").join("schema.json"),
+            baseline,
+        )
+        .unwrap();
+
+        let collection =
+            Collection::open("products".to_string(), temp_dir.path(), 15_000_000, 1).unwrap();
+        assert_eq!(
+            collection.schema_manager.schema_definition().fields["category"],
+            FieldType::Facet {
+                stored: false,
+                max_depth: None,
+            }
+        );
+        assert_eq!(collection.reader.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_update_by_primary_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        assert!(collection.schema_manager.get_field("_id").is_none());
+
+        collection
+            .add_document(named_doc("A1", "old name"))
+            .unwrap();
+        collection.add_document(named_doc("B2", "other")).unwrap();
+        collection.commit().unwrap();
+        collection
+            .update_document(named_doc("A1", "new name"))
+            .unwrap();
+        collection.commit().unwrap();
+
+        let searcher = collection.reader.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+
+        let sku_field = collection.schema_manager.id_field();
+        let name_field = collection.schema_manager.get_field("name").unwrap();
+        let query = tantivy::query::TermQuery::new(
+            tantivy::Term::from_field_text(sku_field, "A1"),
+            tantivy::schema::IndexRecordOption::Basic,
+        );
+        let hits = searcher
+            .search(&query, &tantivy::collector::TopDocs::with_limit(10))
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let doc: TantivyDocument = searcher.doc(hits[0].1).unwrap();
+        let name = doc.get_first(name_field).and_then(|v| v.as_str());
+        assert_eq!(name, Some("new name"));
+    }
+
+    #[test]
+    fn test_update_id_with_separators() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema = sku_schema();
+        schema.primary_key = None;
+        let collection = Collection::create(
+            "products".to_string(),
+            schema,
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+
+        // The IDs share tokens, which a tokenized ID field would match on
+        for id in ["abc-def-123", "abc-def-456", "abc"] {
+            collection.add_document(named_doc(id, "old name")).unwrap();
+        }
+        collection.commit().unwrap();
+        collection
+            .update_document(named_doc("abc-def-123", "new name"))
+            .unwrap();
+        collection.commit().unwrap();
+
+        let searcher = collection.reader.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        let id_field = collection.schema_manager.id_field();
+        let name_field = collection.schema_manager.get_field("name").unwrap();
+        let hits = searcher
+            .search(
+                &tantivy::query::AllQuery,
+                &tantivy::collector::TopDocs::with_limit(10),
+            )
+            .unwrap();
+        let mut names: Vec<(String, String)> = hits
+            .into_iter()
+            .map(|(_, address)| {
+                let doc: TantivyDocument = searcher.doc(address).unwrap();
+                let text = |field| {
+                    doc.get_first(field)
+                        .and_then(|v| v.as_str())
+                        .unwrap()
+                        .to_string()
+                };
+                (text(id_field), text(name_field))
+            })
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                ("abc".to_string(), "old name".to_string()),
+                ("abc-def-123".to_string(), "new name".to_string()),
+                ("abc-def-456".to_string(), "old name".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_size_cached_until_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        let walks = || collection.index_size.lock().unwrap().walks;
+
+        let first = collection.get_stats().unwrap();
+        let second = collection.get_stats().unwrap();
+        assert_eq!(walks(), 1);
+        assert_eq!(first.index_size_bytes, second.index_size_bytes);
+
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+        collection.commit().unwrap();
+        let third = collection.get_stats().unwrap();
+        assert_eq!(walks(), 2);
+        assert!(third.index_size_bytes > first.index_size_bytes);
+    }
+
+    #[test]
+    fn test_index_metrics_count_commits() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        assert_eq!(collection.index_metrics(), IndexMetrics::default());
+
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+        collection
+            .update_document(named_doc("A1", "gadget"))
+            .unwrap();
+        collection.commit().unwrap();
+        let metrics = collection.index_metrics();
+        assert_eq!(metrics.documents_indexed, 2);
+        assert_eq!(metrics.commits, 1);
+        assert!(metrics.last_commit_micros.is_some());
+
+        collection.commit().unwrap();
+        assert_eq!(collection.index_metrics().commits, 2);
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema = sku_schema();
+        schema.required = vec!["sku".to_string(), "name".to_string()];
+        let collection = Collection::create(
+            "products".to_string(),
+            schema,
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+
+        let missing_name = IndexDocument {
+            id: "B2".to_string(),
+            fields: HashMap::new(),
+        };
+        let err = collection.add_document(missing_name).unwrap_err();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg)
+                if msg == "Document 'B2' is missing required field 'name'"
+        ));
+
+        // The primary key is required even when not listed
+        let err = collection
+            .add_document(named_doc("", "gadget"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg) if msg == "Document is missing its ID field 'sku'"
+        ));
+
+        let mut undeclared = sku_schema();
+        undeclared.required = vec!["color".to_string()];
+        assert!(SchemaManager::validate(&undeclared).is_err());
+    }
+
+    #[test]
+    fn test_merge_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+
+        // One segment per commit; too few for the default merge policy to merge
+        for i in 0..5 {
+            collection
+                .add_document(named_doc(&format!("A{}", i), "widget"))
+                .unwrap();
+            collection.commit().unwrap();
+        }
+        assert_eq!(collection.reader.searcher().segment_readers().len(), 5);
+
+        collection.merge_segments().unwrap();
+
+        let searcher = collection.reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 5);
+    }
+
+    #[test]
+    fn test_stats_report_segments_and_deletes() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+        collection.add_document(named_doc("B2", "gadget")).unwrap();
+        collection.commit().unwrap();
+        collection.delete_document("A1").unwrap();
+        collection.commit().unwrap();
+
+        let stats = collection.get_stats().unwrap();
+        assert_eq!(stats.document_count, 1);
+        assert_eq!(stats.num_segments, 1);
+        assert_eq!(stats.num_deleted_docs, 1);
+        assert_eq!(stats.heap_size_bytes, 15_000_000);
+        assert!(stats.commit_opstamp > 0);
+    }
+
+    #[test]
+    fn test_poisoned_writer_returns_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            15_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+        collection.commit().unwrap();
+
+        let poisoner = collection.clone();
+        let panicked = std::thread::spawn(move || {
+            let _writer = poisoner.writer.write().unwrap();
+            panic!("panic while holding the writer");
+        })
+        .join();
+        assert!(panicked.is_err());
+
+        assert!(matches!(
+            collection.add_document(named_doc("B2", "gadget")),
+            Err(SearchEngineError::IndexError(_))
+        ));
+        assert!(matches!(
+            collection.delete_document("A1"),
+            Err(SearchEngineError::IndexError(_))
+        ));
+        assert!(matches!(
+            collection.commit(),
+            Err(SearchEngineError::IndexError(_))
+        ));
+
+        // Committed documents stay readable
+        assert_eq!(collection.get_stats().unwrap().document_count, 1);
+    }
+
+    #[test]
+    fn test_docstore_compression_setting() {
+        let temp_dir = TempDir::new().unwrap();
+
+        for (name, enable_compression, expected) in [
+            ("compressed", true, Compressor::Lz4),
+            ("uncompressed", false, Compressor::None),
+        ] {
+            Collection::create(
+                name.to_string(),
+                sku_schema(),
+                temp_dir.path(),
+                15_000_000,
+                1,
+                enable_compression,
+            )
+            .unwrap();
+
+            // Reopen from disk to check the persisted settings and round-trip a document
+            let collection =
+                Collection::open(name.to_string(), temp_dir.path(), 15_000_000, 1).unwrap();
+            assert_eq!(collection.index.settings().docstore_compression, expected);
+
+            collection.add_document(named_doc("A1", "widget")).unwrap();
+            collection.commit().unwrap();
+
+            let searcher = collection.reader.searcher();
+            let doc: TantivyDocument = searcher.doc(tantivy::DocAddress::new(0, 0)).unwrap();
+            let fields = collection
+                .schema_manager
+                .document_from_tantivy(&doc, None)
+                .unwrap();
+            assert!(matches!(&fields["name"], FieldValue::Text(name) if name == "widget"));
+        }
+    }
+
+    #[test]
+    fn test_writer_num_threads() {
+        let temp_dir = TempDir::new().unwrap();
+        let create = |name: &str, heap_size: usize, num_threads: usize| {
+            Collection::create(
+                name.to_string(),
+                sku_schema(),
+                temp_dir.path(),
+                heap_size,
+                num_threads,
+                true,
+            )
+        };
+
+        // Each writer thread needs at least 15MB of the heap, so threads the
+        // heap cannot supply are dropped
+        assert!(create("two_threads", 30_000_000, 2).is_ok());
+        assert!(create("capped_threads", 20_000_000, 2).is_ok());
+        assert!(create("one_thread", 20_000_000, 1).is_ok());
+        assert!(create("heap_too_small", 10_000_000, 1).is_err());
+    }
+
+    #[test]
+    fn test_schema_heap_size_override() {
+        let temp_dir = TempDir::new().unwrap();
+        let create = |name: &str, heap_size: Option<usize>| {
+            let mut schema = sku_schema();
+            schema.heap_size = heap_size;
+            Collection::create(
+                name.to_string(),
+                schema,
+                temp_dir.path(),
+                50_000_000,
+                2,
+                true,
+            )
+        };
+
+        // The override caps the engine's two threads to what 15MB supports
+        let reference = create("reference", Some(15_000_000)).unwrap();
+        let logs = create("logs", Some(120_000_000)).unwrap();
+        assert_eq!(reference.heap_size, 15_000_000);
+        assert_eq!(logs.heap_size, 120_000_000);
+        drop((reference, logs));
+
+        for (name, heap_size) in [("reference", 15_000_000), ("logs", 120_000_000)] {
+            let collection =
+                Collection::open(name.to_string(), temp_dir.path(), 50_000_000, 2).unwrap();
+            assert_eq!(collection.heap_size, heap_size);
+        }
+
+        let err = create("tiny", Some(1_000_000)).err().unwrap();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg) if msg.contains("heap_size must be at least")
+        ));
+    }
+
+    #[test]
+    fn test_simple_tokenizer_registered() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema = sku_schema();
+        schema.fields.insert(
+            "name".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "simple".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        let collection = Collection::create(
+            "products".to_string(),
+            schema,
+            temp_dir.path(),
+            50_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+
+        collection
+            .add_document(named_doc("A1", "Blue-Widget"))
+            .unwrap();
+        collection.commit().unwrap();
+
+        let name = collection.schema_manager.get_field("name").unwrap();
+        let searcher = collection.reader.searcher();
+        let term = tantivy::Term::from_field_text(name, "widget");
+        assert_eq!(searcher.doc_freq(&term).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_custom_ngram_tokenizer() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema = sku_schema();
+        schema.tokenizers.insert(
+            "ngram".to_string(),
+            TokenizerDefinition::ngram(2, 3).lowercase(),
+        );
+        schema.fields.insert(
+            "name".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "ngram".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        let collection = Collection::create(
+            "products".to_string(),
+            schema,
+            temp_dir.path(),
+            50_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        collection
+            .add_document(named_doc("A1", "Headphones"))
+            .unwrap();
+        collection.commit().unwrap();
+
+        let matches = |collection: &Collection, text: &str| {
+            let query = SearchQuery::new(
+                "products",
+                QueryExpression::FullText {
+                    field: "name".to_string(),
+                    text: text.to_string(),
+                    boost: None,
+                },
+            );
+            SearchEngine::new(collection.clone())
+                .search(query)
+                .unwrap()
+                .total_hits
+        };
+        assert_eq!(matches(&collection, "phon"), 1);
+        assert_eq!(matches(&collection, "HEAD"), 1);
+        assert_eq!(matches(&collection, "xyz"), 0);
+
+        // The tokenizer is registered again when the collection is reopened
+        drop(collection);
+        let collection =
+            Collection::open("products".to_string(), temp_dir.path(), 50_000_000, 1).unwrap();
+        assert_eq!(matches(&collection, "phon"), 1);
+    }
+
+    #[test]
+    fn test_french_stemmer() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut schema = sku_schema();
+        schema.fields.insert(
+            "name".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "fr_stem".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        let collection = Collection::create(
+            "products".to_string(),
+            schema,
+            temp_dir.path(),
+            50_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        collection.add_document(named_doc("A1", "courir")).unwrap();
+        collection.commit().unwrap();
+
+        let query = SearchQuery::new(
+            "products",
+            QueryExpression::FullText {
+                field: "name".to_string(),
+                text: "courez".to_string(),
+                boost: None,
+            },
+        );
+        let result = SearchEngine::new(collection).search(query).unwrap();
+        assert_eq!(result.total_hits, 1);
+    }
+
+    #[test]
+    fn test_strict_and_lenient_update_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection = Collection::create(
+            "products".to_string(),
+            sku_schema(),
+            temp_dir.path(),
+            50_000_000,
+            1,
+            true,
+        )
+        .unwrap();
+        collection.add_document(named_doc("A1", "widget")).unwrap();
+        collection.commit().unwrap();
+
+        // Strict operations reject unknown IDs
+        assert!(matches!(
+            collection.update_existing_document(named_doc("B2", "gadget")),
+            Err(SearchEngineError::DocumentNotFound { id }) if id == "B2"
+        ));
+        assert!(matches!(
+            collection.delete_existing_document("B2"),
+            Err(SearchEngineError::DocumentNotFound { .. })
+        ));
+        collection
+            .update_existing_document(named_doc("A1", "sprocket"))
+            .unwrap();
+        collection.commit().unwrap();
+        assert_eq!(collection.get_stats().unwrap().document_count, 1);
+
+        // Lenient operations insert or ignore unknown IDs
+        collection
+            .update_document(named_doc("B2", "gadget"))
+            .unwrap();
+        collection.delete_document("C3").unwrap();
+        collection.commit().unwrap();
+        assert!(collection.contains_document("B2").unwrap());
+
+        collection.delete_existing_document("A1").unwrap();
+        collection.commit().unwrap();
+        assert!(!collection.contains_document("A1").unwrap());
+    }
 }
@@ -2,32 +2,56 @@ use crate::collection::Collection;
 use crate::error::{Result, SearchEngineError};
 use crate::search::SearchEngine;
 use crate::types::{
-    CollectionStats, EngineConfig, IndexDocument, SchemaDefinition, SearchQuery, SearchResult,
+    CollectionStats, EngineConfig, FieldCapabilities, FieldType, FieldValue, IndexDocument,
+    IndexMetrics, QueryExpression, ReopenBehavior, SchemaDefinition, SearchHit, SearchQuery,
+    SearchResult, TermStats,
 };
-use std::collections::HashMap;
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use tokio::sync::Notify;
 use tokio::time::{Duration, interval};
 
+/// File in the data directory that maps aliases to collection names
+const ALIASES_FILE: &str = "aliases.json";
+
+/// Hits fetched per page by `search_stream` when the query sets no limit
+const STREAM_PAGE_SIZE: usize = 1000;
+
 /// Main search engine that manages multiple collections
 pub struct RustSearchEngine {
     config: EngineConfig,
     collections: Arc<RwLock<HashMap<String, Collection>>>,
+    /// Alternative names resolving to a collection, persisted in `aliases.json`
+    aliases: RwLock<HashMap<String, String>>,
     auto_commit_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Signals the auto-commit task to finish after its current tick
+    shutdown: Arc<Notify>,
+    /// Collections found on disk at startup that failed to open, with the error
+    failed_collections: Vec<(String, String)>,
 }
 
 impl RustSearchEngine {
     /// Create a new search engine with the given configuration
     pub fn new(config: EngineConfig) -> Result<Self> {
+        Self::validate_config(&config)?;
+
         // Create data directory if it doesn't exist
         std::fs::create_dir_all(&config.data_dir)?;
 
         let collections = Arc::new(RwLock::new(HashMap::new()));
+        let aliases = RwLock::new(Self::load_aliases(&config.data_dir)?);
 
         let mut engine = Self {
             config,
             collections,
+            aliases,
             auto_commit_handle: None,
+            shutdown: Arc::new(Notify::new()),
+            failed_collections: Vec::new(),
         };
 
         // Load existing collections
@@ -38,26 +62,55 @@ impl RustSearchEngine {
 
     /// Start the search engine with auto-commit functionality
     pub async fn start(&mut self) -> Result<()> {
+        if self.config.warmup_on_start {
+            self.warmup_all()?;
+        }
+
         // Start auto-commit task
         let collections = self.collections.clone();
         let commit_interval = self.config.commit_interval_ms;
+        let nrt_refresh = self.config.nrt_refresh_ms;
+        let shutdown = self.shutdown.clone();
 
         let handle = tokio::spawn(async move {
+            let mut refresh_interval =
+                nrt_refresh.map(|refresh_ms| interval(Duration::from_millis(refresh_ms)));
             let mut interval = interval(Duration::from_millis(commit_interval));
 
             loop {
-                interval.tick().await;
-
-                // Commit all collections
-                let collections_guard = collections.read().unwrap();
-                for collection in collections_guard.values() {
-                    if let Err(e) = collection.commit() {
-                        tracing::warn!(
-                            "Failed to auto-commit collection '{}': {}",
-                            collection.name,
-                            e
-                        );
+                // Only wait on the shutdown signal between commits, so a commit
+                // in progress always runs to completion
+                let full_commit = tokio::select! {
+                    _ = interval.tick() => true,
+                    _ = async { refresh_interval.as_mut().unwrap().tick().await },
+                        if refresh_interval.is_some() => false,
+                    _ = shutdown.notified() => break,
+                };
+
+                // Commit all collections outside the lock, so creating or
+                // dropping collections is not blocked by slow commits
+                let snapshot: Vec<Collection> =
+                    collections.read().unwrap().values().cloned().collect();
+                // Commits block on disk I/O, so they run off the async workers
+                let committed = tokio::task::spawn_blocking(move || {
+                    for collection in snapshot.iter().filter(|c| !c.is_read_only()) {
+                        let committed = if full_commit {
+                            collection.commit()
+                        } else {
+                            collection.soft_commit()
+                        };
+                        if let Err(e) = committed {
+                            tracing::warn!(
+                                "Failed to auto-commit collection '{}': {}",
+                                collection.name,
+                                e
+                            );
+                        }
                     }
+                })
+                .await;
+                if let Err(e) = committed {
+                    tracing::warn!("Auto-commit ended abnormally: {}", e);
                 }
             }
         });
@@ -72,9 +125,16 @@ impl RustSearchEngine {
     }
 
     /// Stop the search engine
+    ///
+    /// Waits for the auto-commit task to finish its current commit, then makes
+    /// a final commit. Dropping the engine afterwards only commits collections
+    /// changed since.
     pub async fn stop(&mut self) -> Result<()> {
         if let Some(handle) = self.auto_commit_handle.take() {
-            handle.abort();
+            self.shutdown.notify_one();
+            if let Err(e) = handle.await {
+                tracing::warn!("Auto-commit task ended abnormally: {}", e);
+            }
         }
 
         // Final commit for all collections
@@ -86,9 +146,10 @@ impl RustSearchEngine {
 
     /// Create a new collection with the given schema
     pub fn create_collection(&self, name: String, schema_def: SchemaDefinition) -> Result<()> {
+        validate_collection_name(&name)?;
         let mut collections = self.collections.write().unwrap();
 
-        if collections.contains_key(&name) {
+        if collections.contains_key(&name) || self.aliases.read().unwrap().contains_key(&name) {
             return Err(SearchEngineError::CollectionError(format!(
                 "Collection '{}' already exists",
                 name
@@ -100,6 +161,8 @@ impl RustSearchEngine {
             schema_def,
             &self.config.data_dir,
             self.config.default_heap_size,
+            self.config.num_threads,
+            self.config.enable_compression,
         )?;
 
         collections.insert(name.clone(), collection);
@@ -108,8 +171,43 @@ impl RustSearchEngine {
         Ok(())
     }
 
+    /// Open a collection from disk, or return the handle if it is already open
+    ///
+    /// A collection is never opened twice, since that would create a second
+    /// `IndexWriter` on the same directory.
+    pub fn open_collection(&self, name: &str) -> Result<Collection> {
+        validate_collection_name(name)?;
+        let mut collections = self.collections.write().unwrap();
+
+        if let Some(collection) = collections.get(name) {
+            return match self.config.reopen_behavior {
+                ReopenBehavior::ReuseExisting => Ok(collection.clone()),
+                ReopenBehavior::Error => Err(SearchEngineError::CollectionError(format!(
+                    "Collection '{}' is already open",
+                    name
+                ))),
+            };
+        }
+
+        let collection = Collection::open(
+            name.to_string(),
+            &self.config.data_dir,
+            self.config.default_heap_size,
+            self.config.num_threads,
+        )?;
+
+        collections.insert(name.to_string(), collection.clone());
+
+        tracing::info!("Opened collection: {}", name);
+        Ok(collection)
+    }
+
     /// Drop a collection
+    ///
+    /// An alias drops the collection it resolves to. Every alias of the
+    /// dropped collection is removed along with it.
     pub fn drop_collection(&self, name: &str) -> Result<()> {
+        let name = &self.resolve_alias(name);
         let mut collections = self.collections.write().unwrap();
 
         if let Some(collection) = collections.remove(name) {
@@ -122,14 +220,416 @@ impl RustSearchEngine {
                 std::fs::remove_dir_all(collection_path)?;
             }
 
+            let mut aliases = self.aliases.write().unwrap();
+            let alias_count = aliases.len();
+            aliases.retain(|_, target| target != name);
+            if aliases.len() != alias_count {
+                self.save_aliases(&aliases)?;
+            }
+
             tracing::info!("Dropped collection: {}", name);
             Ok(())
         } else {
-            Err(SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
+            Err(SearchEngineError::CollectionNotFound {
+                name: name.to_string(),
+            })
+        }
+    }
+
+    /// Rename a collection, on disk and in memory
+    ///
+    /// Pending changes are committed first. The collection's schema takes the
+    /// new name as well, and aliases of the collection follow it to the new
+    /// name. Renaming to the name of an existing collection is an error.
+    pub fn rename_collection(&self, old_name: &str, new_name: &str) -> Result<()> {
+        validate_collection_name(new_name)?;
+        let mut collections = self.collections.write().unwrap();
+        let old = collections.get(old_name).cloned().ok_or_else(|| {
+            SearchEngineError::CollectionNotFound {
+                name: old_name.to_string(),
+            }
+        })?;
+
+        let data_dir = Path::new(&self.config.data_dir);
+        if collections.contains_key(new_name)
+            || self.aliases.read().unwrap().contains_key(new_name)
+            || data_dir.join(new_name).exists()
+        {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' already exists",
+                new_name
+            )));
+        }
+
+        old.commit()?;
+        // Every clone shares the writer, so closing releases the directory's
+        // writer lock before it moves and no stale clone can keep writing
+        old.close()?;
+
+        let renamed = Collection::rename_on_disk(data_dir, old_name, new_name).and_then(|()| {
+            Collection::open(
+                new_name.to_string(),
+                &self.config.data_dir,
+                old.heap_size,
+                self.config.num_threads,
+            )
+            .or_else(|e| {
+                Collection::rename_on_disk(data_dir, new_name, old_name)?;
+                Err(e)
+            })
+        });
+        let renamed = match renamed {
+            Ok(collection) => collection,
+            Err(e) => {
+                let reopened = Collection::open(
+                    old_name.to_string(),
+                    &self.config.data_dir,
+                    old.heap_size,
+                    self.config.num_threads,
+                )?;
+                collections.insert(old_name.to_string(), reopened);
+                return Err(e);
+            }
+        };
+        collections.remove(old_name);
+        collections.insert(new_name.to_string(), renamed);
+
+        let mut aliases = self.aliases.write().unwrap();
+        let mut retargeted = false;
+        for target in aliases.values_mut().filter(|target| *target == old_name) {
+            *target = new_name.to_string();
+            retargeted = true;
+        }
+        if retargeted {
+            self.save_aliases(&aliases)?;
+        }
+
+        tracing::info!("Renamed collection '{}' to '{}'", old_name, new_name);
+        Ok(())
+    }
+
+    /// Create an alias that resolves to the `target` collection
+    ///
+    /// Searches, document writes and commits accept the alias in place of the
+    /// collection name. An alias follows the rules for collection names, and
+    /// cannot share its name with a collection or another alias.
+    pub fn create_alias(&self, alias: &str, target: &str) -> Result<()> {
+        validate_collection_name(alias)?;
+        let collections = self.collections.read().unwrap();
+        let mut aliases = self.aliases.write().unwrap();
+
+        // Collections on disk that failed to load are shadowed too
+        if collections.contains_key(alias)
+            || aliases.contains_key(alias)
+            || Path::new(&self.config.data_dir).join(alias).exists()
+        {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection or alias '{}' already exists",
+                alias
+            )));
+        }
+        if !collections.contains_key(target) {
+            return Err(SearchEngineError::CollectionNotFound {
+                name: target.to_string(),
+            });
+        }
+
+        aliases.insert(alias.to_string(), target.to_string());
+        self.save_aliases(&aliases)?;
+
+        tracing::info!("Created alias '{}' for collection '{}'", alias, target);
+        Ok(())
+    }
+
+    /// Point an existing alias at another collection
+    ///
+    /// Each request resolves the alias once, so it sees either the old or the
+    /// new target, never a mix of both.
+    pub fn swap_alias(&self, alias: &str, new_target: &str) -> Result<()> {
+        let collections = self.collections.read().unwrap();
+        let mut aliases = self.aliases.write().unwrap();
+
+        if !aliases.contains_key(alias) {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Alias '{}' does not exist",
+                alias
+            )));
+        }
+        if !collections.contains_key(new_target) {
+            return Err(SearchEngineError::CollectionNotFound {
+                name: new_target.to_string(),
+            });
+        }
+
+        let old_target = aliases.insert(alias.to_string(), new_target.to_string());
+        if let Err(e) = self.save_aliases(&aliases) {
+            if let Some(old_target) = old_target {
+                aliases.insert(alias.to_string(), old_target);
+            }
+            return Err(e);
+        }
+
+        tracing::info!("Swapped alias '{}' to collection '{}'", alias, new_target);
+        Ok(())
+    }
+
+    /// Collection name an alias resolves to, or `name` itself if it is not an alias
+    fn resolve_alias(&self, name: &str) -> String {
+        self.aliases
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Load the alias registry from the data directory
+    fn load_aliases(data_dir: &str) -> Result<HashMap<String, String>> {
+        let aliases_path = Path::new(data_dir).join(ALIASES_FILE);
+        if !aliases_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let aliases_json = std::fs::read_to_string(aliases_path)?;
+        Ok(serde_json::from_str(&aliases_json)?)
+    }
+
+    /// Save the alias registry, replacing the previous file in one rename
+    fn save_aliases(&self, aliases: &HashMap<String, String>) -> Result<()> {
+        let data_dir = Path::new(&self.config.data_dir);
+        let staging_path = data_dir.join(format!(".{}", ALIASES_FILE));
+        std::fs::write(&staging_path, serde_json::to_string_pretty(aliases)?)?;
+        std::fs::rename(staging_path, data_dir.join(ALIASES_FILE))?;
+        Ok(())
+    }
+
+    /// Add a field to an existing collection
+    ///
+    /// Tantivy schemas are fixed once created, so the collection is reindexed
+    /// into a new index directory which then replaces the old one. Existing
+    /// documents have no value for the new field. Writes to the collection are
+    /// blocked while it is rebuilt.
+    pub fn add_field(
+        &self,
+        collection: &str,
+        field_name: &str,
+        field_type: FieldType,
+    ) -> Result<()> {
+        let mut collections = self.collections.write().unwrap();
+        let old = collections.get(collection).cloned().ok_or_else(|| {
+            SearchEngineError::CollectionNotFound {
+                name: collection.to_string(),
+            }
+        })?;
+
+        let mut schema_def = old.schema_manager.schema_definition().clone();
+        if schema_def.fields.contains_key(field_name)
+            || old.schema_manager.id_field_name() == field_name
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Field '{}' already exists in collection '{}'",
+                field_name, collection
+            )));
+        }
+        if old.is_read_only() {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' is read-only",
+                collection
+            )));
+        }
+        schema_def.fields.insert(field_name.to_string(), field_type);
+
+        self.rebuild_collection(&mut collections, &old, schema_def)?;
+
+        tracing::info!(
+            "Added field '{}' to collection '{}'",
+            field_name,
+            collection
+        );
+        Ok(())
+    }
+
+    /// Switch a text field to another tokenizer, reindexing its existing values
+    ///
+    /// The collection is rebuilt the same way as by `add_field`: documents are
+    /// copied from their stored values into a new index, so every field of the
+    /// collection must be stored unless it is empty. Writes to the collection
+    /// are blocked while it is rebuilt.
+    pub fn reanalyze_field(
+        &self,
+        collection: &str,
+        field: &str,
+        new_tokenizer: String,
+    ) -> Result<()> {
+        let mut collections = self.collections.write().unwrap();
+        let old = collections.get(collection).cloned().ok_or_else(|| {
+            SearchEngineError::CollectionNotFound {
+                name: collection.to_string(),
+            }
+        })?;
+        if old.is_read_only() {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' is read-only",
+                collection
+            )));
+        }
+
+        let mut schema_def = old.schema_manager.schema_definition().clone();
+        match schema_def.fields.get_mut(field) {
+            Some(FieldType::Text { tokenizer, .. }) => *tokenizer = new_tokenizer.clone(),
+            Some(_) => {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Field '{}' is not a text field",
+                    field
+                )));
+            }
+            None => {
+                return Err(SearchEngineError::FieldNotFound {
+                    field: field.to_string(),
+                });
+            }
+        }
+
+        self.rebuild_collection(&mut collections, &old, schema_def)?;
+
+        tracing::info!(
+            "Reanalyzed field '{}' of collection '{}' with tokenizer '{}'",
+            field,
+            collection,
+            new_tokenizer
+        );
+        Ok(())
+    }
+
+    /// Reindex a collection under `schema_def` and swap the new index in
+    ///
+    /// The old collection is closed first, so none of its clones can write
+    /// while its stored documents are copied. If rebuilding fails, it is
+    /// reopened unchanged.
+    fn rebuild_collection(
+        &self,
+        collections: &mut HashMap<String, Collection>,
+        old: &Collection,
+        schema_def: SchemaDefinition,
+    ) -> Result<()> {
+        old.commit()?;
+        old.close()?;
+
+        let rebuilt = self.reindex_and_swap(old, schema_def).or_else(|e| {
+            Collection::open(
+                old.name.clone(),
+                &self.config.data_dir,
+                old.heap_size,
+                self.config.num_threads,
+            )
+            .and_then(|reopened| {
+                collections.insert(old.name.clone(), reopened);
+                Err(e)
+            })
+        })?;
+        collections.insert(old.name.clone(), rebuilt);
+        Ok(())
+    }
+
+    fn reindex_and_swap(
+        &self,
+        old: &Collection,
+        schema_def: SchemaDefinition,
+    ) -> Result<Collection> {
+        // Build the new index next to the collections so swapping is a rename
+        let data_dir = Path::new(&self.config.data_dir);
+        let staging = tempfile::Builder::new()
+            .prefix(".reindex-")
+            .tempdir_in(data_dir)?;
+        old.reindex_into(
+            schema_def,
+            staging.path(),
+            self.config.default_heap_size,
+            self.config.num_threads,
+        )?;
+
+        // The old index moves into the staging directory and is removed with it
+        let collection_path = data_dir.join(&old.name);
+        let retired_path = staging.path().join(".retired");
+        std::fs::rename(&collection_path, &retired_path)?;
+        if let Err(e) = std::fs::rename(staging.path().join(&old.name), &collection_path) {
+            std::fs::rename(&retired_path, &collection_path)?;
+            return Err(e.into());
+        }
+
+        Collection::open(
+            old.name.clone(),
+            &self.config.data_dir,
+            self.config.default_heap_size,
+            self.config.num_threads,
+        )
+    }
+
+    /// Back up a collection into a `.tar.gz` archive at `dest`
+    ///
+    /// Pending changes are committed first, so the archive holds everything
+    /// added to the collection so far.
+    pub fn backup_collection(&self, name: &str, dest: &Path) -> Result<()> {
+        // Archive outside the collections lock so other collections stay usable
+        let collection = self.get_collection(name)?;
+
+        collection.backup(dest)?;
+        tracing::info!("Backed up collection '{}' to {}", name, dest.display());
+        Ok(())
+    }
+
+    /// Restore a collection from an archive written by `backup_collection`
+    ///
+    /// The collection keeps the name it was backed up under, which is returned.
+    /// Restoring over an existing collection is an error.
+    pub fn restore_collection(&self, archive: &Path) -> Result<String> {
+        let data_dir = Path::new(&self.config.data_dir);
+
+        // Unpack next to the collections so moving it into place is a rename
+        let staging = tempfile::Builder::new()
+            .prefix(".restore-")
+            .tempdir_in(data_dir)?;
+        let file = std::fs::File::open(archive)?;
+        tar::Archive::new(GzDecoder::new(file)).unpack(staging.path())?;
+
+        let entries = std::fs::read_dir(staging.path())?.collect::<std::io::Result<Vec<_>>>()?;
+        let name = match entries.as_slice() {
+            [entry] if entry.path().is_dir() => entry.file_name().to_string_lossy().to_string(),
+            _ => {
+                return Err(SearchEngineError::CollectionError(format!(
+                    "Backup archive '{}' must contain a single collection directory",
+                    archive.display()
+                )));
+            }
+        };
+
+        validate_collection_name(&name)?;
+        let mut collections = self.collections.write().unwrap();
+        let collection_path = data_dir.join(&name);
+        if collections.contains_key(&name) || collection_path.exists() {
+            return Err(SearchEngineError::CollectionError(format!(
+                "Collection '{}' already exists",
                 name
-            )))
+            )));
         }
+
+        std::fs::rename(staging.path().join(&name), &collection_path)?;
+        let collection = match Collection::open(
+            name.clone(),
+            &self.config.data_dir,
+            self.config.default_heap_size,
+            self.config.num_threads,
+        ) {
+            Ok(collection) => collection,
+            Err(e) => {
+                std::fs::remove_dir_all(&collection_path)?;
+                return Err(e);
+            }
+        };
+        collections.insert(name.clone(), collection);
+
+        tracing::info!("Restored collection '{}' from {}", name, archive.display());
+        Ok(name)
     }
 
     /// List all collections
@@ -138,39 +638,103 @@ impl RustSearchEngine {
         collections.keys().cloned().collect()
     }
 
+    /// Clone a collection's handle, holding the collections lock only for the lookup
+    ///
+    /// `Collection` shares its index, reader and writer through `Arc`s, so the
+    /// clone is cheap and read-only work on it does not block collections from
+    /// being created or dropped. Aliases resolve to their target collection.
+    fn get_collection(&self, name: &str) -> Result<Collection> {
+        let name = self.resolve_alias(name);
+        let collections = self.collections.read().unwrap();
+        collections
+            .get(&name)
+            .cloned()
+            .ok_or(SearchEngineError::CollectionNotFound { name })
+    }
+
     /// Get collection statistics
     pub fn get_collection_stats(&self, name: &str) -> Result<CollectionStats> {
-        let collections = self.collections.read().unwrap();
-        let collection = collections.get(name).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!("Collection '{}' not found", name))
-        })?;
+        let collection = self.get_collection(name)?;
 
         collection.get_stats()
     }
 
+    /// Get the schema definition a collection was created with
+    pub fn get_schema(&self, name: &str) -> Result<SchemaDefinition> {
+        let collection = self.get_collection(name)?;
+
+        Ok(collection.schema_manager.schema_definition().clone())
+    }
+
+    /// Get the stored, indexed and fast flags of every field in a collection
+    ///
+    /// Only fast fields can be sorted on and range-queried efficiently.
+    pub fn field_capabilities(&self, name: &str) -> Result<Vec<FieldCapabilities>> {
+        let collection = self.get_collection(name)?;
+
+        Ok(collection.schema_manager.field_capabilities())
+    }
+
     /// Get statistics for all collections
     pub fn get_all_stats(&self) -> Result<Vec<CollectionStats>> {
-        let collections = self.collections.read().unwrap();
+        let collections: Vec<Collection> =
+            self.collections.read().unwrap().values().cloned().collect();
         let mut stats = Vec::new();
 
-        for collection in collections.values() {
+        for collection in &collections {
             stats.push(collection.get_stats()?);
         }
 
         Ok(stats)
     }
 
+    /// Warm up every collection so first queries do not pay index loading costs
+    pub fn warmup_all(&self) -> Result<()> {
+        let collections: Vec<Collection> =
+            self.collections.read().unwrap().values().cloned().collect();
+
+        for collection in &collections {
+            collection.warmup()?;
+            tracing::debug!("Warmed up collection: {}", collection.name);
+        }
+
+        Ok(())
+    }
+
     /// Add a document to a collection
     pub fn add_document(&self, collection_name: &str, doc: IndexDocument) -> Result<()> {
+        let collection_name = &self.resolve_alias(collection_name);
         let collections = self.collections.read().unwrap();
         let collection = collections.get(collection_name).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
-                collection_name
-            ))
+            SearchEngineError::CollectionNotFound {
+                name: collection_name.to_string(),
+            }
         })?;
 
         collection.add_document(doc)?;
+        Self::commit_if_over_threshold(collection, self.config.commit_max_docs)?;
+
+        tracing::debug!("Added document to collection: {}", collection_name);
+        Ok(())
+    }
+
+    /// Add a document to a collection without blocking the async runtime
+    ///
+    /// Same as `add_document`, but the indexing work, and the commit when
+    /// `commit_max_docs` is reached, run on Tokio's blocking thread pool.
+    pub async fn add_document_async(
+        &self,
+        collection_name: &str,
+        doc: IndexDocument,
+    ) -> Result<()> {
+        let collection = self.get_collection(collection_name)?;
+        let commit_max_docs = self.config.commit_max_docs;
+
+        run_blocking(move || {
+            collection.add_document(doc)?;
+            Self::commit_if_over_threshold(&collection, commit_max_docs)
+        })
+        .await?;
 
         tracing::debug!("Added document to collection: {}", collection_name);
         Ok(())
@@ -178,15 +742,37 @@ impl RustSearchEngine {
 
     /// Update a document in a collection
     pub fn update_document(&self, collection_name: &str, doc: IndexDocument) -> Result<()> {
+        let collection_name = &self.resolve_alias(collection_name);
         let collections = self.collections.read().unwrap();
         let collection = collections.get(collection_name).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
-                collection_name
-            ))
+            SearchEngineError::CollectionNotFound {
+                name: collection_name.to_string(),
+            }
         })?;
 
         collection.update_document(doc)?;
+        Self::commit_if_over_threshold(collection, self.config.commit_max_docs)?;
+
+        tracing::debug!("Updated document in collection: {}", collection_name);
+        Ok(())
+    }
+
+    /// Update an existing document, failing if its ID is not in the collection
+    pub fn update_existing_document(
+        &self,
+        collection_name: &str,
+        doc: IndexDocument,
+    ) -> Result<()> {
+        let collection_name = &self.resolve_alias(collection_name);
+        let collections = self.collections.read().unwrap();
+        let collection = collections.get(collection_name).ok_or_else(|| {
+            SearchEngineError::CollectionNotFound {
+                name: collection_name.to_string(),
+            }
+        })?;
+
+        collection.update_existing_document(doc)?;
+        Self::commit_if_over_threshold(collection, self.config.commit_max_docs)?;
 
         tracing::debug!("Updated document in collection: {}", collection_name);
         Ok(())
@@ -194,46 +780,322 @@ impl RustSearchEngine {
 
     /// Delete a document from a collection
     pub fn delete_document(&self, collection_name: &str, doc_id: &str) -> Result<()> {
+        let collection_name = &self.resolve_alias(collection_name);
         let collections = self.collections.read().unwrap();
         let collection = collections.get(collection_name).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
-                collection_name
-            ))
+            SearchEngineError::CollectionNotFound {
+                name: collection_name.to_string(),
+            }
         })?;
 
         collection.delete_document(doc_id)?;
+        Self::commit_if_over_threshold(collection, self.config.commit_max_docs)?;
 
         tracing::debug!("Deleted document from collection: {}", collection_name);
         Ok(())
     }
 
-    /// Search documents in a collection
-    pub fn search(&self, query: SearchQuery) -> Result<SearchResult> {
+    /// Delete an existing document, failing if its ID is not in the collection
+    pub fn delete_existing_document(&self, collection_name: &str, doc_id: &str) -> Result<()> {
+        let collection_name = &self.resolve_alias(collection_name);
         let collections = self.collections.read().unwrap();
-        let collection = collections.get(&query.collection).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
-                query.collection
-            ))
+        let collection = collections.get(collection_name).ok_or_else(|| {
+            SearchEngineError::CollectionNotFound {
+                name: collection_name.to_string(),
+            }
         })?;
 
-        let search_engine = SearchEngine::new(collection.clone());
+        collection.delete_existing_document(doc_id)?;
+        Self::commit_if_over_threshold(collection, self.config.commit_max_docs)?;
+
+        tracing::debug!("Deleted document from collection: {}", collection_name);
+        Ok(())
+    }
+
+    /// Apply a patch to every committed document matching a query
+    ///
+    /// Fields the patch does not name keep their stored values. All matching
+    /// documents are reindexed in one writer session and become visible on the
+    /// next commit. Returns the number of documents updated.
+    pub fn update_by_query(
+        &self,
+        collection_name: &str,
+        query: &QueryExpression,
+        patch: HashMap<String, FieldValue>,
+    ) -> Result<usize> {
+        let collection = self.get_collection(collection_name)?;
+
+        let search_engine = SearchEngine::with_config(collection.clone(), &self.config);
+        let updated = search_engine.update_by_query(query, &patch)?;
+        Self::commit_if_over_threshold(&collection, self.config.commit_max_docs)?;
+
+        tracing::debug!(
+            "Updated {} documents by query in collection: {}",
+            updated,
+            collection.name
+        );
+        Ok(updated)
+    }
+
+    /// Search documents in a collection
+    pub fn search(&self, query: SearchQuery) -> Result<SearchResult> {
+        let collection = self.get_collection(&query.collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
         let result = search_engine.search(query)?;
 
         tracing::debug!("Search completed in {}ms", result.took_ms);
         Ok(result)
     }
 
+    /// Iterate over every hit of a query, fetching one page at a time
+    ///
+    /// Pages are fetched with `search_after` cursors, so only one page of hits
+    /// is held in memory however many documents match. The query's `limit` sets
+    /// the page size, defaulting to 1000 or `max_result_window` if smaller,
+    /// and a `search_after` cursor sets where the stream starts. Queries with
+    /// an `offset` or a `sort` are rejected. The stream ends after the first
+    /// error.
+    pub fn search_stream(
+        &self,
+        mut query: SearchQuery,
+    ) -> impl Iterator<Item = Result<SearchHit>> + '_ {
+        let unsupported = if query.offset.is_some_and(|offset| offset > 0) {
+            Some("offset")
+        } else if query.sort.is_some() {
+            Some("sort")
+        } else {
+            None
+        };
+        let mut error = unsupported.map(|option| {
+            SearchEngineError::QueryError(format!(
+                "search_stream cannot be combined with {}",
+                option
+            ))
+        });
+        query.limit = Some(
+            query
+                .limit
+                .unwrap_or(STREAM_PAGE_SIZE.min(self.config.max_result_window)),
+        );
+        let mut page = VecDeque::new();
+        let mut exhausted = false;
+
+        std::iter::from_fn(move || {
+            if let Some(error) = error.take() {
+                exhausted = true;
+                return Some(Err(error));
+            }
+            loop {
+                if let Some(hit) = page.pop_front() {
+                    return Some(Ok(hit));
+                }
+                if exhausted {
+                    return None;
+                }
+
+                match self.search(query.clone()) {
+                    Ok(result) => {
+                        match result.documents.last() {
+                            Some(last) => {
+                                query.search_after = Some((last.score, last.id.clone()));
+                                exhausted = !result.has_more;
+                            }
+                            None => exhausted = true,
+                        }
+                        page.extend(result.documents);
+                    }
+                    Err(e) => {
+                        exhausted = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Search several collections and merge their hits into one ranking
+    ///
+    /// Each collection is searched for its own top `limit` hits, which are then
+    /// merged by score; every hit records the collection it came from. Scores
+    /// use each collection's own term statistics. Collections missing a field
+    /// the query references are skipped with a warning, and `total_hits` only
+    /// counts the collections that were searched.
+    pub fn multi_search(
+        &self,
+        collections: &[String],
+        query: QueryExpression,
+        limit: usize,
+    ) -> Result<SearchResult> {
+        let start_time = std::time::Instant::now();
+        let mut total_hits = 0;
+        let mut documents = Vec::new();
+        let mut timed_out = false;
+
+        for name in collections {
+            let mut search_query = SearchQuery::new(name.clone(), query.clone());
+            search_query.limit = Some(limit);
+            match self.search(search_query) {
+                Ok(result) => {
+                    total_hits += result.total_hits;
+                    timed_out |= result.timed_out;
+                    documents.extend(result.documents);
+                }
+                Err(SearchEngineError::FieldNotFound { field }) => {
+                    tracing::warn!(
+                        "Skipping collection '{}' in multi-search: field '{}' not found",
+                        name,
+                        field
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        documents.sort_by(|a, b| b.score.total_cmp(&a.score));
+        documents.truncate(limit);
+
+        Ok(SearchResult {
+            total_hits,
+            has_more: total_hits > documents.len(),
+            documents,
+            took_ms: start_time.elapsed().as_millis() as u64,
+            facet_counts: HashMap::new(),
+            timed_out,
+            approximate: false,
+            limit,
+            offset: 0,
+        })
+    }
+
+    /// Count the documents matching a query without fetching them
+    pub fn count(&self, query: SearchQuery) -> Result<usize> {
+        let collection = self.get_collection(&query.collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.count(&query)
+    }
+
+    /// Get the `n` terms of an indexed text field that occur in the most documents
+    pub fn top_terms(&self, collection: &str, field: &str, n: usize) -> Result<Vec<(String, u64)>> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.top_terms(field, n)
+    }
+
+    /// Count the documents matching a query per fixed-width bucket of a fast numeric or date field
+    ///
+    /// See `SearchEngine::histogram`.
+    pub fn histogram(
+        &self,
+        collection: &str,
+        query: &QueryExpression,
+        field: &str,
+        interval: f64,
+    ) -> Result<Vec<(f64, u64)>> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.histogram(query, field, interval)
+    }
+
+    /// Get the document frequency and total occurrences of a term in a field
+    pub fn term_stats(&self, collection: &str, field: &str, term: &str) -> Result<TermStats> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.term_stats(field, term)
+    }
+
+    /// Complete a prefix with up to `limit` terms of a field, most frequent first
+    pub fn autocomplete(
+        &self,
+        collection: &str,
+        field: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.autocomplete(field, prefix, limit)
+    }
+
+    /// Suggest corrections for a misspelled term, ranked by document frequency
+    pub fn suggest(
+        &self,
+        collection: &str,
+        field: &str,
+        term: &str,
+        max_edits: u8,
+    ) -> Result<Vec<(String, u64)>> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.suggest(field, term, max_edits)
+    }
+
+    /// Tokens a collection's text field produces for some text
+    pub fn analyze(&self, collection_name: &str, field: &str, text: &str) -> Result<Vec<String>> {
+        let collection = self.get_collection(collection_name)?;
+        collection.schema_manager.analyze(field, text)
+    }
+
+    /// Explain how a document's score for a query is computed
+    pub fn explain(&self, query: SearchQuery, doc_id: &str) -> Result<String> {
+        let collection = self.get_collection(&query.collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.explain(&query, doc_id)
+    }
+
+    /// Explain why a document does not match a query
+    pub fn explain_no_match(
+        &self,
+        collection: &str,
+        query: &QueryExpression,
+        doc_id: &str,
+    ) -> Result<Vec<String>> {
+        let collection = self.get_collection(collection)?;
+
+        let search_engine = SearchEngine::with_config(collection, &self.config);
+        search_engine.explain_no_match(query, doc_id)
+    }
+
+    /// Commit a collection whose uncommitted documents reached `commit_max_docs`
+    fn commit_if_over_threshold(
+        collection: &Collection,
+        commit_max_docs: Option<usize>,
+    ) -> Result<()> {
+        if let Some(max_docs) = commit_max_docs
+            && collection.uncommitted_docs.load(Ordering::Relaxed) >= max_docs
+        {
+            collection.commit()?;
+            tracing::debug!(
+                "Committed collection '{}' after {} uncommitted documents",
+                collection.name,
+                max_docs
+            );
+        }
+        Ok(())
+    }
+
+    /// Freeze a collection, rejecting further writes while searches keep working
+    ///
+    /// Pending changes are committed first. Frozen collections are skipped by
+    /// auto-commit and stay frozen across restarts.
+    pub fn freeze_collection(&self, name: &str) -> Result<()> {
+        let collection = self.get_collection(name)?;
+        collection.freeze()?;
+
+        tracing::info!("Froze collection: {}", collection.name);
+        Ok(())
+    }
+
     /// Commit changes for a specific collection
     pub fn commit_collection(&self, collection_name: &str) -> Result<()> {
-        let collections = self.collections.read().unwrap();
-        let collection = collections.get(collection_name).ok_or_else(|| {
-            SearchEngineError::CollectionError(format!(
-                "Collection '{}' not found",
-                collection_name
-            ))
-        })?;
+        let collection = self.get_collection(collection_name)?;
 
         collection.commit()?;
 
@@ -241,6 +1103,34 @@ impl RustSearchEngine {
         Ok(())
     }
 
+    /// Merge a collection's committed segments into one
+    pub fn optimize_collection(&self, collection_name: &str) -> Result<()> {
+        let collection = self.get_collection(collection_name)?;
+        collection.merge_segments()?;
+
+        tracing::debug!("Optimized collection: {}", collection_name);
+        Ok(())
+    }
+
+    /// Make a collection's pending changes searchable without a full commit
+    pub fn refresh_collection(&self, collection_name: &str) -> Result<()> {
+        let collection = self.get_collection(collection_name)?;
+        collection.soft_commit()
+    }
+
+    /// Commit a collection without blocking the async runtime
+    ///
+    /// Same as `commit_collection`, but the commit runs on Tokio's blocking
+    /// thread pool.
+    pub async fn commit_async(&self, collection_name: &str) -> Result<()> {
+        let collection = self.get_collection(collection_name)?;
+
+        run_blocking(move || collection.commit()).await?;
+
+        tracing::debug!("Committed collection: {}", collection_name);
+        Ok(())
+    }
+
     /// Commit changes for all collections
     pub async fn commit_all(&self) -> Result<()> {
         let collections = self.collections.read().unwrap();
@@ -264,38 +1154,69 @@ impl RustSearchEngine {
             return Ok(());
         }
 
+        let mut collection_names = Vec::new();
         for entry in std::fs::read_dir(data_dir)? {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_dir() {
+            // Check if this is a valid collection directory
+            if path.is_dir() && path.join("schema.json").exists() {
                 let collection_name = path
                     .file_name()
                     .and_then(|n| n.to_str())
                     .unwrap_or_default()
                     .to_string();
+                collection_names.push(collection_name);
+            }
+        }
 
-                // Check if this is a valid collection directory
-                let schema_path = path.join("schema.json");
-                if schema_path.exists() {
-                    match Collection::open(
-                        collection_name.clone(),
-                        &self.config.data_dir,
-                        self.config.default_heap_size,
-                    ) {
-                        Ok(collection) => {
-                            let mut collections = self.collections.write().unwrap();
-                            collections.insert(collection_name.clone(), collection);
-                            tracing::info!("Loaded existing collection: {}", collection_name);
-                        }
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to load collection '{}': {}",
-                                collection_name,
-                                e
+        // Open collections on a few worker threads, each taking the next
+        // unopened name, so startup does not grow linearly with their number
+        let workers = std::thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(collection_names.len());
+        let next = AtomicUsize::new(0);
+        let opened: Vec<(String, Result<Collection>)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut opened = Vec::new();
+                        while let Some(name) =
+                            collection_names.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let collection = Collection::open(
+                                name.clone(),
+                                &self.config.data_dir,
+                                self.config.default_heap_size,
+                                self.config.num_threads,
                             );
+                            opened.push((name.clone(), collection));
                         }
-                    }
+                        opened
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        let mut collections = self.collections.write().unwrap();
+        for (collection_name, collection) in opened {
+            match collection {
+                Ok(collection) => {
+                    tracing::info!("Loaded existing collection: {}", collection_name);
+                    collections.insert(collection_name, collection);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load collection '{}': {}", collection_name, e);
+                    self.failed_collections
+                        .push((collection_name, e.to_string()));
                 }
             }
         }
@@ -311,6 +1232,8 @@ impl RustSearchEngine {
     /// Update engine configuration (some settings require restart)
     pub fn update_config(&mut self, new_config: EngineConfig) -> Result<()> {
         // Validate new configuration
+        Self::validate_config(&new_config)?;
+
         if new_config.data_dir != self.config.data_dir {
             return Err(SearchEngineError::ConfigError(
                 "Cannot change data directory while engine is running".to_string(),
@@ -322,35 +1245,140 @@ impl RustSearchEngine {
         Ok(())
     }
 
+    /// Validate configuration values
+    fn validate_config(config: &EngineConfig) -> Result<()> {
+        if config.num_threads == 0 {
+            return Err(SearchEngineError::ConfigError(
+                "num_threads must be at least 1".to_string(),
+            ));
+        }
+
+        if !config.bm25_k1.is_finite() || config.bm25_k1 < 0.0 {
+            return Err(SearchEngineError::ConfigError(
+                "bm25_k1 must be a non-negative number".to_string(),
+            ));
+        }
+
+        if config.nrt_refresh_ms == Some(0) {
+            return Err(SearchEngineError::ConfigError(
+                "nrt_refresh_ms must be at least 1".to_string(),
+            ));
+        }
+
+        if config.max_result_window == 0 {
+            return Err(SearchEngineError::ConfigError(
+                "max_result_window must be at least 1".to_string(),
+            ));
+        }
+
+        if config.max_expansions == 0 {
+            return Err(SearchEngineError::ConfigError(
+                "max_expansions must be at least 1".to_string(),
+            ));
+        }
+
+        if config.commit_max_docs == Some(0) {
+            return Err(SearchEngineError::ConfigError(
+                "commit_max_docs must be at least 1".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&config.bm25_b) {
+            return Err(SearchEngineError::ConfigError(
+                "bm25_b must be between 0 and 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Collections found on disk at startup that failed to open
+    ///
+    /// Each entry is the collection name and the error that prevented it from
+    /// loading. These collections are not served until the engine is restarted.
+    pub fn failed_collections(&self) -> &[(String, String)] {
+        &self.failed_collections
+    }
+
     /// Health check for the search engine
     pub fn health_check(&self) -> Result<EngineHealth> {
         let collections = self.collections.read().unwrap();
         let mut collection_healths = Vec::new();
 
+        let mut documents_indexed = 0;
+        let mut commits = 0;
         for (name, collection) in collections.iter() {
             let stats = collection.get_stats()?;
+            let index_metrics = collection.index_metrics();
+            documents_indexed += index_metrics.documents_indexed;
+            commits += index_metrics.commits;
             collection_healths.push(CollectionHealth {
                 name: name.clone(),
                 status: "healthy".to_string(),
                 document_count: stats.document_count,
                 index_size_bytes: stats.index_size_bytes,
+                index_metrics,
+                error: None,
+            });
+        }
+        for (name, error) in &self.failed_collections {
+            collection_healths.push(CollectionHealth {
+                name: name.clone(),
+                status: "error".to_string(),
+                document_count: 0,
+                index_size_bytes: 0,
+                index_metrics: IndexMetrics::default(),
+                error: Some(error.clone()),
             });
         }
 
         Ok(EngineHealth {
             status: "healthy".to_string(),
             collections: collection_healths,
+            documents_indexed,
+            commits,
             uptime_ms: 0, // TODO: Track actual uptime
         })
     }
 }
 
+/// Check that a collection name is safe to use as a directory under `data_dir`
+///
+/// Only ASCII letters, digits, `-` and `_` are allowed, so a name can never
+/// contain a path separator, refer to a parent directory or collide with the
+/// hidden staging directories.
+fn validate_collection_name(name: &str) -> Result<()> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(is_safe) {
+        return Err(SearchEngineError::CollectionError(format!(
+            "Invalid collection name '{}': use only letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Run blocking index work on Tokio's blocking thread pool
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| SearchEngineError::IndexError(format!("Blocking index task failed: {}", e)))?
+}
+
 /// Engine health information
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct EngineHealth {
     pub status: String,
     pub collections: Vec<CollectionHealth>,
     pub uptime_ms: u64,
+    /// Documents indexed across all collections since they were opened
+    #[serde(default)]
+    pub documents_indexed: u64,
+    /// Commits across all collections since they were opened
+    #[serde(default)]
+    pub commits: u64,
 }
 
 /// Collection health information
@@ -360,6 +1388,11 @@ pub struct CollectionHealth {
     pub status: String,
     pub document_count: usize,
     pub index_size_bytes: u64,
+    #[serde(default)]
+    pub index_metrics: IndexMetrics,
+    /// Why the collection failed to load, for collections with status "error"
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 impl Drop for RustSearchEngine {
@@ -368,9 +1401,18 @@ impl Drop for RustSearchEngine {
             handle.abort();
         }
 
-        // Final commit for all collections
+        if !self.config.commit_on_drop {
+            tracing::info!("Discarding uncommitted changes on shutdown");
+            return;
+        }
+
+        // Final commit for collections with changes, so nothing committed by
+        // `stop` is committed twice but later writes are not lost
         let collections = self.collections.read().unwrap();
         for (name, collection) in collections.iter() {
+            if collection.uncommitted_docs.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
             if let Err(e) = collection.commit() {
                 tracing::error!(
                     "Failed to commit collection '{}' during shutdown: {}",
@@ -381,3 +1423,1191 @@ impl Drop for RustSearchEngine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EngineConfigBuilder;
+    use crate::schema_helpers;
+    use tempfile::TempDir;
+
+    fn test_engine(temp_dir: &TempDir) -> RustSearchEngine {
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .build()
+            .unwrap();
+        RustSearchEngine::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_reanalyze_field() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection(
+                "notes".to_string(),
+                schema_helpers::text_collection_schema("notes", &[("title", true, true)]),
+            )
+            .unwrap();
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("Running shoes".to_string()),
+        );
+        engine
+            .add_document(
+                "notes",
+                IndexDocument {
+                    id: "note-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("notes").unwrap();
+
+        let stemmed = || {
+            let query = QueryExpression::FullText {
+                field: "title".to_string(),
+                text: "runs".to_string(),
+                boost: None,
+            };
+            engine
+                .search(SearchQuery::new("notes", query))
+                .unwrap()
+                .total_hits
+        };
+        assert_eq!(stemmed(), 0);
+
+        engine
+            .reanalyze_field("notes", "title", "en_stem".to_string())
+            .unwrap();
+        assert_eq!(stemmed(), 1);
+        let collection = engine.open_collection("notes").unwrap();
+        assert!(matches!(
+            collection.schema_manager.schema_definition().fields["title"],
+            FieldType::Text { ref tokenizer, .. } if tokenizer == "en_stem"
+        ));
+
+        // An unknown tokenizer leaves the collection as it was
+        assert!(matches!(
+            engine.reanalyze_field("notes", "title", "no_such_stem".to_string()),
+            Err(SearchEngineError::SchemaError(_))
+        ));
+        assert_eq!(stemmed(), 1);
+        assert!(matches!(
+            engine.reanalyze_field("notes", "missing", "en_stem".to_string()),
+            Err(SearchEngineError::FieldNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unsafe_collection_names_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let config = EngineConfigBuilder::new()
+            .data_dir(&data_dir)
+            .build()
+            .unwrap();
+        let engine = RustSearchEngine::new(config).unwrap();
+
+        for name in ["../evil", "nested/posts", "back\\slash", "/abs", "..", ""] {
+            let created =
+                engine.create_collection(name.to_string(), schema_helpers::blog_post_schema());
+            assert!(
+                matches!(created, Err(SearchEngineError::CollectionError(_))),
+                "{:?} was accepted",
+                name
+            );
+        }
+        assert!(!temp_dir.path().join("evil").exists());
+
+        engine
+            .create_collection(
+                "blog-posts_2".to_string(),
+                schema_helpers::blog_post_schema(),
+            )
+            .unwrap();
+        assert!(data_dir.join("blog-posts_2").is_dir());
+        assert!(matches!(
+            engine.rename_collection("blog-posts_2", "../moved"),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_collections_in_parallel() {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let engine = test_engine(&temp_dir);
+            for i in 0..20 {
+                engine
+                    .create_collection(format!("posts-{}", i), schema_helpers::blog_post_schema())
+                    .unwrap();
+            }
+        }
+        std::fs::write(
+            temp_dir.path().join("posts-7").join("schema.json"),
+            "{ not json",
+        )
+        .unwrap();
+
+        let engine = test_engine(&temp_dir);
+        let mut loaded = engine.list_collections();
+        loaded.sort();
+        let mut expected: Vec<_> = (0..20)
+            .filter(|i| *i != 7)
+            .map(|i| format!("posts-{}", i))
+            .collect();
+        expected.sort();
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn test_corrupt_collection_reported_as_failed() {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let engine = test_engine(&temp_dir);
+            for name in ["posts", "broken"] {
+                engine
+                    .create_collection(name.to_string(), schema_helpers::blog_post_schema())
+                    .unwrap();
+            }
+        }
+        std::fs::write(
+            temp_dir.path().join("broken").join("meta.json"),
+            "{ not json",
+        )
+        .unwrap();
+
+        let engine = test_engine(&temp_dir);
+        assert_eq!(engine.list_collections(), vec!["posts".to_string()]);
+
+        let failed = engine.failed_collections();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "broken");
+        assert!(!failed[0].1.is_empty());
+
+        let health = engine.health_check().unwrap();
+        let broken = health
+            .collections
+            .iter()
+            .find(|collection| collection.name == "broken")
+            .unwrap();
+        assert_eq!(broken.status, "error");
+        assert_eq!(broken.error.as_deref(), Some(failed[0].1.as_str()));
+        let posts = health
+            .collections
+            .iter()
+            .find(|collection| collection.name == "posts")
+            .unwrap();
+        assert_eq!(posts.status, "healthy");
+        assert!(posts.error.is_none());
+    }
+
+    #[test]
+    fn test_commit_on_drop() {
+        for commit_on_drop in [true, false] {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .commit_on_drop(commit_on_drop)
+                .build()
+                .unwrap();
+            {
+                let engine = RustSearchEngine::new(config).unwrap();
+                engine
+                    .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+                    .unwrap();
+                let mut fields = HashMap::new();
+                fields.insert(
+                    "title".to_string(),
+                    crate::FieldValue::Text("pending".to_string()),
+                );
+                let doc = IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                };
+                engine.add_document("posts", doc).unwrap();
+            }
+
+            // A fresh engine only sees what was committed
+            let engine = test_engine(&temp_dir);
+            let stats = engine.get_collection_stats("posts").unwrap();
+            assert_eq!(stats.document_count, usize::from(commit_on_drop));
+        }
+    }
+
+    #[test]
+    fn test_open_collection_twice_reuses_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        let first = engine.open_collection("posts").unwrap();
+        let second = engine.open_collection("posts").unwrap();
+
+        assert_eq!(first.name, second.name);
+        assert!(Arc::ptr_eq(&first.writer, &second.writer));
+        assert_eq!(engine.list_collections().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stop_persists_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        engine.start().await.unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("Persisted".to_string()),
+        );
+        engine
+            .add_document(
+                "posts",
+                IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.stop().await.unwrap();
+        let uncommitted = engine.get_collection("posts").unwrap().uncommitted_docs;
+        assert_eq!(uncommitted.load(Ordering::Relaxed), 0);
+        drop(engine);
+
+        let engine = test_engine(&temp_dir);
+        let result = engine
+            .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(result.documents[0].id, "post-1");
+    }
+
+    #[tokio::test]
+    async fn test_write_after_stop_committed_on_drop() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        engine.start().await.unwrap();
+        engine.stop().await.unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("Written after stop".to_string()),
+        );
+        engine
+            .add_document(
+                "posts",
+                IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        drop(engine);
+
+        let engine = test_engine(&temp_dir);
+        let result = engine
+            .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(result.documents[0].id, "post-1");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_create_collection_during_auto_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .commit_interval_ms(1)
+            .build()
+            .unwrap();
+        let mut engine = RustSearchEngine::new(config).unwrap();
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        engine.start().await.unwrap();
+
+        // Commits run on the blocking pool, so collections are created while
+        // a commit is in flight
+        let created = tokio::time::timeout(Duration::from_secs(30), async {
+            for i in 0..10 {
+                let mut fields = HashMap::new();
+                fields.insert(
+                    "title".to_string(),
+                    crate::FieldValue::Text(format!("Post {}", i)),
+                );
+                engine
+                    .add_document(
+                        "posts",
+                        IndexDocument {
+                            id: i.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+                engine
+                    .create_collection(format!("extra_{}", i), schema_helpers::blog_post_schema())
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        })
+        .await;
+
+        assert!(created.is_ok(), "creating collections timed out");
+        assert_eq!(engine.list_collections().len(), 11);
+        engine.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_add_field_reindexes_collection() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection(
+                "notes".to_string(),
+                schema_helpers::text_collection_schema("notes", &[("title", true, true)]),
+            )
+            .unwrap();
+        let note = |id: &str, priority: Option<i64>| {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "title".to_string(),
+                crate::FieldValue::Text(format!("Note {}", id)),
+            );
+            if let Some(priority) = priority {
+                fields.insert("priority".to_string(), crate::FieldValue::I64(priority));
+            }
+            IndexDocument {
+                id: id.to_string(),
+                fields,
+            }
+        };
+        engine.add_document("notes", note("old-1", None)).unwrap();
+        engine.add_document("notes", note("old-2", None)).unwrap();
+
+        let priority = FieldType::I64 {
+            stored: true,
+            indexed: true,
+            fast: true,
+        };
+        engine
+            .add_field("notes", "priority", priority.clone())
+            .unwrap();
+        assert!(matches!(
+            engine.add_field("notes", "priority", priority),
+            Err(SearchEngineError::SchemaError(_))
+        ));
+
+        let result = engine
+            .search(SearchQuery::new("notes", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(result.total_hits, 2);
+        assert!(
+            result
+                .documents
+                .iter()
+                .all(|hit| !hit.fields.contains_key("priority") && hit.fields.contains_key("title"))
+        );
+
+        engine
+            .add_document("notes", note("new-1", Some(5)))
+            .unwrap();
+        engine.commit_collection("notes").unwrap();
+        let query = SearchQuery::new(
+            "notes",
+            QueryExpression::Term {
+                field: "priority".to_string(),
+                value: crate::FieldValue::I64(5),
+            },
+        );
+        let result = engine.search(query).unwrap();
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(result.documents[0].id, "new-1");
+    }
+
+    #[test]
+    fn test_backup_restore_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = TempDir::new().unwrap();
+        let archive = backup_dir.path().join("posts.tar.gz");
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        for i in 0..3 {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "title".to_string(),
+                crate::FieldValue::Text(format!("Backed up post {}", i)),
+            );
+            engine
+                .add_document(
+                    "posts",
+                    IndexDocument {
+                        id: format!("post-{}", i),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+
+        engine.backup_collection("posts", &archive).unwrap();
+        assert!(matches!(
+            engine.restore_collection(&archive),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+
+        engine.drop_collection("posts").unwrap();
+        assert_eq!(engine.restore_collection(&archive).unwrap(), "posts");
+
+        let query = SearchQuery::new(
+            "posts",
+            QueryExpression::FullText {
+                field: "title".to_string(),
+                text: "backed".to_string(),
+                boost: None,
+            },
+        );
+        let result = engine.search(query).unwrap();
+        assert_eq!(result.total_hits, 3);
+    }
+
+    #[test]
+    fn test_multi_search_merges_by_score() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        let collections = [
+            (
+                "news",
+                "title",
+                vec![("n1", "rust release"), ("n2", "weather")],
+            ),
+            (
+                "blogs",
+                "title",
+                vec![
+                    ("b1", "rust"),
+                    ("b2", "learning rust the long and patient way"),
+                ],
+            ),
+            ("products", "name", vec![("p1", "rust remover")]),
+        ];
+        for (name, field, docs) in &collections {
+            let schema = schema_helpers::text_collection_schema(name, &[(*field, true, true)]);
+            engine.create_collection(name.to_string(), schema).unwrap();
+            for (id, text) in docs {
+                let mut fields = HashMap::new();
+                fields.insert(field.to_string(), crate::FieldValue::Text(text.to_string()));
+                engine
+                    .add_document(
+                        name,
+                        IndexDocument {
+                            id: id.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection(name).unwrap();
+        }
+
+        let query = QueryExpression::FullText {
+            field: "title".to_string(),
+            text: "rust".to_string(),
+            boost: None,
+        };
+        let names: Vec<String> = ["news", "blogs", "products"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let result = engine.multi_search(&names, query.clone(), 10).unwrap();
+
+        // `products` has no title field and is skipped
+        assert_eq!(result.total_hits, 3);
+        let hits: Vec<_> = result
+            .documents
+            .iter()
+            .map(|hit| (hit.collection.as_str(), hit.id.as_str()))
+            .collect();
+        assert_eq!(hits.len(), 3);
+        assert!(hits.contains(&("news", "n1")));
+        assert!(hits.contains(&("blogs", "b1")));
+        assert!(hits.contains(&("blogs", "b2")));
+        assert!(
+            result
+                .documents
+                .windows(2)
+                .all(|pair| pair[0].score >= pair[1].score)
+        );
+
+        let top = engine.multi_search(&names, query, 1).unwrap();
+        assert_eq!(top.documents.len(), 1);
+        assert_eq!(top.documents[0].id, result.documents[0].id);
+    }
+
+    #[test]
+    fn test_get_schema_returns_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        let template = schema_helpers::product_catalog_schema();
+        engine
+            .create_collection("products".to_string(), template.clone())
+            .unwrap();
+
+        let schema = engine.get_schema("products").unwrap();
+        assert_eq!(
+            serde_json::to_value(&schema).unwrap(),
+            serde_json::to_value(&template).unwrap()
+        );
+
+        assert!(matches!(
+            engine.get_schema("missing"),
+            Err(SearchEngineError::CollectionNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_concurrent_searches_while_indexing() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+
+        const DOCS: usize = 200;
+        let query = || {
+            SearchQuery::new(
+                "notes",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: "note".to_string(),
+                    boost: None,
+                },
+            )
+        };
+
+        std::thread::scope(|scope| {
+            let engine = &engine;
+            scope.spawn(move || {
+                for i in 0..DOCS {
+                    let mut fields = HashMap::new();
+                    fields.insert(
+                        "body".to_string(),
+                        crate::FieldValue::Text(format!("note number {}", i)),
+                    );
+                    engine
+                        .add_document(
+                            "notes",
+                            IndexDocument {
+                                id: i.to_string(),
+                                fields,
+                            },
+                        )
+                        .unwrap();
+                    if i % 20 == 19 {
+                        engine.commit_collection("notes").unwrap();
+                    }
+                }
+            });
+
+            // Creating and dropping collections needs the write lock
+            scope.spawn(move || {
+                for i in 0..5 {
+                    let name = format!("scratch{}", i);
+                    let schema = schema_helpers::text_collection_schema(&name, &[]);
+                    engine.create_collection(name.clone(), schema).unwrap();
+                    engine.drop_collection(&name).unwrap();
+                }
+            });
+
+            for _ in 0..16 {
+                scope.spawn(move || {
+                    let mut last_seen = 0;
+                    for _ in 0..25 {
+                        let total_hits = engine.search(query()).unwrap().total_hits;
+                        assert!(total_hits >= last_seen && total_hits <= DOCS);
+                        last_seen = total_hits;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(engine.search(query()).unwrap().total_hits, DOCS);
+    }
+
+    #[test]
+    fn test_warmup_then_search() {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let engine = test_engine(&temp_dir);
+            engine
+                .create_collection("docs".to_string(), schema_helpers::blog_post_schema())
+                .unwrap();
+            for i in 0..10 {
+                let mut fields = HashMap::new();
+                fields.insert(
+                    "content".to_string(),
+                    crate::FieldValue::Text("warm rust index".to_string()),
+                );
+                fields.insert("view_count".to_string(), crate::FieldValue::I64(i));
+                engine
+                    .add_document(
+                        "docs",
+                        IndexDocument {
+                            id: i.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection("docs").unwrap();
+        }
+
+        // Reopen so the collection is loaded cold from disk
+        let engine = test_engine(&temp_dir);
+        engine.warmup_all().unwrap();
+
+        let query = SearchQuery::new(
+            "docs",
+            QueryExpression::FullText {
+                field: "content".to_string(),
+                text: "rust".to_string(),
+                boost: None,
+            },
+        );
+        assert_eq!(engine.search(query).unwrap().total_hits, 10);
+    }
+
+    #[test]
+    fn test_search_stream_exports_every_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        let schema = schema_helpers::text_collection_schema("logs", &[("line", true, true)]);
+        engine
+            .create_collection("logs".to_string(), schema)
+            .unwrap();
+        for i in 0..5000 {
+            let mut fields = HashMap::new();
+            let line = format!("{}request served", "slow ".repeat(i % 7));
+            fields.insert("line".to_string(), crate::FieldValue::Text(line));
+            engine
+                .add_document(
+                    "logs",
+                    IndexDocument {
+                        id: format!("{:05}", i),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("logs").unwrap();
+
+        let mut query = SearchQuery::new(
+            "logs",
+            QueryExpression::FullText {
+                field: "line".to_string(),
+                text: "request".to_string(),
+                boost: None,
+            },
+        );
+        query.limit = Some(256);
+
+        // Only the previous hit is kept to check the ranking order
+        let mut previous: Option<SearchHit> = None;
+        let mut count = 0;
+        for hit in engine.search_stream(query.clone()) {
+            let hit = hit.unwrap();
+            if let Some(previous) = &previous {
+                assert!(
+                    previous.score > hit.score
+                        || (previous.score == hit.score && previous.id < hit.id)
+                );
+            }
+            previous = Some(hit);
+            count += 1;
+        }
+        assert_eq!(count, 5000);
+
+        query.offset = Some(10);
+        let mut stream = engine.search_stream(query);
+        assert!(matches!(
+            stream.next(),
+            Some(Err(SearchEngineError::QueryError(_)))
+        ));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_rename_collection() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        for name in ["staging", "other"] {
+            engine
+                .create_collection(name.to_string(), schema_helpers::blog_post_schema())
+                .unwrap();
+        }
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("release notes".to_string()),
+        );
+        engine
+            .add_document(
+                "staging",
+                IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+
+        assert!(matches!(
+            engine.rename_collection("staging", "other"),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+
+        // Uncommitted documents are committed before the rename
+        let stale = engine.open_collection("staging").unwrap();
+        engine.rename_collection("staging", "production").unwrap();
+        assert!(!temp_dir.path().join("staging").exists());
+
+        // The old writer is released, so only the renamed collection writes
+        let doc = |id: &str| IndexDocument {
+            id: id.to_string(),
+            fields: HashMap::new(),
+        };
+        assert!(matches!(
+            stale.add_document(doc("post-2")),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+        engine.add_document("production", doc("post-3")).unwrap();
+        engine.commit_collection("production").unwrap();
+        assert!(matches!(
+            engine.search(SearchQuery::new("staging", QueryExpression::MatchAll)),
+            Err(SearchEngineError::CollectionNotFound { .. })
+        ));
+
+        let query = QueryExpression::Term {
+            field: "title".to_string(),
+            value: crate::FieldValue::Text("release".to_string()),
+        };
+        let result = engine
+            .search(SearchQuery::new("production", query.clone()))
+            .unwrap();
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(engine.get_schema("production").unwrap().name, "production");
+
+        // The new name survives a restart
+        drop(engine);
+        let engine = test_engine(&temp_dir);
+        let mut names = engine.list_collections();
+        names.sort();
+        assert_eq!(names, vec!["other", "production"]);
+        let result = engine
+            .search(SearchQuery::new("production", query))
+            .unwrap();
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(
+            engine
+                .get_collection_stats("production")
+                .unwrap()
+                .document_count,
+            2
+        );
+    }
+
+    #[test]
+    fn test_drop_and_rename_update_aliases() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        for name in ["products_v1", "archive"] {
+            engine
+                .create_collection(name.to_string(), schema_helpers::blog_post_schema())
+                .unwrap();
+        }
+        engine.create_alias("products", "products_v1").unwrap();
+        engine.create_alias("old", "archive").unwrap();
+
+        // Aliases follow a renamed collection
+        engine
+            .rename_collection("products_v1", "products_v2")
+            .unwrap();
+        assert!(
+            engine
+                .search(SearchQuery::new("products", QueryExpression::MatchAll))
+                .is_ok()
+        );
+
+        // Dropping through an alias drops its target and the alias with it
+        engine.drop_collection("old").unwrap();
+        assert_eq!(engine.list_collections(), vec!["products_v2".to_string()]);
+        assert!(matches!(
+            engine.search(SearchQuery::new("old", QueryExpression::MatchAll)),
+            Err(SearchEngineError::CollectionNotFound { .. })
+        ));
+        engine
+            .create_collection("old".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        drop(engine);
+        let aliases: HashMap<String, String> = serde_json::from_str(
+            &std::fs::read_to_string(temp_dir.path().join(ALIASES_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            aliases,
+            HashMap::from([("products".to_string(), "products_v2".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_swap_alias() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        for name in ["products_v1", "products_v2"] {
+            engine
+                .create_collection(name.to_string(), schema_helpers::blog_post_schema())
+                .unwrap();
+        }
+        engine.create_alias("products", "products_v1").unwrap();
+
+        // Writes through the alias land in the current target
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("old".to_string()),
+        );
+        engine
+            .add_document(
+                "products",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("new".to_string()),
+        );
+        engine
+            .add_document(
+                "products_v2",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("products_v2").unwrap();
+
+        let search_alias = |engine: &RustSearchEngine| {
+            let result = engine
+                .search(SearchQuery::new("products", QueryExpression::MatchAll))
+                .unwrap();
+            assert_eq!(result.total_hits, 1);
+            result.documents[0].collection.clone()
+        };
+        assert_eq!(search_alias(&engine), "products_v1");
+
+        engine.swap_alias("products", "products_v2").unwrap();
+        assert_eq!(search_alias(&engine), "products_v2");
+
+        assert!(matches!(
+            engine.create_alias("products_v1", "products_v2"),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+        assert!(matches!(
+            engine.swap_alias("products", "missing"),
+            Err(SearchEngineError::CollectionNotFound { .. })
+        ));
+        assert!(matches!(
+            engine.create_alias("../products", "products_v2"),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+        // A collection directory that is not loaded cannot be shadowed either
+        std::fs::create_dir(temp_dir.path().join("unloaded")).unwrap();
+        assert!(matches!(
+            engine.create_alias("unloaded", "products_v2"),
+            Err(SearchEngineError::CollectionError(_))
+        ));
+
+        // Aliases persist across restarts
+        drop(engine);
+        let engine = test_engine(&temp_dir);
+        assert_eq!(search_alias(&engine), "products_v2");
+    }
+
+    #[test]
+    fn test_commit_max_docs() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .commit_max_docs(5)
+            .build()
+            .unwrap();
+        let engine = RustSearchEngine::new(config).unwrap();
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        // The auto-commit timer is never started, so only the threshold commits
+        for i in 0..6 {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "title".to_string(),
+                crate::FieldValue::Text(format!("post {}", i)),
+            );
+            engine
+                .add_document(
+                    "posts",
+                    IndexDocument {
+                        id: i.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+
+        let result = engine
+            .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(result.total_hits, 5);
+        let collection = engine.get_collection("posts").unwrap();
+        assert_eq!(collection.uncommitted_docs.load(Ordering::Relaxed), 1);
+
+        // Deletes count towards the threshold too
+        for i in 0..4 {
+            engine.delete_document("posts", &i.to_string()).unwrap();
+        }
+        let result = engine
+            .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(result.total_hits, 2);
+        assert_eq!(collection.uncommitted_docs.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_nrt_refresh_makes_documents_searchable() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .commit_interval_ms(60_000)
+            .nrt_refresh_ms(20)
+            .build()
+            .unwrap();
+        let mut engine = RustSearchEngine::new(config).unwrap();
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        engine.start().await.unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("fresh".to_string()),
+        );
+        engine
+            .add_document(
+                "posts",
+                IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+
+        // Well within the hour-long commit interval
+        let mut found = false;
+        for _ in 0..100 {
+            let result = engine
+                .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+                .unwrap();
+            if result.total_hits == 1 {
+                found = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(found);
+        engine.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_soft_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        let collection = engine.get_collection("posts").unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "title".to_string(),
+            crate::FieldValue::Text("fresh".to_string()),
+        );
+        let doc = IndexDocument {
+            id: "post-1".to_string(),
+            fields,
+        };
+        collection.add_document(doc).unwrap();
+        assert_eq!(collection.reader.searcher().num_docs(), 0);
+        engine.refresh_collection("posts").unwrap();
+        assert_eq!(collection.reader.searcher().num_docs(), 1);
+        assert_eq!(collection.uncommitted_docs.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        let missing = engine.search(SearchQuery::new("missing", QueryExpression::MatchAll));
+        assert!(matches!(
+            missing,
+            Err(SearchEngineError::CollectionNotFound { name }) if name == "missing"
+        ));
+
+        let unknown_field = engine.search(SearchQuery::new(
+            "posts",
+            QueryExpression::Term {
+                field: "nope".to_string(),
+                value: crate::FieldValue::I64(1),
+            },
+        ));
+        assert!(matches!(
+            unknown_field,
+            Err(SearchEngineError::FieldNotFound { field }) if field == "nope"
+        ));
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "view_count".to_string(),
+            crate::FieldValue::Text("many".to_string()),
+        );
+        let mismatch = engine.add_document(
+            "posts",
+            IndexDocument {
+                id: "post-1".to_string(),
+                fields,
+            },
+        );
+        assert!(matches!(
+            mismatch,
+            Err(SearchEngineError::TypeMismatch { field, expected, got })
+                if field == "view_count" && expected == "i64" && got == "text"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_async_indexing() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = test_engine(&temp_dir);
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        for i in 0..3 {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "title".to_string(),
+                crate::FieldValue::Text(format!("async post {}", i)),
+            );
+            let doc = IndexDocument {
+                id: format!("post-{}", i),
+                fields,
+            };
+            engine.add_document_async("posts", doc).await.unwrap();
+        }
+        engine.commit_async("posts").await.unwrap();
+
+        let results = engine
+            .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+            .unwrap();
+        assert_eq!(results.total_hits, 3);
+        assert!(matches!(
+            engine.commit_async("missing").await,
+            Err(SearchEngineError::CollectionNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_freeze_collection() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = |id: &str| {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "title".to_string(),
+                crate::FieldValue::Text("frozen".to_string()),
+            );
+            IndexDocument {
+                id: id.to_string(),
+                fields,
+            }
+        };
+        {
+            let engine = test_engine(&temp_dir);
+            engine
+                .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+                .unwrap();
+            engine.add_document("posts", doc("post-1")).unwrap();
+            engine.freeze_collection("posts").unwrap();
+
+            assert!(matches!(
+                engine.add_document("posts", doc("post-2")),
+                Err(SearchEngineError::CollectionError(_))
+            ));
+            assert!(matches!(
+                engine.update_document("posts", doc("post-1")),
+                Err(SearchEngineError::CollectionError(_))
+            ));
+            assert!(matches!(
+                engine.delete_document("posts", "post-1"),
+                Err(SearchEngineError::CollectionError(_))
+            ));
+            // The pending document was committed before freezing
+            let results = engine
+                .search(SearchQuery::new("posts", QueryExpression::MatchAll))
+                .unwrap();
+            assert_eq!(results.total_hits, 1);
+        }
+
+        let engine = test_engine(&temp_dir);
+        assert!(engine.get_collection("posts").unwrap().is_read_only());
+        assert!(engine.add_document("posts", doc("post-2")).is_err());
+    }
+
+    #[test]
+    fn test_open_collection_twice_errors_when_configured() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .reopen_behavior(ReopenBehavior::Error)
+            .build()
+            .unwrap();
+        let engine = RustSearchEngine::new(config).unwrap();
+        engine
+            .create_collection("posts".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+
+        assert!(engine.open_collection("posts").is_err());
+    }
+}
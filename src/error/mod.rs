@@ -1,84 +1,72 @@
-use std::fmt;
+use thiserror::Error;
 
 /// Custom error type for the search engine
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum SearchEngineError {
     /// Tantivy-related errors
-    TantivyError(tantivy::TantivyError),
+    #[error("Tantivy error: {0}")]
+    TantivyError(#[from] tantivy::TantivyError),
 
     /// I/O errors
-    IoError(std::io::Error),
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
 
     /// Serialization/deserialization errors
-    SerdeError(serde_json::Error),
+    #[error("Serialization error: {0}")]
+    SerdeError(#[from] serde_json::Error),
 
     /// Schema-related errors
+    #[error("Schema error: {0}")]
     SchemaError(String),
 
     /// Collection-related errors
+    #[error("Collection error: {0}")]
     CollectionError(String),
 
+    /// Collection does not exist
+    #[error("Collection '{name}' not found")]
+    CollectionNotFound { name: String },
+
+    /// Document with the given ID does not exist
+    #[error("Document '{id}' not found")]
+    DocumentNotFound { id: String },
+
+    /// Field is not part of the collection schema
+    #[error("Field '{field}' not found")]
+    FieldNotFound { field: String },
+
+    /// Value does not match the type declared for its field
+    #[error("Field '{field}' type mismatch: expected {expected}, got {got}")]
+    TypeMismatch {
+        field: String,
+        expected: String,
+        got: String,
+    },
+
     /// Query parsing errors
+    #[error("Query error: {0}")]
     QueryError(String),
 
     /// Index errors
+    #[error("Index error: {0}")]
     IndexError(String),
 
     /// Configuration errors
+    #[error("Configuration error: {0}")]
     ConfigError(String),
 
     /// Search errors
+    #[error("Search error: {0}")]
     SearchError(String),
 
+    /// Query ran past its `timeout_ms` and partial results were not requested
+    #[error("Query timed out after {timeout_ms} ms")]
+    Timeout { timeout_ms: u64 },
+
     /// Generic error with custom message
+    #[error("Error: {0}")]
     CustomError(String),
 }
 
-impl fmt::Display for SearchEngineError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SearchEngineError::TantivyError(e) => write!(f, "Tantivy error: {}", e),
-            SearchEngineError::IoError(e) => write!(f, "I/O error: {}", e),
-            SearchEngineError::SerdeError(e) => write!(f, "Serialization error: {}", e),
-            SearchEngineError::SchemaError(msg) => write!(f, "Schema error: {}", msg),
-            SearchEngineError::CollectionError(msg) => write!(f, "Collection error: {}", msg),
-            SearchEngineError::QueryError(msg) => write!(f, "Query error: {}", msg),
-            SearchEngineError::IndexError(msg) => write!(f, "Index error: {}", msg),
-            SearchEngineError::ConfigError(msg) => write!(f, "Configuration error: {}", msg),
-            SearchEngineError::SearchError(msg) => write!(f, "Search error: {}", msg),
-            SearchEngineError::CustomError(msg) => write!(f, "Error: {}", msg),
-        }
-    }
-}
-
-impl std::error::Error for SearchEngineError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            SearchEngineError::TantivyError(e) => Some(e),
-            SearchEngineError::IoError(e) => Some(e),
-            SearchEngineError::SerdeError(e) => Some(e),
-            _ => None,
-        }
-    }
-}
-
-impl From<tantivy::TantivyError> for SearchEngineError {
-    fn from(error: tantivy::TantivyError) -> Self {
-        SearchEngineError::TantivyError(error)
-    }
-}
-
-impl From<std::io::Error> for SearchEngineError {
-    fn from(error: std::io::Error) -> Self {
-        SearchEngineError::IoError(error)
-    }
-}
-
-impl From<serde_json::Error> for SearchEngineError {
-    fn from(error: serde_json::Error) -> Self {
-        SearchEngineError::SerdeError(error)
-    }
-}
-
 /// Result type alias for convenience
 pub type Result<T> = std::result::Result<T, SearchEngineError>;
@@ -5,21 +5,25 @@
 //! - Numeric range queries with B-Tree indexing
 //! - Custom schema definitions per collection
 //! - Modular architecture for extensibility
-//! - Future support for geospatial indexing
+//! - Geo point fields with bounding-box queries
 
 pub mod collection;
 pub mod engine;
 pub mod error;
 pub mod schema;
 pub mod search;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod types;
 
 // Re-export commonly used types
 pub use engine::{CollectionHealth, EngineHealth, RustSearchEngine};
 pub use error::{Result, SearchEngineError};
 pub use types::{
-    CollectionStats, EngineConfig, FieldType, FieldValue, IndexDocument, QueryExpression,
-    SchemaDefinition, SearchHit, SearchQuery, SearchResult, SortField, SortOrder,
+    BaseTokenizer, CollectionStats, EmptyQueryBehavior, EngineConfig, FieldCapabilities, FieldType,
+    FieldValue, IndexDocument, IndexMetrics, MissingOrder, QueryExpression, QueryKind,
+    ReadConsistency, ReopenBehavior, SchemaDefinition, ScoringMode, SearchHit, SearchQuery,
+    SearchResult, SortField, SortOrder, TermStats, TokenFilterDefinition, TokenizerDefinition,
 };
 
 /// Convenience function to create a new search engine with default configuration
@@ -32,8 +36,10 @@ pub fn create_engine() -> Result<RustSearchEngine> {
 pub fn create_engine_with_data_dir<P: AsRef<std::path::Path>>(
     data_dir: P,
 ) -> Result<RustSearchEngine> {
-    let mut config = EngineConfig::default();
-    config.data_dir = data_dir.as_ref().to_string_lossy().to_string();
+    let config = EngineConfig {
+        data_dir: data_dir.as_ref().to_string_lossy().to_string(),
+        ..Default::default()
+    };
     RustSearchEngine::new(config)
 }
 
@@ -59,6 +65,11 @@ impl EngineConfigBuilder {
         self
     }
 
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.config.num_threads = num_threads;
+        self
+    }
+
     pub fn commit_interval_ms(mut self, interval_ms: u64) -> Self {
         self.config.commit_interval_ms = interval_ms;
         self
@@ -69,8 +80,78 @@ impl EngineConfigBuilder {
         self
     }
 
-    pub fn build(self) -> EngineConfig {
-        self.config
+    pub fn reopen_behavior(mut self, behavior: ReopenBehavior) -> Self {
+        self.config.reopen_behavior = behavior;
+        self
+    }
+
+    pub fn query_spans(mut self, enable: bool) -> Self {
+        self.config.query_spans = enable;
+        self
+    }
+
+    pub fn empty_query_behavior(mut self, behavior: EmptyQueryBehavior) -> Self {
+        self.config.empty_query_behavior = behavior;
+        self
+    }
+
+    pub fn max_clause_count(mut self, max_clause_count: usize) -> Self {
+        self.config.max_clause_count = max_clause_count;
+        self
+    }
+
+    pub fn max_result_window(mut self, max_result_window: usize) -> Self {
+        self.config.max_result_window = max_result_window;
+        self
+    }
+
+    pub fn max_expansions(mut self, max_expansions: usize) -> Self {
+        self.config.max_expansions = max_expansions;
+        self
+    }
+
+    pub fn scoring_mode(mut self, mode: ScoringMode) -> Self {
+        self.config.scoring_mode = mode;
+        self
+    }
+
+    pub fn bm25_k1(mut self, k1: f32) -> Self {
+        self.config.bm25_k1 = k1;
+        self
+    }
+
+    pub fn bm25_b(mut self, b: f32) -> Self {
+        self.config.bm25_b = b;
+        self
+    }
+
+    pub fn warmup_on_start(mut self, enable: bool) -> Self {
+        self.config.warmup_on_start = enable;
+        self
+    }
+
+    pub fn commit_max_docs(mut self, max_docs: usize) -> Self {
+        self.config.commit_max_docs = Some(max_docs);
+        self
+    }
+
+    pub fn nrt_refresh_ms(mut self, refresh_ms: u64) -> Self {
+        self.config.nrt_refresh_ms = Some(refresh_ms);
+        self
+    }
+
+    pub fn commit_on_drop(mut self, commit: bool) -> Self {
+        self.config.commit_on_drop = commit;
+        self
+    }
+
+    pub fn build(self) -> Result<EngineConfig> {
+        if self.config.num_threads == 0 {
+            return Err(SearchEngineError::ConfigError(
+                "num_threads must be at least 1".to_string(),
+            ));
+        }
+        Ok(self.config)
     }
 }
 
@@ -96,6 +177,8 @@ pub mod schema_helpers {
                     stored: *stored,
                     indexed: *indexed,
                     tokenizer: "default".to_string(),
+                    stop_words: None,
+                    normalize: None,
                 },
             );
         }
@@ -104,6 +187,10 @@ pub mod schema_helpers {
             name: name.to_string(),
             fields: field_map,
             primary_key: None,
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
         }
     }
 
@@ -117,6 +204,8 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
@@ -126,6 +215,8 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
@@ -135,6 +226,8 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "keyword".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
@@ -165,12 +258,22 @@ pub mod schema_helpers {
             },
         );
 
-        fields.insert("category".to_string(), FieldType::Facet);
+        fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: false,
+                max_depth: None,
+            },
+        );
 
         SchemaDefinition {
             name: "blog_posts".to_string(),
             fields,
             primary_key: Some("_id".to_string()),
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
         }
     }
 
@@ -184,6 +287,8 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
@@ -193,6 +298,8 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
@@ -220,15 +327,27 @@ pub mod schema_helpers {
                 stored: true,
                 indexed: true,
                 tokenizer: "keyword".to_string(),
+                stop_words: None,
+                normalize: None,
             },
         );
 
-        fields.insert("category".to_string(), FieldType::Facet);
+        fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: false,
+                max_depth: None,
+            },
+        );
 
         SchemaDefinition {
             name: "products".to_string(),
             fields,
             primary_key: Some("_id".to_string()),
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
         }
     }
 }
@@ -264,11 +383,35 @@ mod tests {
             .heap_size(100_000_000)
             .commit_interval_ms(5000)
             .enable_compression(false)
-            .build();
+            .build()
+            .unwrap();
 
         assert_eq!(config.data_dir, "/tmp/test");
         assert_eq!(config.default_heap_size, 100_000_000);
         assert_eq!(config.commit_interval_ms, 5000);
         assert!(!config.enable_compression);
     }
+
+    #[test]
+    fn test_config_builder_num_threads() {
+        let config = EngineConfigBuilder::new().num_threads(4).build().unwrap();
+        assert_eq!(config.num_threads, 4);
+
+        assert!(matches!(
+            EngineConfigBuilder::new().num_threads(0).build(),
+            Err(SearchEngineError::ConfigError(_))
+        ));
+
+        // Configurations built by hand are checked by the engine
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfig {
+            data_dir: temp_dir.path().to_string_lossy().to_string(),
+            num_threads: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            RustSearchEngine::new(config),
+            Err(SearchEngineError::ConfigError(_))
+        ));
+    }
 }
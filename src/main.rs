@@ -1,12 +1,12 @@
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use raven::types::format_rfc3339;
 use raven::{
     EngineConfigBuilder, FieldType, FieldValue, IndexDocument, QueryExpression, RustSearchEngine,
     SchemaDefinition, SearchQuery, schema_helpers,
 };
-use serde_json;
 use std::collections::HashMap;
 use std::io::{self, Write};
-use tracing_subscriber;
 
 #[derive(Parser)]
 #[command(name = "raven")]
@@ -20,6 +20,22 @@ struct Cli {
 
     #[arg(short, long)]
     verbose: bool,
+
+    /// Timezone used to display timestamps
+    #[arg(long, value_enum, default_value_t = Timezone::Utc)]
+    timezone: Timezone,
+
+    /// Field searched when a search names none; defaults to the collection's
+    /// first indexed text field
+    #[arg(long, global = true)]
+    default_field: Option<String>,
+}
+
+/// Timezone for rendering timestamps in CLI output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Timezone {
+    Utc,
+    Local,
 }
 
 #[derive(Subcommand)]
@@ -63,9 +79,9 @@ enum Commands {
         collection: String,
         /// Search query
         query: String,
-        /// Field to search (for full-text search)
-        #[arg(short, long, default_value = "content")]
-        field: String,
+        /// Field to search (for full-text search); overrides --default-field
+        #[arg(short, long)]
+        field: Option<String>,
         /// Number of results to return
         #[arg(short, long, default_value = "10")]
         limit: usize,
@@ -74,6 +90,12 @@ enum Commands {
         offset: usize,
     },
 
+    /// Print a collection's schema as JSON
+    Schema {
+        /// Collection name
+        collection: String,
+    },
+
     /// Get collection statistics
     Stats {
         /// Collection name (optional, shows all if not specified)
@@ -91,6 +113,30 @@ enum Commands {
         /// Collection name (optional, commits all if not specified)
         collection: Option<String>,
     },
+
+    /// Merge a collection's segments to speed up searches
+    Optimize {
+        /// Collection name
+        collection: String,
+    },
+
+    /// Show the tokens a text field's tokenizer produces for some text
+    Analyze {
+        /// Collection name
+        collection: String,
+        /// Text field whose tokenizer is used
+        field: String,
+        /// Text to tokenize
+        text: String,
+    },
+
+    /// Serve the HTTP API
+    #[cfg(feature = "server")]
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+    },
 }
 
 #[tokio::main]
@@ -108,7 +154,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(subscriber)?;
 
     // Create engine
-    let config = EngineConfigBuilder::new().data_dir(&cli.data_dir).build();
+    let config = EngineConfigBuilder::new().data_dir(&cli.data_dir).build()?;
 
     let mut engine = RustSearchEngine::new(config)?;
     engine.start().await?;
@@ -188,16 +234,21 @@ async fn main() -> anyhow::Result<()> {
             limit,
             offset,
         } => {
+            let field = match field.or(cli.default_field.clone()) {
+                Some(field) => field,
+                None => first_text_field(&engine, &collection)?,
+            };
             let search_query = SearchQuery {
-                collection: collection.clone(),
-                query: QueryExpression::FullText {
-                    field,
-                    text: query,
-                    boost: None,
-                },
                 limit: Some(limit),
                 offset: Some(offset),
-                sort: None,
+                ..SearchQuery::new(
+                    collection.clone(),
+                    QueryExpression::FullText {
+                        field,
+                        text: query,
+                        boost: None,
+                    },
+                )
             };
 
             let result = engine.search(search_query)?;
@@ -233,14 +284,29 @@ async fn main() -> anyhow::Result<()> {
             }
         }
 
+        Commands::Schema { collection } => {
+            let schema = engine.get_schema(&collection)?;
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+
         Commands::Stats { collection } => {
             if let Some(collection_name) = collection {
                 let stats = engine.get_collection_stats(&collection_name)?;
                 println!("Collection: {}", stats.name);
                 println!("Documents: {}", stats.document_count);
                 println!("Index size: {} bytes", stats.index_size_bytes);
-                println!("Created: {}", stats.created_at);
-                println!("Updated: {}", stats.updated_at);
+                println!("Segments: {}", stats.num_segments);
+                println!("Deleted documents: {}", stats.num_deleted_docs);
+                println!("Writer heap: {} bytes", stats.heap_size_bytes);
+                println!("Commit opstamp: {}", stats.commit_opstamp);
+                println!(
+                    "Created: {}",
+                    format_timestamp(stats.created_at, cli.timezone)
+                );
+                println!(
+                    "Updated: {}",
+                    format_timestamp(stats.updated_at, cli.timezone)
+                );
             } else {
                 let all_stats = engine.get_all_stats()?;
                 if all_stats.is_empty() {
@@ -250,8 +316,18 @@ async fn main() -> anyhow::Result<()> {
                         println!("Collection: {}", stats.name);
                         println!("  Documents: {}", stats.document_count);
                         println!("  Index size: {} bytes", stats.index_size_bytes);
-                        println!("  Created: {}", stats.created_at);
-                        println!("  Updated: {}", stats.updated_at);
+                        println!("  Segments: {}", stats.num_segments);
+                        println!("  Deleted documents: {}", stats.num_deleted_docs);
+                        println!("  Writer heap: {} bytes", stats.heap_size_bytes);
+                        println!("  Commit opstamp: {}", stats.commit_opstamp);
+                        println!(
+                            "  Created: {}",
+                            format_timestamp(stats.created_at, cli.timezone)
+                        );
+                        println!(
+                            "  Updated: {}",
+                            format_timestamp(stats.updated_at, cli.timezone)
+                        );
                         println!();
                     }
                 }
@@ -259,7 +335,7 @@ async fn main() -> anyhow::Result<()> {
         }
 
         Commands::Interactive => {
-            run_interactive_mode(&mut engine).await?;
+            run_interactive_mode(&mut engine, cli.default_field.as_deref()).await?;
         }
 
         Commands::Health => {
@@ -286,12 +362,68 @@ async fn main() -> anyhow::Result<()> {
                 println!("Committed all collections");
             }
         }
+
+        Commands::Optimize { collection } => {
+            engine.optimize_collection(&collection)?;
+            println!("Optimized collection: {}", collection);
+        }
+
+        Commands::Analyze {
+            collection,
+            field,
+            text,
+        } => {
+            for token in engine.analyze(&collection, &field, &text)? {
+                println!("{}", token);
+            }
+        }
+
+        #[cfg(feature = "server")]
+        Commands::Serve { port } => {
+            let shared = std::sync::Arc::new(tokio::sync::RwLock::new(engine));
+            raven::server::serve(shared.clone(), port).await?;
+            engine = std::sync::Arc::into_inner(shared)
+                .ok_or_else(|| anyhow::anyhow!("engine still in use after server shutdown"))?
+                .into_inner();
+        }
     }
 
     engine.stop().await?;
     Ok(())
 }
 
+/// Name of the first indexed full-text field of a collection, in alphabetical order
+fn first_text_field(engine: &RustSearchEngine, collection: &str) -> anyhow::Result<String> {
+    let schema_def = engine.get_schema(collection)?;
+    let mut text_fields: Vec<&String> = schema_def
+        .fields
+        .iter()
+        .filter(|(_, field_type)| matches!(field_type, FieldType::Text { indexed: true, .. }))
+        .map(|(name, _)| name)
+        .collect();
+    text_fields.sort();
+
+    text_fields
+        .first()
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Collection '{}' has no indexed text field to search",
+                collection
+            )
+        })
+}
+
+/// Format a timestamp as RFC3339 in the requested timezone
+fn format_timestamp(timestamp: DateTime<Utc>, timezone: Timezone) -> String {
+    match timezone {
+        Timezone::Utc => format_rfc3339(&timestamp),
+        Timezone::Local => timestamp
+            .with_timezone(&Local)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+    }
+}
+
 fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDefinition> {
     println!("Creating schema for collection: {}", collection_name);
     println!("Enter field definitions (type 'done' when finished):");
@@ -314,7 +446,7 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
             continue;
         }
 
-        println!("Field types: text, i64, f64, date, facet, bytes");
+        println!("Field types: text, i64, u64, bool, f64, date, facet, bytes, geo, json");
         print!("Field type: ");
         io::stdout().flush()?;
 
@@ -336,7 +468,9 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
                 io::stdin().read_line(&mut input)?;
                 let indexed = input.trim().to_lowercase() == "y";
 
-                print!("Tokenizer (default, simple, en_stem, keyword): ");
+                print!(
+                    "Tokenizer (default, simple, keyword, or a stemmer such as en_stem, fr_stem): "
+                );
                 io::stdout().flush()?;
                 input.clear();
                 io::stdin().read_line(&mut input)?;
@@ -351,6 +485,8 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
                     stored,
                     indexed,
                     tokenizer: tokenizer.to_string(),
+                    stop_words: None,
+                    normalize: None,
                 }
             }
             "i64" => {
@@ -378,6 +514,56 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
                     fast,
                 }
             }
+            "u64" => {
+                print!("Stored (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let stored = input.trim().to_lowercase() == "y";
+
+                print!("Indexed (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let indexed = input.trim().to_lowercase() == "y";
+
+                print!("Fast (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let fast = input.trim().to_lowercase() == "y";
+
+                FieldType::U64 {
+                    stored,
+                    indexed,
+                    fast,
+                }
+            }
+            "bool" => {
+                print!("Stored (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let stored = input.trim().to_lowercase() == "y";
+
+                print!("Indexed (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let indexed = input.trim().to_lowercase() == "y";
+
+                print!("Fast (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let fast = input.trim().to_lowercase() == "y";
+
+                FieldType::Bool {
+                    stored,
+                    indexed,
+                    fast,
+                }
+            }
             "f64" => {
                 print!("Stored (y/n): ");
                 io::stdout().flush()?;
@@ -428,7 +614,18 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
                     fast,
                 }
             }
-            "facet" => FieldType::Facet,
+            "facet" => {
+                print!("Stored (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let stored = input.trim().to_lowercase() == "y";
+
+                FieldType::Facet {
+                    stored,
+                    max_depth: None,
+                }
+            }
             "bytes" => {
                 print!("Stored (y/n): ");
                 io::stdout().flush()?;
@@ -444,6 +641,36 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
 
                 FieldType::Bytes { stored, indexed }
             }
+            "geo" => {
+                print!("Stored (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let stored = input.trim().to_lowercase() == "y";
+
+                print!("Indexed (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let indexed = input.trim().to_lowercase() == "y";
+
+                FieldType::Geo { stored, indexed }
+            }
+            "json" => {
+                print!("Stored (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let stored = input.trim().to_lowercase() == "y";
+
+                print!("Indexed (y/n): ");
+                io::stdout().flush()?;
+                input.clear();
+                io::stdin().read_line(&mut input)?;
+                let indexed = input.trim().to_lowercase() == "y";
+
+                FieldType::Json { stored, indexed }
+            }
             _ => {
                 println!("Unknown field type: {}", field_type_str);
                 continue;
@@ -458,10 +685,17 @@ fn create_schema_interactively(collection_name: &str) -> anyhow::Result<SchemaDe
         name: collection_name.to_string(),
         fields,
         primary_key: Some("_id".to_string()),
+        required: Vec::new(),
+        allowed_query_types: None,
+        tokenizers: HashMap::new(),
+        heap_size: None,
     })
 }
 
-async fn run_interactive_mode(engine: &mut RustSearchEngine) -> anyhow::Result<()> {
+async fn run_interactive_mode(
+    engine: &mut RustSearchEngine,
+    default_field: Option<&str>,
+) -> anyhow::Result<()> {
     println!("Rust Search Engine - Interactive Mode");
     println!("Type 'help' for available commands, 'quit' to exit");
 
@@ -534,17 +768,27 @@ async fn run_interactive_mode(engine: &mut RustSearchEngine) -> anyhow::Result<(
 
                 let collection = parts[1];
                 let query = parts[2..].join(" ");
+                let field = match default_field {
+                    Some(field) => field.to_string(),
+                    None => match first_text_field(engine, collection) {
+                        Ok(field) => field,
+                        Err(e) => {
+                            println!("Search error: {}", e);
+                            continue;
+                        }
+                    },
+                };
 
                 let search_query = SearchQuery {
-                    collection: collection.to_string(),
-                    query: QueryExpression::FullText {
-                        field: "content".to_string(),
-                        text: query,
-                        boost: None,
-                    },
                     limit: Some(5),
-                    offset: None,
-                    sort: None,
+                    ..SearchQuery::new(
+                        collection,
+                        QueryExpression::FullText {
+                            field,
+                            text: query,
+                            boost: None,
+                        },
+                    )
                 };
 
                 match engine.search(search_query) {
@@ -589,3 +833,100 @@ async fn run_interactive_mode(engine: &mut RustSearchEngine) -> anyhow::Result<(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_first_text_field_searches_body() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = raven::create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        schema.fields.insert(
+            "views".to_string(),
+            FieldType::I64 {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "body".to_string(),
+            FieldValue::Text("searchable body text".to_string()),
+        );
+        engine
+            .add_document(
+                "notes",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("notes").unwrap();
+
+        let field = first_text_field(&engine, "notes").unwrap();
+        assert_eq!(field, "body");
+
+        let query = SearchQuery::new(
+            "notes",
+            QueryExpression::FullText {
+                field,
+                text: "searchable".to_string(),
+                boost: None,
+            },
+        );
+        assert_eq!(engine.search(query).unwrap().total_hits, 1);
+    }
+
+    #[test]
+    fn test_stats_timestamps_match_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = raven::create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection(
+                "notes".to_string(),
+                schema_helpers::text_collection_schema("notes", &[("body", true, true)]),
+            )
+            .unwrap();
+        let stats = engine.get_collection_stats("notes").unwrap();
+        let json = serde_json::to_value(&stats).unwrap();
+
+        for (stored, key) in [
+            (stats.created_at, "created_at"),
+            (stats.updated_at, "updated_at"),
+        ] {
+            let printed = format_timestamp(stored, Timezone::Utc);
+            let parsed = DateTime::parse_from_rfc3339(&printed).unwrap();
+            assert_eq!(parsed, stored);
+            assert_eq!(json[key], printed.as_str());
+        }
+
+        let round_trip: raven::CollectionStats = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip.created_at, stats.created_at);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let timestamp = DateTime::parse_from_rfc3339("2023-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            format_timestamp(timestamp, Timezone::Utc),
+            "2023-01-02T03:04:05Z"
+        );
+
+        let local = format_timestamp(timestamp, Timezone::Local);
+        let parsed = DateTime::parse_from_rfc3339(&local).unwrap();
+        assert_eq!(parsed, timestamp);
+        assert_eq!(parsed.offset(), timestamp.with_timezone(&Local).offset());
+    }
+}
@@ -1,31 +1,600 @@
 use crate::error::{Result, SearchEngineError};
-use crate::types::{FieldType, FieldValue, SchemaDefinition};
+use crate::types::{
+    BaseTokenizer, FieldCapabilities, FieldType, FieldValue, IndexDocument,
+    MIN_HEAP_SIZE_PER_THREAD, SchemaDefinition, TokenFilterDefinition, TokenizerDefinition,
+};
 use std::collections::HashMap;
 use tantivy::schema::{
-    DateOptions, Field, INDEXED, NumericOptions, STORED, STRING, Schema, SchemaBuilder, TEXT,
-    TextFieldIndexing, TextOptions, Value,
+    DateOptions, FAST, FacetOptions, Field, IndexRecordOption, JsonObjectOptions, NumericOptions,
+    STORED, STRING, Schema, SchemaBuilder, TextFieldIndexing, TextOptions, Value,
+};
+use tantivy::tokenizer::{
+    BoxTokenStream, Language, LowerCaser, NgramTokenizer, PreTokenizedString, RawTokenizer,
+    RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer, Token, Tokenizer,
+    TokenizerManager, WhitespaceTokenizer,
 };
 
+/// Check a facet path's syntax, components and depth
+///
+/// An empty component usually means a `/` inside a name was not escaped, as in
+/// `/audio//video` or a trailing `/`, so it is rejected rather than indexed as
+/// an unintended level of the hierarchy.
+fn validate_facet_path(field_name: &str, path: &str, max_depth: Option<usize>) -> Result<()> {
+    let facet = tantivy::schema::Facet::from_text(path).map_err(|e| {
+        SearchEngineError::SchemaError(format!(
+            "Invalid facet '{}' for field '{}': {}",
+            path, field_name, e
+        ))
+    })?;
+    let components = facet.to_path();
+    if components.iter().any(|component| component.is_empty()) {
+        return Err(SearchEngineError::SchemaError(format!(
+            "Facet '{}' for field '{}' has an empty path component; escape '/' inside a component as '\\/'",
+            path, field_name
+        )));
+    }
+    if let Some(max_depth) = max_depth
+        && components.len() > max_depth
+    {
+        return Err(SearchEngineError::SchemaError(format!(
+            "Facet '{}' for field '{}' is {} levels deep, more than the maximum of {}",
+            path,
+            field_name,
+            components.len(),
+            max_depth
+        )));
+    }
+    Ok(())
+}
+
+/// Build Tantivy's pre-tokenized text from `(token, position)` pairs
+///
+/// The stored text is the tokens joined by spaces, ordered by position, and
+/// each token's offsets point into it.
+pub(crate) fn pre_tokenized_string(tokens: &[(String, u32)]) -> PreTokenizedString {
+    let mut tokens: Vec<&(String, u32)> = tokens.iter().collect();
+    tokens.sort_by_key(|(_, position)| *position);
+
+    let mut text = String::new();
+    let mut pre_tokenized = Vec::with_capacity(tokens.len());
+    for (token, position) in tokens {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        let offset_from = text.len();
+        text.push_str(token);
+        pre_tokenized.push(Token {
+            offset_from,
+            offset_to: text.len(),
+            position: *position as usize,
+            text: token.clone(),
+            position_length: 1,
+        });
+    }
+    PreTokenizedString {
+        text,
+        tokens: pre_tokenized,
+    }
+}
+
 /// Schema manager for handling Tantivy schemas
 #[derive(Debug, Clone)]
 pub struct SchemaManager {
     schema_def: SchemaDefinition,
     tantivy_schema: Schema,
     field_map: HashMap<String, Field>,
+    id_field_name: String,
+    boost_field: Option<Field>,
+    /// Latitude and longitude fields backing each geo field
+    geo_fields: HashMap<String, (Field, Field)>,
+}
+
+/// Name of the ID field used when the schema defines no primary key
+pub const DEFAULT_ID_FIELD: &str = "_id";
+
+/// Name of the per-document boost field
+pub const BOOST_FIELD: &str = "_boost";
+
+/// Names of the latitude and longitude fields backing a geo field
+pub fn geo_field_names(field_name: &str) -> (String, String) {
+    (format!("{}.lat", field_name), format!("{}.lon", field_name))
 }
 
+/// Name of the tokenizer a text field is indexed with
+///
+/// Fields with stop words get their own copy of their tokenizer with the stop
+/// words removed, registered under a name derived from the field.
+pub fn field_tokenizer_name(
+    field_name: &str,
+    tokenizer: &str,
+    stop_words: Option<&[String]>,
+) -> String {
+    match stop_words {
+        Some(words) if !words.is_empty() => format!("{}.{}.stop_words", field_name, tokenizer),
+        _ => tokenizer.to_string(),
+    }
+}
+
+/// Tokenizer names a text field may use
+pub const TOKENIZERS: &[&str] = &[
+    "default", "simple", "en_stem", "keyword", "fr_stem", "de_stem", "es_stem", "it_stem",
+    "pt_stem", "nl_stem", "ru_stem", "sv_stem",
+];
+
+/// Tokenizer of keyword fields normalized to lowercase
+pub const KEYWORD_LOWERCASE_TOKENIZER: &str = "keyword_lowercase";
+
+/// Normalizations a keyword field may use
+pub const NORMALIZERS: &[&str] = &["lowercase"];
+
+/// Stemming tokenizers registered alongside Tantivy's built-in `en_stem`
+pub const STEMMERS: &[(&str, Language)] = &[
+    ("fr_stem", Language::French),
+    ("de_stem", Language::German),
+    ("es_stem", Language::Spanish),
+    ("it_stem", Language::Italian),
+    ("pt_stem", Language::Portuguese),
+    ("nl_stem", Language::Dutch),
+    ("ru_stem", Language::Russian),
+    ("sv_stem", Language::Swedish),
+];
+
 impl SchemaManager {
     /// Create a new schema manager from schema definition
+    ///
+    /// The definition is checked with [`SchemaManager::validate`] first.
     pub fn new(schema_def: SchemaDefinition) -> Result<Self> {
-        let (tantivy_schema, field_map) = Self::build_tantivy_schema(&schema_def)?;
+        Self::validate(&schema_def)?;
+        Self::build(schema_def)
+    }
+
+    /// Build a schema manager without the structural checks of `validate`,
+    /// so indexes created before a check was introduced can still be opened
+    fn build(schema_def: SchemaDefinition) -> Result<Self> {
+        Self::validate_tokenizers(&schema_def)?;
+        let (tantivy_schema, mut field_map) = Self::build_tantivy_schema(&schema_def)?;
+        let id_field_name = Self::primary_key_name(&schema_def).to_string();
+        let boost_field = field_map.remove(BOOST_FIELD);
+        let geo_fields = Self::geo_field_names_of(&schema_def)
+            .filter_map(|(name, lat, lon)| {
+                Some((name, (field_map.remove(&lat)?, field_map.remove(&lon)?)))
+            })
+            .collect();
 
         Ok(Self {
             schema_def,
             tantivy_schema,
             field_map,
+            id_field_name,
+            boost_field,
+            geo_fields,
+        })
+    }
+
+    /// Create a schema manager for an existing index
+    ///
+    /// Fields are resolved by name against the index's own schema, so indexes
+    /// created before a field was introduced (such as `_boost`) keep working.
+    pub fn for_index_schema(schema_def: SchemaDefinition, index_schema: &Schema) -> Result<Self> {
+        let manager = Self::build(schema_def)?;
+
+        let field_map: HashMap<String, Field> = manager
+            .field_map
+            .keys()
+            .filter_map(|name| {
+                index_schema
+                    .get_field(name)
+                    .ok()
+                    .map(|field| (name.clone(), field))
+            })
+            .collect();
+
+        if !field_map.contains_key(&manager.id_field_name) {
+            return Err(SearchEngineError::SchemaError(format!(
+                "ID field '{}' not found in index schema",
+                manager.id_field_name
+            )));
+        }
+
+        let geo_fields = Self::geo_field_names_of(&manager.schema_def)
+            .filter_map(|(name, lat, lon)| {
+                let lat = index_schema.get_field(&lat).ok()?;
+                let lon = index_schema.get_field(&lon).ok()?;
+                Some((name, (lat, lon)))
+            })
+            .collect();
+
+        Ok(Self {
+            tantivy_schema: index_schema.clone(),
+            field_map,
+            boost_field: index_schema.get_field(BOOST_FIELD).ok(),
+            geo_fields,
+            ..manager
         })
     }
 
+    /// Create a schema manager from a schema in Tantivy's JSON format
+    ///
+    /// The schema must have an `_id` text field, which becomes the default
+    /// primary key; `_boost` is recognized and pairs of f64 fields named
+    /// `<field>.lat` and `<field>.lon` become geo fields. Fields with options
+    /// a `FieldType` cannot express, such as fast text fields or tokenizers
+    /// other than the built-in ones, are rejected.
+    pub fn from_tantivy_json(name: &str, json: &str) -> Result<Self> {
+        let schema: Schema = serde_json::from_str(json)?;
+        if !schema
+            .get_field(DEFAULT_ID_FIELD)
+            .is_ok_and(|field| schema.get_field_entry(field).field_type().is_str())
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Tantivy schema has no '{}' text field",
+                DEFAULT_ID_FIELD
+            )));
+        }
+
+        let unsupported = |field_name: &str, reason: &str| {
+            SearchEngineError::SchemaError(format!(
+                "Field '{}' cannot be represented: {}",
+                field_name, reason
+            ))
+        };
+        let mut fields = HashMap::new();
+        for (_, entry) in schema.fields() {
+            let field_name = entry.name();
+            if field_name == DEFAULT_ID_FIELD || field_name == BOOST_FIELD {
+                continue;
+            }
+
+            let field_type = match entry.field_type() {
+                tantivy::schema::FieldType::Str(options) => {
+                    if options.is_fast() {
+                        return Err(unsupported(
+                            field_name,
+                            "fast text fields are not supported",
+                        ));
+                    }
+                    let (tokenizer, normalize) = match options.get_indexing_options() {
+                        Some(indexing) if indexing.tokenizer() == "raw" => ("keyword", None),
+                        Some(indexing) if indexing.tokenizer() == KEYWORD_LOWERCASE_TOKENIZER => {
+                            ("keyword", Some("lowercase".to_string()))
+                        }
+                        Some(indexing) => (indexing.tokenizer(), None),
+                        None => ("default", None),
+                    };
+                    if !TOKENIZERS.contains(&tokenizer) {
+                        return Err(unsupported(
+                            field_name,
+                            &format!("tokenizer '{}' is not built in", tokenizer),
+                        ));
+                    }
+                    FieldType::Text {
+                        stored: options.is_stored(),
+                        indexed: options.get_indexing_options().is_some(),
+                        tokenizer: tokenizer.to_string(),
+                        stop_words: None,
+                        normalize,
+                    }
+                }
+                tantivy::schema::FieldType::I64(options) => FieldType::I64 {
+                    stored: options.is_stored(),
+                    indexed: options.is_indexed(),
+                    fast: options.is_fast(),
+                },
+                tantivy::schema::FieldType::U64(options) => FieldType::U64 {
+                    stored: options.is_stored(),
+                    indexed: options.is_indexed(),
+                    fast: options.is_fast(),
+                },
+                tantivy::schema::FieldType::Bool(options) => FieldType::Bool {
+                    stored: options.is_stored(),
+                    indexed: options.is_indexed(),
+                    fast: options.is_fast(),
+                },
+                tantivy::schema::FieldType::F64(options) => {
+                    if let Some(geo_name) = field_name.strip_suffix(".lat")
+                        && options.is_fast()
+                        && schema
+                            .get_field(&geo_field_names(geo_name).1)
+                            .is_ok_and(|lon| {
+                                schema.get_field_entry(lon).field_type() == entry.field_type()
+                            })
+                    {
+                        fields.insert(
+                            geo_name.to_string(),
+                            FieldType::Geo {
+                                stored: options.is_stored(),
+                                indexed: options.is_indexed(),
+                            },
+                        );
+                        continue;
+                    }
+                    if let Some(geo_name) = field_name.strip_suffix(".lon")
+                        && schema.get_field(&geo_field_names(geo_name).0).is_ok()
+                    {
+                        continue;
+                    }
+                    FieldType::F64 {
+                        stored: options.is_stored(),
+                        indexed: options.is_indexed(),
+                        fast: options.is_fast(),
+                    }
+                }
+                tantivy::schema::FieldType::Date(options) => FieldType::Date {
+                    stored: options.is_stored(),
+                    indexed: options.is_indexed(),
+                    fast: options.is_fast(),
+                },
+                tantivy::schema::FieldType::Facet(options) => FieldType::Facet {
+                    stored: options.is_stored(),
+                    max_depth: None,
+                },
+                tantivy::schema::FieldType::Bytes(options) => {
+                    if options.is_fast() {
+                        return Err(unsupported(
+                            field_name,
+                            "fast bytes fields are not supported",
+                        ));
+                    }
+                    FieldType::Bytes {
+                        stored: options.is_stored(),
+                        indexed: options.is_indexed(),
+                    }
+                }
+                tantivy::schema::FieldType::JsonObject(options) => {
+                    if options.is_fast() {
+                        return Err(unsupported(
+                            field_name,
+                            "fast JSON fields are not supported",
+                        ));
+                    }
+                    if let Some(indexing) = options.get_text_indexing_options()
+                        && indexing.tokenizer() != "default"
+                    {
+                        return Err(unsupported(
+                            field_name,
+                            "JSON fields must use the 'default' tokenizer",
+                        ));
+                    }
+                    FieldType::Json {
+                        stored: options.is_stored(),
+                        indexed: options.is_indexed(),
+                    }
+                }
+                tantivy::schema::FieldType::IpAddr(_) => {
+                    return Err(unsupported(
+                        field_name,
+                        "IP address fields are not supported",
+                    ));
+                }
+            };
+            fields.insert(field_name.to_string(), field_type);
+        }
+
+        Self::new(SchemaDefinition {
+            name: name.to_string(),
+            fields,
+            primary_key: None,
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
+        })
+    }
+
+    /// Geo fields of a schema with the names of their backing fields
+    fn geo_field_names_of(
+        schema_def: &SchemaDefinition,
+    ) -> impl Iterator<Item = (String, String, String)> + '_ {
+        schema_def
+            .fields
+            .iter()
+            .filter(|(_, field_type)| matches!(field_type, FieldType::Geo { .. }))
+            .map(|(name, _)| {
+                let (lat, lon) = geo_field_names(name);
+                (name.clone(), lat, lon)
+            })
+    }
+
+    /// Reject schema definitions that cannot make a useful collection
+    ///
+    /// Fails with a schema error when a field uses a reserved name, when a text
+    /// or JSON field is neither stored nor indexed, or when a custom primary key
+    /// is not one of the declared fields.
+    pub fn validate(schema_def: &SchemaDefinition) -> Result<()> {
+        for reserved in [DEFAULT_ID_FIELD, BOOST_FIELD] {
+            if schema_def.fields.contains_key(reserved) {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Field name '{}' is reserved",
+                    reserved
+                )));
+            }
+        }
+
+        let mut field_defs: Vec<_> = schema_def.fields.iter().collect();
+        field_defs.sort_by(|a, b| a.0.cmp(b.0));
+        for (field_name, field_type) in field_defs {
+            if let FieldType::Text {
+                stored: false,
+                indexed: false,
+                ..
+            }
+            | FieldType::Json {
+                stored: false,
+                indexed: false,
+            } = field_type
+            {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Field '{}' is neither stored nor indexed",
+                    field_name
+                )));
+            }
+            if let FieldType::Facet {
+                max_depth: Some(0), ..
+            } = field_type
+            {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Facet field '{}' must allow a max_depth of at least 1",
+                    field_name
+                )));
+            }
+        }
+
+        if let Some(heap_size) = schema_def.heap_size
+            && heap_size < MIN_HEAP_SIZE_PER_THREAD
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "heap_size must be at least {} bytes, got {}",
+                MIN_HEAP_SIZE_PER_THREAD, heap_size
+            )));
+        }
+
+        let primary_key = Self::primary_key_name(schema_def);
+        if primary_key != DEFAULT_ID_FIELD && !schema_def.fields.contains_key(primary_key) {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Primary key '{}' is not a declared field",
+                primary_key
+            )));
+        }
+        // Updates and deletes match the whole ID as one term, and hits read it back
+        if let Some(field_type) = schema_def.fields.get(primary_key)
+            && !matches!(
+                field_type,
+                FieldType::Text { stored: true, indexed: true, tokenizer, .. }
+                    if tokenizer == "keyword"
+            )
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Primary key field '{}' must be a stored and indexed text field with the keyword tokenizer",
+                primary_key
+            )));
+        }
+
+        if let Some(name) = schema_def
+            .required
+            .iter()
+            .find(|name| *name != primary_key && !schema_def.fields.contains_key(*name))
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Required field '{}' is not a declared field",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Name of the field holding document IDs
+    fn primary_key_name(schema_def: &SchemaDefinition) -> &str {
+        schema_def
+            .primary_key
+            .as_deref()
+            .unwrap_or(DEFAULT_ID_FIELD)
+    }
+
+    /// Reject text fields whose tokenizer is neither one of `TOKENIZERS` nor
+    /// declared by the schema, custom tokenizers that cannot be built, and
+    /// normalization of anything but a non-key keyword field
+    fn validate_tokenizers(schema_def: &SchemaDefinition) -> Result<()> {
+        let mut custom_names: Vec<&str> =
+            schema_def.tokenizers.keys().map(String::as_str).collect();
+        custom_names.sort();
+
+        for name in &custom_names {
+            if TOKENIZERS.contains(name) {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Tokenizer '{}' is built in and cannot be redefined",
+                    name
+                )));
+            }
+            build_text_analyzer(name, &schema_def.tokenizers[*name])?;
+        }
+
+        let mut field_defs: Vec<_> = schema_def.fields.iter().collect();
+        field_defs.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (field_name, field_type) in field_defs {
+            if let FieldType::Text {
+                tokenizer,
+                stop_words: Some(words),
+                ..
+            } = field_type
+                && tokenizer == "keyword"
+                && !words.is_empty()
+            {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Field '{}' uses the keyword tokenizer, which does not support stop words",
+                    field_name
+                )));
+            }
+
+            if let FieldType::Text {
+                tokenizer,
+                normalize: Some(normalize),
+                ..
+            } = field_type
+            {
+                if tokenizer != "keyword" {
+                    return Err(SearchEngineError::SchemaError(format!(
+                        "Field '{}' sets normalize, which only applies to keyword fields",
+                        field_name
+                    )));
+                }
+                if !NORMALIZERS.contains(&normalize.as_str()) {
+                    return Err(SearchEngineError::SchemaError(format!(
+                        "Field '{}' uses unknown normalizer '{}'; expected one of: {}",
+                        field_name,
+                        normalize,
+                        NORMALIZERS.join(", ")
+                    )));
+                }
+                if *field_name == Self::primary_key_name(schema_def) {
+                    return Err(SearchEngineError::SchemaError(format!(
+                        "Primary key field '{}' cannot be normalized",
+                        field_name
+                    )));
+                }
+            }
+
+            if let FieldType::Text { tokenizer, .. } = field_type
+                && !TOKENIZERS.contains(&tokenizer.as_str())
+                && !schema_def.tokenizers.contains_key(tokenizer)
+            {
+                if tokenizer.ends_with("_stem") {
+                    let stemmers: Vec<&str> = TOKENIZERS
+                        .iter()
+                        .copied()
+                        .filter(|name| name.ends_with("_stem"))
+                        .collect();
+                    return Err(SearchEngineError::SchemaError(format!(
+                        "Unknown stemmer '{}' for field '{}'; supported stemmers are: {}",
+                        tokenizer,
+                        field_name,
+                        stemmers.join(", ")
+                    )));
+                }
+
+                let valid: Vec<&str> = TOKENIZERS.iter().copied().chain(custom_names).collect();
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Unknown tokenizer '{}' for field '{}'; valid tokenizers are: {}",
+                    tokenizer,
+                    field_name,
+                    valid.join(", ")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the custom tokenizers declared by the schema, keyed by name
+    pub fn custom_tokenizers(&self) -> Result<Vec<(String, TextAnalyzer)>> {
+        self.schema_def
+            .tokenizers
+            .iter()
+            .map(|(name, definition)| Ok((name.clone(), build_text_analyzer(name, definition)?)))
+            .collect()
+    }
+
     /// Build Tantivy schema from our schema definition
     fn build_tantivy_schema(
         schema_def: &SchemaDefinition,
@@ -33,17 +602,36 @@ impl SchemaManager {
         let mut schema_builder = SchemaBuilder::new();
         let mut field_map = HashMap::new();
 
-        // Add ID field (always present)
-        let id_field = schema_builder.add_text_field("_id", TEXT | STORED);
-        field_map.insert("_id".to_string(), id_field);
+        // Add ID field (always present), unless the primary key is a declared field.
+        // IDs are indexed untokenized so a term lookup matches the whole ID, and
+        // kept in a fast field so ranking can break score ties without the doc store.
+        let id_field_name = Self::primary_key_name(schema_def);
+        match schema_def.fields.get(id_field_name) {
+            None => {
+                let id_field = schema_builder.add_text_field(id_field_name, STRING | STORED | FAST);
+                field_map.insert(id_field_name.to_string(), id_field);
+            }
+            Some(FieldType::Text { .. }) => {}
+            Some(_) => {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Primary key field '{}' must be a text field",
+                    id_field_name
+                )));
+            }
+        }
+
+        // Add user-defined fields in a stable order
+        let mut field_defs: Vec<_> = schema_def.fields.iter().collect();
+        field_defs.sort_by(|a, b| a.0.cmp(b.0));
 
-        // Add user-defined fields
-        for (field_name, field_type) in &schema_def.fields {
+        for (field_name, field_type) in field_defs {
             let field = match field_type {
                 FieldType::Text {
                     stored,
                     indexed,
                     tokenizer,
+                    stop_words,
+                    normalize,
                 } => {
                     let mut options = TextOptions::default();
 
@@ -52,6 +640,17 @@ impl SchemaManager {
                     }
 
                     if *indexed {
+                        // Normalized keywords are still a single term per value
+                        if tokenizer == "keyword" && normalize.is_some() {
+                            let text_indexing = TextFieldIndexing::default()
+                                .set_tokenizer(KEYWORD_LOWERCASE_TOKENIZER)
+                                .set_index_option(IndexRecordOption::Basic);
+                            options = options.set_indexing_options(text_indexing);
+                            let field = schema_builder.add_text_field(field_name, options);
+                            field_map.insert(field_name.clone(), field);
+                            continue;
+                        }
+
                         // Handle keyword tokenizer separately
                         if tokenizer == "keyword" {
                             // For exact matching, use STRING field
@@ -66,23 +665,13 @@ impl SchemaManager {
                             continue;
                         }
 
-                        let text_indexing = match tokenizer.as_str() {
-                            "simple" => TextFieldIndexing::default()
-                                .set_tokenizer("simple")
-                                .set_index_option(
-                                    tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
-                                ),
-                            "en_stem" => TextFieldIndexing::default()
-                                .set_tokenizer("en_stem")
-                                .set_index_option(
-                                    tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
-                                ),
-                            _ => TextFieldIndexing::default()
-                                .set_tokenizer("default")
-                                .set_index_option(
-                                    tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
-                                ),
-                        };
+                        let tokenizer =
+                            field_tokenizer_name(field_name, tokenizer, stop_words.as_deref());
+                        let text_indexing = TextFieldIndexing::default()
+                            .set_tokenizer(&tokenizer)
+                            .set_index_option(
+                                tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
+                            );
 
                         options = options.set_indexing_options(text_indexing);
                     }
@@ -112,6 +701,50 @@ impl SchemaManager {
                     schema_builder.add_i64_field(field_name, options)
                 }
 
+                FieldType::U64 {
+                    stored,
+                    indexed,
+                    fast,
+                } => {
+                    let mut options = NumericOptions::default();
+
+                    if *stored {
+                        options = options.set_stored();
+                    }
+
+                    if *indexed {
+                        options = options.set_indexed();
+                    }
+
+                    if *fast {
+                        options = options.set_fast();
+                    }
+
+                    schema_builder.add_u64_field(field_name, options)
+                }
+
+                FieldType::Bool {
+                    stored,
+                    indexed,
+                    fast,
+                } => {
+                    let mut options = NumericOptions::default();
+
+                    if *stored {
+                        options = options.set_stored();
+                    }
+
+                    if *indexed {
+                        options = options.set_indexed();
+                    }
+
+                    if *fast {
+                        options = options.set_fast();
+                    }
+
+                    schema_builder.add_bool_field(field_name, options)
+                }
+
                 FieldType::F64 {
                     stored,
                     indexed,
@@ -156,7 +789,15 @@ impl SchemaManager {
                     schema_builder.add_date_field(field_name, options)
                 }
 
-                FieldType::Facet => schema_builder.add_facet_field(field_name, INDEXED),
+                FieldType::Facet { stored, .. } => {
+                    let mut options = FacetOptions::default();
+
+                    if *stored {
+                        options = options.set_stored();
+                    }
+
+                    schema_builder.add_facet_field(field_name, options)
+                }
 
                 FieldType::Bytes { stored, indexed } => {
                     let mut options = tantivy::schema::BytesOptions::default();
@@ -172,19 +813,53 @@ impl SchemaManager {
                     schema_builder.add_bytes_field(field_name, options)
                 }
 
-                FieldType::Geo {
-                    stored: _,
-                    indexed: _,
-                } => {
-                    // TODO: Implement geospatial fields when Tantivy supports them
-                    // For now, we'll skip these fields
+                FieldType::Geo { stored, indexed } => {
+                    // Coordinates are always fast so they can be read while filtering
+                    let mut options = NumericOptions::default().set_fast();
+
+                    if *stored {
+                        options = options.set_stored();
+                    }
+
+                    if *indexed {
+                        options = options.set_indexed();
+                    }
+
+                    let (lat_name, lon_name) = geo_field_names(field_name);
+                    let lat_field = schema_builder.add_f64_field(&lat_name, options.clone());
+                    let lon_field = schema_builder.add_f64_field(&lon_name, options);
+                    field_map.insert(lat_name, lat_field);
+                    field_map.insert(lon_name, lon_field);
                     continue;
                 }
+
+                FieldType::Json { stored, indexed } => {
+                    let mut options = JsonObjectOptions::default();
+
+                    if *stored {
+                        options = options.set_stored();
+                    }
+
+                    if *indexed {
+                        options = options.set_indexing_options(
+                            TextFieldIndexing::default()
+                                .set_tokenizer("default")
+                                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+                        );
+                    }
+
+                    schema_builder.add_json_field(field_name, options)
+                }
             };
 
             field_map.insert(field_name.clone(), field);
         }
 
+        // Add per-document boost field, read as a fast field at scoring time
+        let boost_field =
+            schema_builder.add_f64_field(BOOST_FIELD, NumericOptions::default().set_fast());
+        field_map.insert(BOOST_FIELD.to_string(), boost_field);
+
         let schema = schema_builder.build();
         Ok((schema, field_map))
     }
@@ -194,6 +869,11 @@ impl SchemaManager {
         &self.tantivy_schema
     }
 
+    /// Serialize the Tantivy schema in Tantivy's own JSON format
+    pub fn to_tantivy_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.tantivy_schema)?)
+    }
+
     /// Get the schema definition
     pub fn schema_definition(&self) -> &SchemaDefinition {
         &self.schema_def
@@ -204,6 +884,91 @@ impl SchemaManager {
         self.field_map.get(field_name).copied()
     }
 
+    /// Get the name of the field holding document IDs
+    pub fn id_field_name(&self) -> &str {
+        &self.id_field_name
+    }
+
+    /// Get the field holding document IDs
+    pub fn id_field(&self) -> Field {
+        self.field_map[&self.id_field_name]
+    }
+
+    /// Get the per-document boost field, if the index has one
+    pub fn boost_field(&self) -> Option<Field> {
+        self.boost_field
+    }
+
+    /// Get the latitude and longitude fields backing a geo field
+    pub fn get_geo_fields(&self, field_name: &str) -> Option<(Field, Field)> {
+        self.geo_fields.get(field_name).copied()
+    }
+
+    /// Get the stored, indexed and fast flags of every field, ordered by name
+    ///
+    /// Facet and geo fields are always fast, as Tantivy keeps facet paths and
+    /// coordinates in fast fields whatever the schema declares.
+    pub fn field_capabilities(&self) -> Vec<FieldCapabilities> {
+        let mut capabilities: Vec<_> =
+            self.schema_def
+                .fields
+                .iter()
+                .map(|(name, field_type)| {
+                    let (stored, indexed, fast) = match *field_type {
+                        FieldType::Text {
+                            stored, indexed, ..
+                        } => (stored, indexed, false),
+                        FieldType::I64 {
+                            stored,
+                            indexed,
+                            fast,
+                        }
+                        | FieldType::U64 {
+                            stored,
+                            indexed,
+                            fast,
+                        }
+                        | FieldType::Bool {
+                            stored,
+                            indexed,
+                            fast,
+                        }
+                        | FieldType::F64 {
+                            stored,
+                            indexed,
+                            fast,
+                        }
+                        | FieldType::Date {
+                            stored,
+                            indexed,
+                            fast,
+                        } => (stored, indexed, fast),
+                        FieldType::Facet { stored, .. } => (stored, true, true),
+                        FieldType::Geo { stored, indexed } => (stored, indexed, true),
+                        FieldType::Bytes { stored, indexed }
+                        | FieldType::Json { stored, indexed } => (stored, indexed, false),
+                    };
+                    FieldCapabilities {
+                        name: name.clone(),
+                        stored,
+                        indexed,
+                        fast,
+                    }
+                })
+                .collect();
+        capabilities.sort_by(|a, b| a.name.cmp(&b.name));
+        capabilities
+    }
+
+    /// Get the names of the fields that can be sorted on and range-queried efficiently
+    pub fn fast_fields(&self) -> Vec<String> {
+        self.field_capabilities()
+            .into_iter()
+            .filter(|capabilities| capabilities.fast)
+            .map(|capabilities| capabilities.name)
+            .collect()
+    }
+
     /// Get all fields
     pub fn get_all_fields(&self) -> &HashMap<String, Field> {
         &self.field_map
@@ -223,7 +988,12 @@ impl SchemaManager {
 
         let tantivy_value = match value {
             FieldValue::Text(text) => tantivy::schema::OwnedValue::Str(text.to_string()),
+            FieldValue::PreTokenized(tokens) => {
+                tantivy::schema::OwnedValue::PreTokStr(pre_tokenized_string(tokens))
+            }
             FieldValue::I64(num) => tantivy::schema::OwnedValue::I64(*num),
+            FieldValue::U64(num) => tantivy::schema::OwnedValue::U64(*num),
+            FieldValue::Bool(flag) => tantivy::schema::OwnedValue::Bool(*flag),
             FieldValue::F64(num) => tantivy::schema::OwnedValue::F64(*num),
             FieldValue::Date(date) => {
                 let timestamp = date.timestamp();
@@ -236,19 +1006,33 @@ impl SchemaManager {
                 tantivy::schema::OwnedValue::Facet(facet_path)
             }
             FieldValue::Bytes(bytes) => tantivy::schema::OwnedValue::Bytes(bytes.to_vec()),
+            FieldValue::Json(object) => tantivy::schema::OwnedValue::from(object.clone()),
+            FieldValue::GeoPoint { .. } => {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Geo field '{}' is indexed as two values, see `get_geo_fields`",
+                    field_name
+                )));
+            }
         };
 
         Ok(tantivy_value)
     }
 
     /// Convert Tantivy document to our format
+    ///
+    /// When `projection` is set, only the named fields are materialized.
     pub fn document_from_tantivy(
         &self,
         doc: &impl tantivy::Document,
+        projection: Option<&[String]>,
     ) -> Result<HashMap<String, FieldValue>> {
         let mut fields = HashMap::new();
 
         for (field_name, field) in &self.field_map {
+            if projection.is_some_and(|names| !names.contains(field_name)) {
+                continue;
+            }
+
             // Collect all values for this field from the document
             let mut values = Vec::new();
             for (_field, value) in doc.iter_fields_and_values() {
@@ -257,56 +1041,714 @@ impl SchemaManager {
                 }
             }
 
-            if !values.is_empty() {
-                if let Some(value) = values.first() {
-                    // Use pattern matching without trying to match on the enum variant directly
-                    let field_value = if let Some(s) = value.as_str() {
-                        FieldValue::Text(s.to_string())
-                    } else if let Some(i) = value.as_i64() {
-                        FieldValue::I64(i)
-                    } else if let Some(f) = value.as_f64() {
-                        FieldValue::F64(f)
-                    } else if let Some(d) = value.as_datetime() {
-                        let timestamp = d.into_timestamp_secs();
-                        let dt = chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
-                        FieldValue::Date(dt)
-                    } else if let Some(f) = value.as_facet() {
-                        FieldValue::Facet(f.to_string())
-                    } else if let Some(b) = value.as_bytes() {
-                        FieldValue::Bytes(b.to_vec())
-                    } else {
-                        continue;
-                    };
-                    fields.insert(field_name.clone(), field_value);
-                }
+            if !values.is_empty()
+                && let Some(value) = values.first()
+            {
+                // Use pattern matching without trying to match on the enum variant directly
+                let field_value = if let Some(s) = value.as_str() {
+                    FieldValue::Text(s.to_string())
+                } else if let Some(i) = value.as_i64() {
+                    FieldValue::I64(i)
+                } else if let Some(u) = value.as_u64() {
+                    FieldValue::U64(u)
+                } else if let Some(b) = value.as_bool() {
+                    FieldValue::Bool(b)
+                } else if let Some(f) = value.as_f64() {
+                    FieldValue::F64(f)
+                } else if let Some(d) = value.as_datetime() {
+                    let timestamp = d.into_timestamp_secs();
+                    let dt = chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+                    FieldValue::Date(dt)
+                } else if let Some(f) = value.as_facet() {
+                    // Stored facets come back in their encoded form
+                    let facet = tantivy::schema::Facet::from_encoded(f.as_bytes().to_vec())
+                        .map_err(|e| {
+                            SearchEngineError::SchemaError(format!("Invalid stored facet: {}", e))
+                        })?;
+                    FieldValue::Facet(facet.to_string())
+                } else if let Some(b) = value.as_bytes() {
+                    FieldValue::Bytes(b.to_vec())
+                } else if value.as_object().is_some() {
+                    let object = tantivy::schema::OwnedValue::from(value.as_value());
+                    FieldValue::Json(serde_json::to_value(object)?)
+                } else {
+                    continue;
+                };
+                fields.insert(field_name.clone(), field_value);
+            }
+        }
+
+        for (field_name, (lat_field, lon_field)) in &self.geo_fields {
+            if projection.is_some_and(|names| !names.contains(field_name)) {
+                continue;
+            }
+
+            let first_f64 = |field: Field| {
+                doc.iter_fields_and_values()
+                    .find(|(f, _)| *f == field)
+                    .and_then(|(_, value)| value.as_f64())
+            };
+            if let (Some(lat), Some(lon)) = (first_f64(*lat_field), first_f64(*lon_field)) {
+                fields.insert(field_name.clone(), FieldValue::GeoPoint { lat, lon });
             }
         }
         Ok(fields)
     }
 
+    /// Check that a document has a value for the primary key and every required field
+    pub fn validate_required_fields(&self, doc: &IndexDocument) -> Result<()> {
+        if doc.id.is_empty() {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Document is missing its ID field '{}'",
+                self.id_field_name
+            )));
+        }
+
+        match self
+            .schema_def
+            .required
+            .iter()
+            .find(|name| **name != self.id_field_name && !doc.fields.contains_key(*name))
+        {
+            Some(name) => Err(SearchEngineError::SchemaError(format!(
+                "Document '{}' is missing required field '{}'",
+                doc.id, name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Run text through a text field's tokenizer and filters, returning the tokens
+    ///
+    /// The tokens are the terms the field would index for the text, so this
+    /// shows why a query does or does not match.
+    pub fn analyze(&self, field_name: &str, text: &str) -> Result<Vec<String>> {
+        let Some(FieldType::Text {
+            tokenizer,
+            stop_words,
+            normalize,
+            ..
+        }) = self.schema_def.fields.get(field_name)
+        else {
+            return Err(match self.schema_def.fields.get(field_name) {
+                Some(_) => SearchEngineError::SchemaError(format!(
+                    "Field '{}' is not a text field",
+                    field_name
+                )),
+                None => SearchEngineError::FieldNotFound {
+                    field: field_name.to_string(),
+                },
+            });
+        };
+
+        let mut analyzer = match normalize {
+            Some(_) => keyword_lowercase_analyzer(),
+            None => self.tokenizer_analyzer(tokenizer)?,
+        };
+        if let Some(words) = stop_words
+            && !words.is_empty()
+        {
+            analyzer = with_stop_words(analyzer, words);
+        }
+
+        let mut tokens = Vec::new();
+        analyzer
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.text.clone()));
+        Ok(tokens)
+    }
+
+    /// Analyzer registered under a tokenizer name for this schema's collections
+    fn tokenizer_analyzer(&self, name: &str) -> Result<TextAnalyzer> {
+        if let Some(definition) = self.schema_def.tokenizers.get(name) {
+            return build_text_analyzer(name, definition);
+        }
+        if let Some((_, language)) = STEMMERS.iter().find(|(stemmer, _)| *stemmer == name) {
+            return Ok(stemming_analyzer(*language));
+        }
+        match name {
+            "simple" => Ok(simple_analyzer()),
+            // Keyword fields are indexed as a single untokenized term
+            "keyword" => Ok(TextAnalyzer::from(RawTokenizer::default())),
+            _ => TokenizerManager::default().get(name).ok_or_else(|| {
+                SearchEngineError::SchemaError(format!("Unknown tokenizer '{}'", name))
+            }),
+        }
+    }
+
     /// Validate field value against schema
     pub fn validate_field_value(&self, field_name: &str, value: &FieldValue) -> Result<()> {
         let field_type = self.schema_def.fields.get(field_name).ok_or_else(|| {
-            SearchEngineError::SchemaError(format!("Field '{}' not found in schema", field_name))
+            SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            }
         })?;
 
-        let is_valid = match (field_type, value) {
-            (FieldType::Text { .. }, FieldValue::Text(_)) => true,
-            (FieldType::I64 { .. }, FieldValue::I64(_)) => true,
-            (FieldType::F64 { .. }, FieldValue::F64(_)) => true,
-            (FieldType::Date { .. }, FieldValue::Date(_)) => true,
-            (FieldType::Facet, FieldValue::Facet(_)) => true,
-            (FieldType::Bytes { .. }, FieldValue::Bytes(_)) => true,
-            _ => false,
-        };
+        let is_valid = matches!(
+            (field_type, value),
+            (FieldType::Text { .. }, FieldValue::Text(_))
+                | (FieldType::Text { .. }, FieldValue::PreTokenized(_))
+                | (FieldType::I64 { .. }, FieldValue::I64(_))
+                | (FieldType::U64 { .. }, FieldValue::U64(_))
+                | (FieldType::Bool { .. }, FieldValue::Bool(_))
+                | (FieldType::F64 { .. }, FieldValue::F64(_))
+                | (FieldType::Date { .. }, FieldValue::Date(_))
+                | (FieldType::Facet { .. }, FieldValue::Facet(_))
+                | (FieldType::Bytes { .. }, FieldValue::Bytes(_))
+                | (FieldType::Geo { .. }, FieldValue::GeoPoint { .. })
+                | (FieldType::Json { .. }, FieldValue::Json(_))
+        );
 
         if !is_valid {
+            return Err(SearchEngineError::TypeMismatch {
+                field: field_name.to_string(),
+                expected: field_type.type_name().to_string(),
+                got: value.type_name().to_string(),
+            });
+        }
+
+        if let FieldValue::Json(object) = value
+            && !object.is_object()
+        {
             return Err(SearchEngineError::SchemaError(format!(
-                "Field '{}' type mismatch. Expected {:?}, got {:?}",
-                field_name, field_type, value
+                "JSON field '{}' expects an object",
+                field_name
+            )));
+        }
+
+        if let (FieldType::Facet { max_depth, .. }, FieldValue::Facet(path)) = (field_type, value) {
+            validate_facet_path(field_name, path, *max_depth)?;
+        }
+
+        if let FieldValue::PreTokenized(_) = value {
+            let has_positions = self.field_map.get(field_name).is_some_and(|field| {
+                matches!(
+                    self.tantivy_schema.get_field_entry(*field).field_type(),
+                    tantivy::schema::FieldType::Str(options)
+                        if options
+                            .get_indexing_options()
+                            .is_some_and(|indexing| indexing.index_option().has_positions())
+                )
+            });
+            if !has_positions {
+                return Err(SearchEngineError::SchemaError(format!(
+                    "Field '{}' must be a text field indexed with positions to take pre-tokenized text",
+                    field_name
+                )));
+            }
+        }
+
+        if let FieldValue::GeoPoint { lat, lon } = value
+            && !((-90.0..=90.0).contains(lat) && (-180.0..=180.0).contains(lon))
+        {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Geo point ({}, {}) for field '{}' is out of range",
+                lat, lon, field_name
             )));
         }
 
         Ok(())
     }
 }
+
+/// Tokenizer running a registered analyzer, so further filters can be chained onto it
+#[derive(Clone)]
+struct AnalyzerTokenizer(TextAnalyzer);
+
+impl Tokenizer for AnalyzerTokenizer {
+    type TokenStream<'a> = BoxTokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        self.0.token_stream(text)
+    }
+}
+
+/// Build the analyzer of a stemming tokenizer, matching Tantivy's `en_stem` pipeline
+pub fn stemming_analyzer(language: Language) -> TextAnalyzer {
+    TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(Stemmer::new(language))
+        .build()
+}
+
+/// Analyzer of the `simple` tokenizer: split on non-alphanumeric characters and lowercase
+pub fn simple_analyzer() -> TextAnalyzer {
+    TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(LowerCaser)
+        .build()
+}
+
+/// Analyzer of lowercase-normalized keyword fields: the whole value as one lowercased term
+pub fn keyword_lowercase_analyzer() -> TextAnalyzer {
+    TextAnalyzer::builder(RawTokenizer::default())
+        .filter(LowerCaser)
+        .build()
+}
+
+/// Extend an analyzer with a filter removing the given stop words
+pub fn with_stop_words(analyzer: TextAnalyzer, stop_words: &[String]) -> TextAnalyzer {
+    TextAnalyzer::builder(AnalyzerTokenizer(analyzer))
+        .filter(StopWordFilter::remove(stop_words.iter().cloned()))
+        .build()
+}
+
+/// Build the analyzer for a custom tokenizer pipeline
+fn build_text_analyzer(name: &str, definition: &TokenizerDefinition) -> Result<TextAnalyzer> {
+    let builder = match &definition.base {
+        BaseTokenizer::Simple => TextAnalyzer::builder(SimpleTokenizer::default()).dynamic(),
+        BaseTokenizer::Whitespace => {
+            TextAnalyzer::builder(WhitespaceTokenizer::default()).dynamic()
+        }
+        BaseTokenizer::Raw => TextAnalyzer::builder(RawTokenizer::default()).dynamic(),
+        BaseTokenizer::Ngram {
+            min_gram,
+            max_gram,
+            prefix_only,
+        } => {
+            let tokenizer =
+                NgramTokenizer::new(*min_gram, *max_gram, *prefix_only).map_err(|e| {
+                    SearchEngineError::SchemaError(format!("Invalid tokenizer '{}': {}", name, e))
+                })?;
+            TextAnalyzer::builder(tokenizer).dynamic()
+        }
+    };
+
+    let builder = definition
+        .filters
+        .iter()
+        .fold(builder, |builder, filter| match filter {
+            TokenFilterDefinition::Lowercase => builder.filter_dynamic(LowerCaser),
+            TokenFilterDefinition::RemoveLong(max_bytes) => {
+                builder.filter_dynamic(RemoveLongFilter::limit(*max_bytes))
+            }
+            TokenFilterDefinition::StopWords(words) => {
+                builder.filter_dynamic(StopWordFilter::remove(words.iter().cloned()))
+            }
+            TokenFilterDefinition::Stemmer(language) => {
+                builder.filter_dynamic(Stemmer::new(*language))
+            }
+        });
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_with_tokenizer(tokenizer: &str) -> SchemaDefinition {
+        let mut fields = HashMap::new();
+        fields.insert(
+            "body".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: tokenizer.to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        SchemaDefinition {
+            name: "docs".to_string(),
+            fields,
+            primary_key: None,
+            required: Vec::new(),
+            allowed_query_types: None,
+            tokenizers: HashMap::new(),
+            heap_size: None,
+        }
+    }
+
+    #[test]
+    fn test_facet_path_validation() {
+        let mut schema = schema_with_tokenizer("default");
+        schema.fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: true,
+                max_depth: Some(3),
+            },
+        );
+        let manager = SchemaManager::new(schema).unwrap();
+        let check = |path: &str| {
+            manager.validate_field_value("category", &FieldValue::Facet(path.to_string()))
+        };
+
+        assert!(check("/electronics/audio/headphones").is_ok());
+        // An escaped separator stays inside its component
+        assert!(check("/electronics/tv\\/audio").is_ok());
+        assert!(matches!(
+            check("/electronics/audio/headphones/wireless"),
+            Err(SearchEngineError::SchemaError(msg)) if msg.contains("maximum of 3")
+        ));
+        for unescaped in ["/electronics//audio", "/electronics/audio/"] {
+            assert!(matches!(
+                check(unescaped),
+                Err(SearchEngineError::SchemaError(msg)) if msg.contains("empty path component")
+            ));
+        }
+        assert!(check("electronics").is_err());
+
+        let mut schema = schema_with_tokenizer("default");
+        schema.fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: true,
+                max_depth: Some(0),
+            },
+        );
+        assert!(SchemaManager::new(schema).is_err());
+    }
+
+    #[test]
+    fn test_unknown_tokenizer_rejected() {
+        for tokenizer in TOKENIZERS {
+            assert!(SchemaManager::new(schema_with_tokenizer(tokenizer)).is_ok());
+        }
+
+        let err = SchemaManager::new(schema_with_tokenizer("en_stemm"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg)
+                if msg.contains("'en_stemm'") && msg.contains("default, simple, en_stem, keyword")
+        ));
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let valid = schema_with_tokenizer("default");
+        assert!(SchemaManager::validate(&valid).is_ok());
+
+        let is_schema_error = |schema: &SchemaDefinition, expected: &str| {
+            matches!(
+                SchemaManager::validate(schema),
+                Err(SearchEngineError::SchemaError(msg)) if msg.contains(expected)
+            )
+        };
+
+        let mut reserved_id = valid.clone();
+        reserved_id.fields.insert(
+            DEFAULT_ID_FIELD.to_string(),
+            reserved_id.fields["body"].clone(),
+        );
+        assert!(is_schema_error(&reserved_id, "'_id' is reserved"));
+
+        let mut useless = valid.clone();
+        useless.fields.insert(
+            "hidden".to_string(),
+            FieldType::Text {
+                stored: false,
+                indexed: false,
+                tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        assert!(is_schema_error(
+            &useless,
+            "'hidden' is neither stored nor indexed"
+        ));
+
+        let mut missing_key = valid.clone();
+        missing_key.primary_key = Some("sku".to_string());
+        assert!(is_schema_error(&missing_key, "Primary key 'sku'"));
+
+        let normalized = |tokenizer: &str, normalize: &str| {
+            let mut schema = valid.clone();
+            schema.fields.insert(
+                "brand".to_string(),
+                FieldType::Text {
+                    stored: true,
+                    indexed: true,
+                    tokenizer: tokenizer.to_string(),
+                    stop_words: None,
+                    normalize: Some(normalize.to_string()),
+                },
+            );
+            schema
+        };
+        // Tokenizer options are checked when the manager is built
+        let normalize_error = |schema: SchemaDefinition, expected: &str| {
+            matches!(
+                SchemaManager::new(schema),
+                Err(SearchEngineError::SchemaError(msg)) if msg.contains(expected)
+            )
+        };
+        assert!(SchemaManager::new(normalized("keyword", "lowercase")).is_ok());
+        assert!(normalize_error(
+            normalized("default", "lowercase"),
+            "only applies to keyword fields"
+        ));
+        assert!(normalize_error(
+            normalized("keyword", "uppercase"),
+            "unknown normalizer 'uppercase'"
+        ));
+
+        let declared_key = |stored: bool, tokenizer: &str| {
+            let mut schema = valid.clone();
+            schema.fields.insert(
+                "sku".to_string(),
+                FieldType::Text {
+                    stored,
+                    indexed: true,
+                    tokenizer: tokenizer.to_string(),
+                    stop_words: None,
+                    normalize: None,
+                },
+            );
+            schema.primary_key = Some("sku".to_string());
+            schema
+        };
+        assert!(SchemaManager::validate(&declared_key(true, "keyword")).is_ok());
+        assert!(is_schema_error(
+            &declared_key(true, "default"),
+            "Primary key field 'sku' must be"
+        ));
+        assert!(is_schema_error(
+            &declared_key(false, "keyword"),
+            "Primary key field 'sku' must be"
+        ));
+    }
+
+    #[test]
+    fn test_analyze_field_text() {
+        let schema = SchemaManager::new(schema_with_tokenizer("en_stem")).unwrap();
+        assert_eq!(
+            schema.analyze("body", "Running dogs").unwrap(),
+            vec!["run".to_string(), "dog".to_string()]
+        );
+
+        assert!(matches!(
+            schema.analyze("missing", "text"),
+            Err(SearchEngineError::FieldNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_field_capabilities() {
+        let mut schema_def = schema_with_tokenizer("default");
+        schema_def.fields.insert(
+            "views".to_string(),
+            FieldType::I64 {
+                stored: false,
+                indexed: true,
+                fast: true,
+            },
+        );
+        schema_def.fields.insert(
+            "rating".to_string(),
+            FieldType::F64 {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        let schema = SchemaManager::new(schema_def).unwrap();
+
+        assert_eq!(schema.fast_fields(), vec!["views".to_string()]);
+        assert_eq!(
+            schema.field_capabilities(),
+            vec![
+                FieldCapabilities {
+                    name: "body".to_string(),
+                    stored: true,
+                    indexed: true,
+                    fast: false,
+                },
+                FieldCapabilities {
+                    name: "rating".to_string(),
+                    stored: true,
+                    indexed: true,
+                    fast: false,
+                },
+                FieldCapabilities {
+                    name: "views".to_string(),
+                    stored: false,
+                    indexed: true,
+                    fast: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tantivy_json_round_trip() {
+        let mut schema_def = schema_with_tokenizer("en_stem");
+        schema_def.fields.insert(
+            "tag".to_string(),
+            FieldType::Text {
+                stored: false,
+                indexed: true,
+                tokenizer: "keyword".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        schema_def.fields.insert(
+            "views".to_string(),
+            FieldType::I64 {
+                stored: true,
+                indexed: true,
+                fast: true,
+            },
+        );
+        schema_def.fields.insert(
+            "published".to_string(),
+            FieldType::Date {
+                stored: true,
+                indexed: false,
+                fast: true,
+            },
+        );
+        let schema = SchemaManager::new(schema_def.clone()).unwrap();
+
+        let json = schema.to_tantivy_json().unwrap();
+        let restored = SchemaManager::from_tantivy_json("docs", &json).unwrap();
+        assert_eq!(restored.schema_definition().fields, schema_def.fields);
+        assert_eq!(restored.to_tantivy_json().unwrap(), json);
+
+        // Stop words live in a per-field tokenizer Tantivy only knows by name
+        schema_def.fields.insert(
+            "body".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "default".to_string(),
+                stop_words: Some(vec!["the".to_string()]),
+                normalize: None,
+            },
+        );
+        let json = SchemaManager::new(schema_def)
+            .unwrap()
+            .to_tantivy_json()
+            .unwrap();
+        let err = SchemaManager::from_tantivy_json("docs", &json)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg)
+                if msg == "Field 'body' cannot be represented: tokenizer 'body.default.stop_words' is not built in"
+        ));
+    }
+
+    #[test]
+    fn test_index_document_from_json() {
+        let mut schema_def = schema_with_tokenizer("default");
+        schema_def.fields.insert(
+            "views".to_string(),
+            FieldType::I64 {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        schema_def.fields.insert(
+            "rating".to_string(),
+            FieldType::F64 {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        let schema = SchemaManager::new(schema_def).unwrap();
+
+        let doc = IndexDocument::from_json(
+            &schema,
+            serde_json::json!({
+                "_id": 7,
+                "body": "hello",
+                "views": 42,
+                "rating": 4,
+            }),
+        )
+        .unwrap();
+        assert_eq!(doc.id, "7");
+        assert_eq!(doc.fields["body"], FieldValue::Text("hello".to_string()));
+        assert_eq!(doc.fields["views"], FieldValue::I64(42));
+        // Integers are widened for f64 fields
+        assert_eq!(doc.fields["rating"], FieldValue::F64(4.0));
+
+        let err =
+            IndexDocument::from_json(&schema, serde_json::json!({ "_id": "1", "views": "many" }))
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg)
+                if msg == "Field 'views' expects i64, got a string"
+        ));
+    }
+
+    #[test]
+    fn test_date_from_rfc3339_or_epoch_seconds() {
+        let date_type = FieldType::Date {
+            stored: true,
+            indexed: true,
+            fast: false,
+        };
+        let expected = FieldValue::Date(
+            chrono::DateTime::parse_from_rfc3339("2023-01-02T03:04:05Z")
+                .unwrap()
+                .with_timezone(&chrono::Utc),
+        );
+
+        for input in [
+            serde_json::json!("2023-01-02T03:04:05Z"),
+            serde_json::json!(1672628645),
+        ] {
+            assert_eq!(
+                FieldValue::from_json_for_type("created", &date_type, &input).unwrap(),
+                expected
+            );
+            let value: FieldValue =
+                serde_json::from_value(serde_json::json!({ "Date": input })).unwrap();
+            assert_eq!(value, expected);
+        }
+
+        assert!(
+            FieldValue::from_json_for_type("created", &date_type, &serde_json::json!(true))
+                .is_err()
+        );
+        assert!(serde_json::from_str::<FieldValue>(r#"{"Date": "yesterday"}"#).is_err());
+    }
+
+    #[test]
+    fn test_unknown_stemmer_rejected() {
+        let err = SchemaManager::new(schema_with_tokenizer("xx_stem"))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            SearchEngineError::SchemaError(msg)
+                if msg.contains("Unknown stemmer 'xx_stem'") && msg.contains("fr_stem")
+        ));
+    }
+
+    #[test]
+    fn test_custom_tokenizer_validation() {
+        let mut schema = schema_with_tokenizer("trigram");
+        assert!(SchemaManager::new(schema.clone()).is_err());
+
+        schema
+            .tokenizers
+            .insert("trigram".to_string(), TokenizerDefinition::ngram(3, 3));
+        assert!(SchemaManager::new(schema.clone()).is_ok());
+
+        schema
+            .tokenizers
+            .insert("trigram".to_string(), TokenizerDefinition::ngram(3, 2));
+        assert!(SchemaManager::new(schema.clone()).is_err());
+
+        // Built-in tokenizers cannot be redefined
+        schema
+            .tokenizers
+            .insert("trigram".to_string(), TokenizerDefinition::ngram(3, 3));
+        schema.tokenizers.insert(
+            "default".to_string(),
+            TokenizerDefinition::ngram(3, 3).lowercase(),
+        );
+        assert!(SchemaManager::new(schema).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use tantivy::columnar::Column;
+use tantivy::{DateTime, DocId, SegmentReader};
+
+use crate::types::FieldType;
+
+/// Type of a fast numeric or date field read by queries and aggregations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumericColumn {
+    I64,
+    U64,
+    F64,
+    /// Read as a Unix timestamp in seconds
+    Date,
+}
+
+impl NumericColumn {
+    /// Column type of a field, if it is a fast numeric or date field
+    pub(crate) fn of(field_type: &FieldType) -> Option<Self> {
+        match field_type {
+            FieldType::I64 { fast: true, .. } => Some(NumericColumn::I64),
+            FieldType::U64 { fast: true, .. } => Some(NumericColumn::U64),
+            FieldType::F64 { fast: true, .. } => Some(NumericColumn::F64),
+            FieldType::Date { fast: true, .. } => Some(NumericColumn::Date),
+            _ => None,
+        }
+    }
+}
+
+/// Fast field column of one segment, with values widened to `f64`
+pub(crate) enum FieldColumn {
+    I64(Column<i64>),
+    U64(Column<u64>),
+    F64(Column<f64>),
+    Date(Column<DateTime>),
+}
+
+impl FieldColumn {
+    pub(crate) fn open(
+        reader: &SegmentReader,
+        field: &str,
+        column: NumericColumn,
+    ) -> tantivy::Result<Self> {
+        let fast_fields = reader.fast_fields();
+        Ok(match column {
+            NumericColumn::I64 => FieldColumn::I64(fast_fields.i64(field)?),
+            NumericColumn::U64 => FieldColumn::U64(fast_fields.u64(field)?),
+            NumericColumn::F64 => FieldColumn::F64(fast_fields.f64(field)?),
+            NumericColumn::Date => FieldColumn::Date(fast_fields.date(field)?),
+        })
+    }
+
+    /// First value of a document, or `None` if it has none
+    pub(crate) fn value(&self, doc: DocId) -> Option<f64> {
+        match self {
+            FieldColumn::I64(column) => column.first(doc).map(|value| value as f64),
+            FieldColumn::U64(column) => column.first(doc).map(|value| value as f64),
+            FieldColumn::F64(column) => column.first(doc),
+            FieldColumn::Date(column) => column
+                .first(doc)
+                .map(|value| value.into_timestamp_secs() as f64),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::{Bound, RangeBounds};
+use tantivy::columnar::StrColumn;
+use tantivy::schema::{Field, Value};
+use tantivy::store::StoreReader;
+use tantivy::{
+    DocAddress, DocId, Score, Searcher, SegmentOrdinal, SegmentReader, TantivyDocument,
+    TantivyError,
+    collector::{Collector, SegmentCollector},
+};
+
+use super::boost_score;
+
+/// Number of decompressed doc store blocks cached while reading hit IDs
+const STORE_CACHE_BLOCKS: usize = 10;
+
+/// Hit ranked by boosted score, highest first, then by tie key, lowest first
+#[derive(Debug)]
+pub(crate) struct RankedHit<K> {
+    score: Score,
+    key: K,
+    address: DocAddress,
+}
+
+impl<K: Ord> Ord for RankedHit<K> {
+    /// Hits that rank earlier compare as smaller
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.key.cmp(&other.key))
+    }
+}
+
+impl<K: Ord> PartialOrd for RankedHit<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for RankedHit<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for RankedHit<K> {}
+
+/// Stand-in for a document ID when ordering the hits of one segment
+///
+/// Term ordinals of a fast ID column follow ID order within a segment, so they
+/// order hits without reading the ID itself.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum TieKey {
+    Ord(u64),
+    Id(String),
+}
+
+/// Where the IDs of a segment's documents are read from
+enum SegmentIds {
+    /// Fast ID column, present in collections created with a fast ID field
+    Fast(StrColumn),
+    /// Doc store, for collections whose ID field is not a fast field
+    Store {
+        reader: StoreReader,
+        id_field: Field,
+    },
+}
+
+impl SegmentIds {
+    fn open(segment: &SegmentReader, id_field: Field) -> tantivy::Result<Self> {
+        let field_entry = segment.schema().get_field_entry(id_field);
+        if field_entry.is_fast()
+            && let Some(column) = segment.fast_fields().str(field_entry.name())?
+        {
+            return Ok(SegmentIds::Fast(column));
+        }
+        Ok(SegmentIds::Store {
+            reader: segment.get_store_reader(STORE_CACHE_BLOCKS)?,
+            id_field,
+        })
+    }
+
+    fn key(&self, doc: DocId) -> tantivy::Result<TieKey> {
+        match self {
+            SegmentIds::Fast(column) => column
+                .ords()
+                .first(doc)
+                .map(TieKey::Ord)
+                .ok_or_else(|| missing_id(doc)),
+            SegmentIds::Store { reader, id_field } => {
+                let document: TantivyDocument = reader.get(doc)?;
+                document
+                    .get_first(*id_field)
+                    .and_then(|value| value.as_str())
+                    .map(|id| TieKey::Id(id.to_string()))
+                    .ok_or_else(|| missing_id(doc))
+            }
+        }
+    }
+
+    fn id(&self, key: TieKey) -> tantivy::Result<String> {
+        match (self, key) {
+            (_, TieKey::Id(id)) => Ok(id),
+            (SegmentIds::Fast(column), TieKey::Ord(ord)) => {
+                let mut id = String::new();
+                column.ord_to_str(ord, &mut id)?;
+                Ok(id)
+            }
+            (SegmentIds::Store { .. }, TieKey::Ord(_)) => {
+                unreachable!("doc store IDs are not ordinals")
+            }
+        }
+    }
+
+    /// Bound on the tie keys of documents whose ID sorts after `id`
+    fn after(&self, id: &str) -> tantivy::Result<Bound<TieKey>> {
+        match self {
+            SegmentIds::Fast(column) => {
+                let (lower, _) = column
+                    .dictionary()
+                    .term_bounds_to_ord(Bound::Excluded(id), Bound::Unbounded)?;
+                Ok(lower.map(TieKey::Ord))
+            }
+            SegmentIds::Store { .. } => Ok(Bound::Excluded(TieKey::Id(id.to_string()))),
+        }
+    }
+}
+
+fn missing_id(doc: DocId) -> TantivyError {
+    TantivyError::InvalidArgument(format!("Document #({}) has no ID", doc))
+}
+
+/// Order hits that share a score by ID, ascending
+///
+/// Hits must already be ordered by score. IDs are only read when some hits tie.
+pub(crate) fn sort_ties_by_id(
+    searcher: &Searcher,
+    id_field: Field,
+    hits: &mut Vec<(Score, DocAddress)>,
+) -> tantivy::Result<()> {
+    if !hits.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Ok(());
+    }
+
+    let mut segment_ids = HashMap::new();
+    let mut ranked = Vec::with_capacity(hits.len());
+    for &(score, address) in hits.iter() {
+        let ids = match segment_ids.entry(address.segment_ord) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let segment = searcher.segment_reader(address.segment_ord);
+                entry.insert(SegmentIds::open(segment, id_field)?)
+            }
+        };
+        ranked.push(RankedHit {
+            score,
+            key: ids.id(ids.key(address.doc_id)?)?,
+            address,
+        });
+    }
+    ranked.sort();
+    *hits = ranked
+        .into_iter()
+        .map(|hit| (hit.score, hit.address))
+        .collect();
+    Ok(())
+}
+
+/// Collector for the top documents, optionally ranked after a `(score, id)` cursor
+///
+/// Hits are ordered by boosted score, then by ID to break ties, so the order is
+/// deterministic and every hit has a unique position a cursor can point to.
+/// With a fast ID field, ties are broken by term ordinal within each segment and
+/// only the IDs of the top `limit` hits are read; otherwise IDs are read from
+/// the doc store for documents that could still enter the top `limit`.
+pub(crate) struct SearchAfterCollector {
+    limit: usize,
+    after: Option<(Score, String)>,
+    id_field: Field,
+}
+
+impl SearchAfterCollector {
+    pub(crate) fn new(limit: usize, after: Option<(Score, String)>, id_field: Field) -> Self {
+        Self {
+            limit,
+            after,
+            id_field,
+        }
+    }
+}
+
+impl Collector for SearchAfterCollector {
+    type Fruit = Vec<(Score, DocAddress)>;
+    type Child = SearchAfterSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let ids = SegmentIds::open(segment, self.id_field)?;
+        let after = match &self.after {
+            Some((score, id)) => Some((*score, ids.after(id)?)),
+            None => None,
+        };
+        Ok(SearchAfterSegmentCollector {
+            segment_ord: segment_local_id,
+            limit: self.limit,
+            after,
+            ids,
+            boost: Box::new(boost_score(segment)),
+            hits: BinaryHeap::new(),
+            error: None,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<tantivy::Result<Vec<RankedHit<String>>>>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut hits = Vec::new();
+        for segment_hits in segment_fruits {
+            hits.extend(segment_hits?);
+        }
+        hits.sort();
+        Ok(hits
+            .into_iter()
+            .take(self.limit)
+            .map(|hit| (hit.score, hit.address))
+            .collect())
+    }
+}
+
+pub(crate) struct SearchAfterSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    limit: usize,
+    /// Cursor score, and the tie keys ranked after the cursor at that score
+    after: Option<(Score, Bound<TieKey>)>,
+    ids: SegmentIds,
+    boost: Box<dyn Fn(DocId, Score) -> Score + Send>,
+    /// Best hits so far, with the worst on top
+    hits: BinaryHeap<RankedHit<TieKey>>,
+    /// First ID read error, reported when the segment is harvested
+    error: Option<TantivyError>,
+}
+
+impl SegmentCollector for SearchAfterSegmentCollector {
+    type Fruit = tantivy::Result<Vec<RankedHit<String>>>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.error.is_some() || self.limit == 0 {
+            return;
+        }
+
+        let score = (self.boost)(doc, score);
+        // Scores above the cursor were returned on earlier pages
+        if let Some((after_score, _)) = &self.after
+            && score > *after_score
+        {
+            return;
+        }
+        // Lower-scored documents cannot displace a full page
+        if self.hits.len() == self.limit
+            && self.hits.peek().is_some_and(|worst| score < worst.score)
+        {
+            return;
+        }
+
+        let key = match self.ids.key(doc) {
+            Ok(key) => key,
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+        if let Some((after_score, after_key)) = &self.after
+            && score == *after_score
+            && !(after_key.as_ref(), Bound::Unbounded).contains(&key)
+        {
+            return;
+        }
+
+        self.hits.push(RankedHit {
+            score,
+            key,
+            address: DocAddress::new(self.segment_ord, doc),
+        });
+        if self.hits.len() > self.limit {
+            self.hits.pop();
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.hits
+            .into_vec()
+            .into_iter()
+            .map(|hit| {
+                Ok(RankedHit {
+                    score: hit.score,
+                    key: self.ids.id(hit.key)?,
+                    address: hit.address,
+                })
+            })
+            .collect()
+    }
+}
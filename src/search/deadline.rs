@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tantivy::{
+    DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED,
+    collector::{Collector, SegmentCollector},
+    query::Weight,
+};
+
+/// Number of documents collected between two deadline checks
+const CHECK_INTERVAL: u32 = 1024;
+
+/// Collector wrapper that stops collecting once a deadline has passed
+///
+/// Documents collected before the deadline are kept, so the wrapped collector's
+/// fruit holds the partial results. Whether the deadline was hit is reported by
+/// `timed_out` after the search.
+pub(crate) struct DeadlineCollector<C> {
+    inner: C,
+    deadline: Option<Instant>,
+    timed_out: AtomicBool,
+}
+
+impl<C> DeadlineCollector<C> {
+    pub(crate) fn new(inner: C, deadline: Option<Instant>) -> Self {
+        Self {
+            inner,
+            deadline,
+            timed_out: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether collection was cut short by the deadline
+    pub(crate) fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+}
+
+impl<C: Collector> Collector for DeadlineCollector<C> {
+    type Fruit = C::Fruit;
+    type Child = C::Child;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        self.inner.for_segment(segment_local_id, segment)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        self.inner.merge_fruits(segment_fruits)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let Some(deadline) = self.deadline else {
+            return self.inner.collect_segment(weight, segment_ord, reader);
+        };
+
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        // Segments reached after the deadline contribute nothing. Building a
+        // scorer can be expensive too, such as expanding a regex, so it is not
+        // started once the deadline has passed.
+        if self.timed_out() || Instant::now() >= deadline {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return Ok(segment_collector.harvest());
+        }
+
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let alive_bitset = reader.alive_bitset();
+        let requires_scoring = self.requires_scoring();
+        let mut since_check = 0;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED {
+            if alive_bitset.is_none_or(|bitset| bitset.is_alive(doc)) {
+                let score: Score = if requires_scoring {
+                    scorer.score()
+                } else {
+                    0.0
+                };
+                segment_collector.collect(doc, score);
+            }
+
+            since_check += 1;
+            if since_check == CHECK_INTERVAL {
+                since_check = 0;
+                if Instant::now() >= deadline {
+                    self.timed_out.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+            doc = scorer.advance();
+        }
+        if Instant::now() >= deadline {
+            self.timed_out.store(true, Ordering::Relaxed);
+        }
+
+        Ok(segment_collector.harvest())
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use tantivy::query::{AutomatonWeight, EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::schema::Field;
+use tantivy::{DocId, Score, SegmentReader, TantivyError};
+use tantivy_fst::Regex;
+
+/// Regex query that refuses to expand to more than `max_expansions` terms
+///
+/// A regex matches every term of the field its automaton accepts, so a broad
+/// pattern such as `.*` can expand to the whole term dictionary. Matching terms
+/// are counted in each segment before its documents are read, and the search
+/// fails once a segment has more than `max_expansions` of them.
+#[derive(Debug, Clone)]
+pub(crate) struct BoundedRegexQuery {
+    field: Field,
+    regex: Arc<Regex>,
+    max_expansions: usize,
+}
+
+impl BoundedRegexQuery {
+    pub(crate) fn from_pattern(
+        pattern: &str,
+        field: Field,
+        max_expansions: usize,
+    ) -> tantivy::Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| TantivyError::InvalidArgument(format!("{}: {}", pattern, e)))?;
+        Ok(Self {
+            field,
+            regex: Arc::new(regex),
+            max_expansions,
+        })
+    }
+}
+
+impl Query for BoundedRegexQuery {
+    fn weight(&self, _enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(BoundedRegexWeight {
+            weight: AutomatonWeight::new(self.field, self.regex.clone()),
+            field: self.field,
+            regex: self.regex.clone(),
+            max_expansions: self.max_expansions,
+        }))
+    }
+}
+
+struct BoundedRegexWeight {
+    weight: AutomatonWeight<Regex>,
+    field: Field,
+    regex: Arc<Regex>,
+    max_expansions: usize,
+}
+
+impl BoundedRegexWeight {
+    /// Fail if the regex matches more than `max_expansions` terms of a segment
+    fn check_expansions(&self, reader: &SegmentReader) -> tantivy::Result<()> {
+        let inverted_index = reader.inverted_index(self.field)?;
+        let mut terms = inverted_index
+            .terms()
+            .search(self.regex.as_ref())
+            .into_stream()?;
+        let mut expansions = 0;
+        while terms.advance() {
+            expansions += 1;
+            if expansions > self.max_expansions {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "pattern matches more than {} terms",
+                    self.max_expansions
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Weight for BoundedRegexWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        self.check_expansions(reader)?;
+        self.weight.scorer(reader, boost)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+}
@@ -0,0 +1,210 @@
+use tantivy::columnar::Column;
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TERMINATED, TantivyError};
+
+/// Mean Earth radius in kilometres
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Great-circle distance in kilometres between two points given in degrees
+pub(crate) fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let half_dlat = (lat2 - lat1) / 2.0;
+    let half_dlon = (lon2 - lon1).to_radians() / 2.0;
+
+    let a = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Smallest latitude/longitude box holding every point within `radius_km` of a center
+///
+/// Returns `(min_lat, max_lat, min_lon, max_lon)`. Longitudes wrap around, so
+/// `min_lon` is greater than `max_lon` when the box crosses the antimeridian.
+/// Boxes reaching a pole span every longitude.
+pub(crate) fn bounding_box(lat: f64, lon: f64, radius_km: f64) -> (f64, f64, f64, f64) {
+    let delta_lat = (radius_km / EARTH_RADIUS_KM).to_degrees();
+    let min_lat = lat - delta_lat;
+    let max_lat = lat + delta_lat;
+    if min_lat <= -90.0 || max_lat >= 90.0 {
+        return (min_lat.max(-90.0), max_lat.min(90.0), -180.0, 180.0);
+    }
+
+    // Widest at the latitude closest to a pole
+    let widest_lat = lat.abs() + delta_lat;
+    let delta_lon = delta_lat / widest_lat.to_radians().cos();
+    if delta_lon >= 180.0 {
+        return (min_lat, max_lat, -180.0, 180.0);
+    }
+
+    let wrap = |lon: f64| {
+        if lon < -180.0 {
+            lon + 360.0
+        } else if lon > 180.0 {
+            lon - 360.0
+        } else {
+            lon
+        }
+    };
+    (
+        min_lat,
+        max_lat,
+        wrap(lon - delta_lon),
+        wrap(lon + delta_lon),
+    )
+}
+
+/// Query keeping the matches of a prefilter that lie within a radius of a point
+///
+/// Distances are computed from the latitude and longitude fast fields of each
+/// candidate, so the prefilter should already exclude most far-away points.
+/// Scores are those of the prefilter.
+#[derive(Debug)]
+pub(crate) struct GeoDistanceQuery {
+    prefilter: Box<dyn Query>,
+    lat_field: String,
+    lon_field: String,
+    center: (f64, f64),
+    radius_km: f64,
+}
+
+impl GeoDistanceQuery {
+    pub(crate) fn new(
+        prefilter: Box<dyn Query>,
+        lat_field: String,
+        lon_field: String,
+        center: (f64, f64),
+        radius_km: f64,
+    ) -> Self {
+        Self {
+            prefilter,
+            lat_field,
+            lon_field,
+            center,
+            radius_km,
+        }
+    }
+}
+
+impl Clone for GeoDistanceQuery {
+    fn clone(&self) -> Self {
+        Self {
+            prefilter: self.prefilter.box_clone(),
+            lat_field: self.lat_field.clone(),
+            lon_field: self.lon_field.clone(),
+            center: self.center,
+            radius_km: self.radius_km,
+        }
+    }
+}
+
+impl Query for GeoDistanceQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(GeoDistanceWeight {
+            prefilter: self.prefilter.weight(enable_scoring)?,
+            lat_field: self.lat_field.clone(),
+            lon_field: self.lon_field.clone(),
+            center: self.center,
+            radius_km: self.radius_km,
+        }))
+    }
+}
+
+struct GeoDistanceWeight {
+    prefilter: Box<dyn Weight>,
+    lat_field: String,
+    lon_field: String,
+    center: (f64, f64),
+    radius_km: f64,
+}
+
+impl GeoDistanceWeight {
+    fn distance_filter(&self, reader: &SegmentReader) -> tantivy::Result<DistanceFilter> {
+        let fast_fields = reader.fast_fields();
+        Ok(DistanceFilter {
+            lat: fast_fields.f64(&self.lat_field)?,
+            lon: fast_fields.f64(&self.lon_field)?,
+            center: self.center,
+            radius_km: self.radius_km,
+        })
+    }
+}
+
+impl Weight for GeoDistanceWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let mut scorer = GeoDistanceScorer {
+            inner: self.prefilter.scorer(reader, boost)?,
+            filter: self.distance_filter(reader)?,
+        };
+        scorer.skip_to_match();
+        Ok(Box::new(scorer))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        if !self.distance_filter(reader)?.contains(doc) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({}) does not match",
+                doc
+            )));
+        }
+        self.prefilter.explain(reader, doc)
+    }
+}
+
+struct DistanceFilter {
+    lat: Column<f64>,
+    lon: Column<f64>,
+    center: (f64, f64),
+    radius_km: f64,
+}
+
+impl DistanceFilter {
+    fn contains(&self, doc: DocId) -> bool {
+        match (self.lat.first(doc), self.lon.first(doc)) {
+            (Some(lat), Some(lon)) => {
+                haversine_km(self.center.0, self.center.1, lat, lon) <= self.radius_km
+            }
+            _ => false,
+        }
+    }
+}
+
+struct GeoDistanceScorer {
+    inner: Box<dyn Scorer>,
+    filter: DistanceFilter,
+}
+
+impl GeoDistanceScorer {
+    /// Advance the prefilter until it is on a document within the radius
+    fn skip_to_match(&mut self) -> DocId {
+        let mut doc = self.inner.doc();
+        while doc != TERMINATED && !self.filter.contains(doc) {
+            doc = self.inner.advance();
+        }
+        doc
+    }
+}
+
+impl DocSet for GeoDistanceScorer {
+    fn advance(&mut self) -> DocId {
+        self.inner.advance();
+        self.skip_to_match()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.inner.seek(target);
+        self.skip_to_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.inner.size_hint()
+    }
+}
+
+impl Scorer for GeoDistanceScorer {
+    fn score(&mut self) -> Score {
+        self.inner.score()
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, HashMap};
+use tantivy::{
+    DocId, Score, SegmentOrdinal, SegmentReader,
+    collector::{Collector, SegmentCollector},
+};
+
+use super::column::{FieldColumn, NumericColumn};
+
+/// Collector counting matching documents per fixed-width bucket of a fast field
+///
+/// A document falls in the bucket `floor(value / interval)`, keyed by the
+/// bucket's lower bound. Documents without a value in the field are skipped.
+pub(crate) struct HistogramCollector {
+    field: String,
+    column: NumericColumn,
+    interval: f64,
+}
+
+impl HistogramCollector {
+    pub(crate) fn new(field: String, column: NumericColumn, interval: f64) -> Self {
+        Self {
+            field,
+            column,
+            interval,
+        }
+    }
+}
+
+impl Collector for HistogramCollector {
+    /// Non-empty buckets as `(lower bound, document count)`, in ascending order
+    type Fruit = Vec<(f64, u64)>;
+    type Child = HistogramSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(HistogramSegmentCollector {
+            column: FieldColumn::open(segment, &self.field, self.column)?,
+            interval: self.interval,
+            buckets: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<HashMap<i64, u64>>) -> tantivy::Result<Self::Fruit> {
+        let mut buckets = BTreeMap::new();
+        for segment_buckets in segment_fruits {
+            for (bucket, count) in segment_buckets {
+                *buckets.entry(bucket).or_insert(0) += count;
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(bucket, count)| (bucket as f64 * self.interval, count))
+            .collect())
+    }
+}
+
+pub(crate) struct HistogramSegmentCollector {
+    column: FieldColumn,
+    interval: f64,
+    /// Document count per bucket index
+    buckets: HashMap<i64, u64>,
+}
+
+impl SegmentCollector for HistogramSegmentCollector {
+    type Fruit = HashMap<i64, u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(value) = self.column.value(doc) {
+            let bucket = (value / self.interval).floor() as i64;
+            *self.buckets.entry(bucket).or_insert(0) += 1;
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.buckets
+    }
+}
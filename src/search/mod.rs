@@ -1,73 +1,296 @@
 use crate::collection::Collection;
 use crate::error::{Result, SearchEngineError};
+use crate::schema::{BOOST_FIELD, geo_field_names};
 use crate::types::{
-    FieldValue, QueryExpression, SearchHit, SearchQuery, SearchResult, SortField, SortOrder,
+    DOC_ORDER_FIELD, EmptyQueryBehavior, EngineConfig, FieldType, FieldValue, MissingOrder,
+    QueryExpression, ReadConsistency, ScoringMode, SearchHit, SearchQuery, SearchResult, SortField,
+    SortOrder, TermStats,
 };
-use std::time::Instant;
-use tantivy::schema::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tantivy::postings::Postings;
+use tantivy::schema::{IndexRecordOption, Value};
 use tantivy::{
-    DocAddress, Score, Searcher, TantivyDocument, Term,
-    collector::{Count, TopDocs},
+    DocAddress, DocId, DocSet, Score, Searcher, SegmentReader, TERMINATED, TantivyDocument, Term,
+    collector::{Count, FacetCollector, TopDocs},
     query::*,
     schema::Field,
+    snippet::SnippetGenerator,
+    tokenizer::TextAnalyzer,
 };
+use tracing::Span;
+
+mod column;
+mod cursor;
+mod deadline;
+mod expansion;
+mod geo;
+mod histogram;
+mod scoring;
+mod suggest;
+
+use column::NumericColumn;
+use cursor::{SearchAfterCollector, sort_ties_by_id};
+use deadline::DeadlineCollector;
+use expansion::BoundedRegexQuery;
+use geo::GeoDistanceQuery;
+use histogram::HistogramCollector;
+use levenshtein_automata::LevenshteinAutomatonBuilder;
+pub(crate) use scoring::{DEFAULT_BM25_B, DEFAULT_BM25_K1};
+use scoring::{FieldBoostQuery, TermScoring};
+use suggest::LevenshteinDfa;
+
+/// Default maximum length of a highlighted snippet in characters
+const DEFAULT_SNIPPET_MAX_CHARS: usize = 150;
+
+/// Query terms of one stored text field, located in hit text by re-tokenizing it
+struct TermOffsetMatcher {
+    field_name: String,
+    field: Field,
+    analyzer: TextAnalyzer,
+    terms: HashSet<String>,
+}
+
+impl TermOffsetMatcher {
+    /// Byte ranges of the tokens of `text` that are query terms
+    fn offsets(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut offsets = Vec::new();
+        self.analyzer
+            .clone()
+            .token_stream(text)
+            .process(&mut |token| {
+                if self.terms.contains(&token.text) {
+                    offsets.push((token.offset_from, token.offset_to));
+                }
+            });
+        offsets
+    }
+}
+
+/// Top documents, total hit count and whether collection hit its deadline
+type CollectedDocs = (Vec<(Score, DocAddress)>, usize, bool);
 
 /// Search engine for executing queries against collections
 pub struct SearchEngine {
     collection: Collection,
+    config: EngineConfig,
 }
 
 impl SearchEngine {
     /// Create a new search engine for a collection
     pub fn new(collection: Collection) -> Self {
-        Self { collection }
+        Self::with_config(collection, &EngineConfig::default())
+    }
+
+    /// Create a new search engine for a collection using engine-level settings
+    pub fn with_config(collection: Collection, config: &EngineConfig) -> Self {
+        Self {
+            collection,
+            config: config.clone(),
+        }
+    }
+
+    /// Create a span for a query phase, or a disabled span if query spans are off
+    fn phase_span(&self, make_span: impl FnOnce() -> Span) -> Span {
+        if self.config.query_spans {
+            make_span()
+        } else {
+            Span::none()
+        }
     }
 
     /// Execute a search query
     pub fn search(&self, query: SearchQuery) -> Result<SearchResult> {
         let start_time = Instant::now();
 
-        // Get searcher
-        let reader = self.collection.index.reader()?;
+        let search_span = self.phase_span(|| {
+            tracing::info_span!(
+                "search",
+                collection = %self.collection.name,
+                query_type = query.query.type_name(),
+                total_hits = tracing::field::Empty,
+            )
+        });
+        let _search_guard = search_span.enter();
+
+        // Get searcher, reloading first if the latest commit must be visible
+        let reader = &self.collection.reader;
+        if query.consistency == ReadConsistency::Latest {
+            reader.reload()?;
+        }
         let searcher = reader.searcher();
 
         // Build Tantivy query
-        let tantivy_query = self.build_query(&query.query)?;
+        let tantivy_query = self
+            .phase_span(|| tracing::info_span!("build_query"))
+            .in_scope(|| self.build_query(&query.query))?;
 
         // Determine limit and offset
         let limit = query.limit.unwrap_or(10);
         let offset = query.offset.unwrap_or(0);
+        let max_window = self.config.max_result_window;
+        if offset
+            .checked_add(limit)
+            .is_none_or(|window| window > max_window)
+        {
+            return Err(SearchEngineError::QueryError(format!(
+                "result window is too large: offset + limit must be at most {}, got {} + {}",
+                max_window, offset, limit
+            )));
+        }
+
+        if query.search_after.is_some() && query.sort.is_some() {
+            return Err(SearchEngineError::QueryError(
+                "search_after cannot be combined with sort".to_string(),
+            ));
+        }
+        if query.search_after.is_some() && query.normalize_scores {
+            return Err(SearchEngineError::QueryError(
+                "search_after cannot be combined with normalize_scores".to_string(),
+            ));
+        }
+
+        // Document order and geo distance sorts are applied while collecting
+        let leading_sort = query
+            .sort
+            .as_ref()
+            .and_then(|sort_fields| sort_fields.first());
+        let sorted_while_collecting = leading_sort.is_some_and(|sort_field| {
+            sort_field.field == DOC_ORDER_FIELD || sort_field.distance_from.is_some()
+        });
+        // Cursors, sorts applied while collecting and document boosts need every
+        // match visited
+        let approximate = query.approximate
+            && query.search_after.is_none()
+            && !sorted_while_collecting
+            && !has_boosted_documents(&searcher);
 
         // Execute search
-        let (top_docs, total_hits) = if offset > 0 {
-            // If offset is specified, we need to collect more documents
-            let collector = TopDocs::with_limit(offset + limit);
-            let top_docs = searcher.search(&tantivy_query, &collector)?;
-            let total_collector = Count;
-            let total_hits = searcher.search(&tantivy_query, &total_collector)?;
+        let execute_span = self.phase_span(|| {
+            tracing::info_span!("execute", limit, offset, total_hits = tracing::field::Empty)
+        });
+        let execute_guard = execute_span.enter();
+        let (top_docs, total_hits, timed_out, has_more, top_score) = {
+            let deadline = query
+                .timeout_ms
+                .map(|timeout_ms| start_time + Duration::from_millis(timeout_ms));
+            // Collect enough documents to cover the offset, plus one to tell
+            // whether another page follows
+            let fetch = offset + limit + 1;
+            let doc_order = leading_sort.filter(|sort_field| sort_field.field == DOC_ORDER_FIELD);
+            let distance_sort = leading_sort
+                .and_then(|sort_field| sort_field.distance_from.map(|origin| (sort_field, origin)));
+            let (top_docs, total_hits, timed_out) = if let Some(after) = &query.search_after {
+                self.collect_after(
+                    &searcher,
+                    tantivy_query.as_ref(),
+                    fetch,
+                    after.clone(),
+                    deadline,
+                )?
+            } else if let Some(sort_field) = doc_order {
+                self.collect_doc_order(
+                    &searcher,
+                    tantivy_query.as_ref(),
+                    fetch,
+                    &sort_field.order,
+                    deadline,
+                )?
+            } else if let Some((sort_field, origin)) = distance_sort {
+                self.collect_geo_distance(
+                    &searcher,
+                    tantivy_query.as_ref(),
+                    fetch,
+                    sort_field,
+                    origin,
+                    deadline,
+                )?
+            } else if approximate {
+                self.collect_approximate(&searcher, tantivy_query.as_ref(), fetch, deadline)?
+            } else {
+                self.collect_exact(&searcher, tantivy_query.as_ref(), fetch, deadline)?
+            };
+
+            if timed_out && !query.partial_on_timeout {
+                return Err(SearchEngineError::Timeout {
+                    timeout_ms: query.timeout_ms.unwrap_or_default(),
+                });
+            }
+
+            // The best score is taken before skipping, so it is the same on every page
+            let top_score = top_docs
+                .iter()
+                .map(|(score, _)| *score)
+                .fold(Score::NEG_INFINITY, Score::max);
 
             // Skip documents before offset
-            let documents = top_docs.into_iter().skip(offset).collect();
-            (documents, total_hits)
-        } else {
-            let collector = TopDocs::with_limit(limit);
-            let top_docs = searcher.search(&tantivy_query, &collector)?;
-            let total_collector = Count;
-            let total_hits = searcher.search(&tantivy_query, &total_collector)?;
-            (top_docs, total_hits)
+            let mut documents: Vec<_> = top_docs.into_iter().skip(offset).collect();
+            let has_more = documents.len() > limit;
+            documents.truncate(limit);
+            (documents, total_hits, timed_out, has_more, top_score)
         };
+        execute_span.record("total_hits", total_hits);
+        search_span.record("total_hits", total_hits);
+        drop(execute_guard);
 
         // Convert results
+        let convert_span =
+            self.phase_span(|| tracing::info_span!("convert_hits", hits = top_docs.len()));
+        let convert_guard = convert_span.enter();
+        let snippet_generators =
+            self.snippet_generators(&searcher, tantivy_query.as_ref(), &query)?;
+        let term_matchers = if query.return_term_offsets {
+            self.term_offset_matchers(tantivy_query.as_ref())?
+        } else {
+            Vec::new()
+        };
+        // Sort keys are read from the hit fields, so load them alongside the projection
+        let projection = query.fields.as_ref().map(|fields| {
+            let mut projection = fields.clone();
+            projection.extend(query.sort.iter().flatten().map(|sort| sort.field.clone()));
+            projection
+        });
         let mut search_hits = Vec::new();
         for (score, doc_address) in top_docs {
-            let hit = self.convert_search_hit(&searcher, doc_address, score)?;
+            let hit = self.convert_search_hit(
+                &searcher,
+                doc_address,
+                score,
+                projection.as_deref(),
+                &snippet_generators,
+                &term_matchers,
+            )?;
             search_hits.push(hit);
         }
+        drop(convert_guard);
 
-        // Apply sorting if specified
-        if let Some(sort_fields) = &query.sort {
+        // Apply sorting if specified and not already done while collecting
+        if let Some(sort_fields) = &query.sort
+            && !sorted_while_collecting
+        {
             self.sort_results(&mut search_hits, sort_fields)?;
         }
+        if let Some(fields) = &query.fields {
+            for hit in &mut search_hits {
+                hit.fields.retain(|name, _| fields.contains(name));
+            }
+        }
+        if query.normalize_scores && top_score > 0.0 {
+            for hit in &mut search_hits {
+                hit.score /= top_score;
+            }
+        }
+
+        // Count facet values if requested
+        let mut facet_counts = HashMap::new();
+        if let Some(facet_fields) = &query.facets {
+            for facet in facet_fields {
+                let (field_name, path) = facet.split_once(':').unwrap_or((facet, "/"));
+                let counts =
+                    self.count_facets(&searcher, tantivy_query.as_ref(), field_name, path)?;
+                facet_counts.insert(facet.clone(), counts);
+            }
+        }
 
         let elapsed = start_time.elapsed();
 
@@ -75,31 +298,646 @@ impl SearchEngine {
             total_hits,
             documents: search_hits,
             took_ms: elapsed.as_millis() as u64,
+            facet_counts,
+            timed_out,
+            approximate,
+            limit,
+            offset,
+            has_more,
         })
     }
 
-    /// Build Tantivy query from our query expression
+    /// Count the documents matching a query
+    ///
+    /// Only the `Count` collector runs, so no hits are ranked or loaded from the
+    /// doc store. Limit, offset, sorting and other result options are ignored.
+    pub fn count(&self, query: &SearchQuery) -> Result<usize> {
+        let reader = &self.collection.reader;
+        if query.consistency == ReadConsistency::Latest {
+            reader.reload()?;
+        }
+        let searcher = reader.searcher();
+
+        let tantivy_query = self.build_query(&query.query)?;
+        Ok(searcher.search(&tantivy_query, &Count)?)
+    }
+
+    /// Count the documents matching a query per fixed-width bucket of a fast field
+    ///
+    /// The field must be a fast numeric or date field; dates are bucketed by
+    /// their Unix timestamp in seconds, so `interval` is in seconds too. Only
+    /// non-empty buckets are returned, as `(lower bound, document count)` in
+    /// ascending order.
+    pub fn histogram(
+        &self,
+        query: &QueryExpression,
+        field_name: &str,
+        interval: f64,
+    ) -> Result<Vec<(f64, u64)>> {
+        if !(interval.is_finite() && interval > 0.0) {
+            return Err(SearchEngineError::QueryError(format!(
+                "Invalid histogram interval {}",
+                interval
+            )));
+        }
+        let column = self.numeric_column(field_name)?.ok_or_else(|| {
+            SearchEngineError::QueryError(format!(
+                "Field '{}' must be a fast numeric or date field to bucket",
+                field_name
+            ))
+        })?;
+
+        let searcher = self.collection.reader.searcher();
+        let tantivy_query = self.build_query(query)?;
+        let collector = HistogramCollector::new(field_name.to_string(), column, interval);
+        Ok(searcher.search(&tantivy_query, &collector)?)
+    }
+
+    /// Apply a patch to every committed document matching a query, returning the number updated
+    ///
+    /// See `Collection::update_by_query`.
+    pub fn update_by_query(
+        &self,
+        query: &QueryExpression,
+        patch: &HashMap<String, FieldValue>,
+    ) -> Result<usize> {
+        let tantivy_query = self.build_query(query)?;
+        self.collection
+            .update_by_query(tantivy_query.as_ref(), patch)
+    }
+
+    /// Document frequency and total occurrences of a term in an indexed text field
+    ///
+    /// The term is analyzed with the field's tokenizer, as in a term query.
+    /// Counts include deleted documents that have not been merged away yet.
+    pub fn term_stats(&self, field_name: &str, text: &str) -> Result<TermStats> {
+        let field = self.indexed_text_field(field_name)?;
+        let mut stats = TermStats::default();
+        let Some(token) = self.analyze_term(field, text)? else {
+            return Ok(stats);
+        };
+        let term = Term::from_field_text(field, &token);
+
+        let searcher = self.collection.reader.searcher();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let Some(term_info) = inverted_index.get_term_info(&term)? else {
+                continue;
+            };
+            stats.doc_freq += u64::from(term_info.doc_freq);
+            let mut postings = inverted_index
+                .read_postings_from_terminfo(&term_info, IndexRecordOption::WithFreqs)?;
+            while postings.doc() != TERMINATED {
+                stats.total_term_freq += u64::from(postings.term_freq());
+                postings.advance();
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Get the `n` terms of an indexed text field with the highest document frequency
+    ///
+    /// Walks the field's term dictionary in every segment. Frequencies include
+    /// deleted documents that have not been merged away yet. Ties are broken
+    /// alphabetically.
+    pub fn top_terms(&self, field_name: &str, n: usize) -> Result<Vec<(String, u64)>> {
+        let field = self.indexed_text_field(field_name)?;
+        let searcher = self.collection.reader.searcher();
+
+        let mut doc_freqs: HashMap<String, u64> = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                let term = String::from_utf8_lossy(stream.key()).into_owned();
+                *doc_freqs.entry(term).or_default() += u64::from(stream.value().doc_freq);
+            }
+        }
+
+        let mut top_terms: Vec<_> = doc_freqs.into_iter().collect();
+        top_terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_terms.truncate(n);
+        Ok(top_terms)
+    }
+
+    /// Complete a prefix with terms from an indexed text field
+    ///
+    /// Each segment's term dictionary is seeked to the prefix and read only while
+    /// terms still start with it. The prefix is run through the field's analyzer
+    /// first, so it matches indexed terms regardless of case; a prefix the
+    /// analyzer drops entirely, such as a stop word, is matched as given.
+    /// Returns up to `limit` terms, most frequent first, ties alphabetically.
+    pub fn autocomplete(
+        &self,
+        field_name: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let field = self.indexed_text_field(field_name)?;
+        let prefix = &self
+            .analyze_term(field, prefix)?
+            .unwrap_or_else(|| prefix.to_string());
+        let searcher = self.collection.reader.searcher();
+
+        let mut doc_freqs: HashMap<String, u64> = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index.terms().range().ge(prefix).into_stream()?;
+            while stream.advance() && stream.key().starts_with(prefix.as_bytes()) {
+                let term = String::from_utf8_lossy(stream.key()).into_owned();
+                *doc_freqs.entry(term).or_default() += u64::from(stream.value().doc_freq);
+            }
+        }
+
+        let mut completions: Vec<_> = doc_freqs.into_iter().collect();
+        completions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(completions
+            .into_iter()
+            .take(limit)
+            .map(|(term, _)| term)
+            .collect())
+    }
+
+    /// Suggest spelling corrections for a term from an indexed text field
+    ///
+    /// Walks the field's term dictionary with a Levenshtein automaton, so only
+    /// terms within `max_edits` edits are visited; a transposition counts as one
+    /// edit. The term is run through the field's analyzer first so it compares
+    /// against indexed terms. Candidates are ranked by document frequency, and
+    /// the term itself is never suggested.
+    pub fn suggest(
+        &self,
+        field_name: &str,
+        term: &str,
+        max_edits: u8,
+    ) -> Result<Vec<(String, u64)>> {
+        if max_edits > 2 {
+            return Err(SearchEngineError::QueryError(format!(
+                "max_edits must be at most 2, got {}",
+                max_edits
+            )));
+        }
+        let field = self.indexed_text_field(field_name)?;
+
+        let mut analyzer = self.collection.index.tokenizer_for_field(field)?;
+        let mut token_stream = analyzer.token_stream(term);
+        let term = if token_stream.advance() {
+            token_stream.token().text.clone()
+        } else {
+            return Ok(Vec::new());
+        };
+
+        let dfa = LevenshteinAutomatonBuilder::new(max_edits, true).build_dfa(&term);
+        let searcher = self.collection.reader.searcher();
+        let mut doc_freqs: HashMap<String, u64> = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index
+                .terms()
+                .search(LevenshteinDfa(&dfa))
+                .into_stream()?;
+            while stream.advance() {
+                let candidate = String::from_utf8_lossy(stream.key()).into_owned();
+                *doc_freqs.entry(candidate).or_default() += u64::from(stream.value().doc_freq);
+            }
+        }
+        doc_freqs.remove(&term);
+
+        let mut suggestions: Vec<_> = doc_freqs.into_iter().collect();
+        suggestions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(suggestions)
+    }
+
+    /// Collect the exact top documents and total hit count, applying document boosts
+    ///
+    /// Documents with equal scores are ordered by ID, ascending. `TopDocs` picks
+    /// the documents unless equal scores straddle the `limit` cut, where it would
+    /// keep the tied documents that come first in the index rather than by ID;
+    /// only then is the query run again through the cursor collector.
+    fn collect_exact(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        deadline: Option<Instant>,
+    ) -> Result<CollectedDocs> {
+        let id_field = self.collection.schema_manager.id_field();
+        // One document past the cut tells whether a tie straddles it
+        let top_collector = TopDocs::with_limit(limit + 1)
+            .tweak_score(|segment_reader: &SegmentReader| boost_score(segment_reader));
+        let collector = DeadlineCollector::new((top_collector, Count), deadline);
+        let (mut top_docs, total_hits) = searcher.search(query, &collector)?;
+
+        if limit > 0 && top_docs.len() > limit && top_docs[limit].0 == top_docs[limit - 1].0 {
+            let top_collector = SearchAfterCollector::new(limit, None, id_field);
+            let collector = DeadlineCollector::new(top_collector, deadline);
+            let top_docs = searcher.search(query, &collector)?;
+            return Ok((top_docs, total_hits, collector.timed_out()));
+        }
+        top_docs.truncate(limit);
+        sort_ties_by_id(searcher, id_field, &mut top_docs)?;
+        Ok((top_docs, total_hits, collector.timed_out()))
+    }
+
+    /// Collect the top documents ranked after a `(score, id)` cursor, applying document boosts
+    fn collect_after(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        after: (Score, String),
+        deadline: Option<Instant>,
+    ) -> Result<CollectedDocs> {
+        let id_field = self.collection.schema_manager.id_field();
+        let top_collector = SearchAfterCollector::new(limit, Some(after), id_field);
+        let collector = DeadlineCollector::new((top_collector, Count), deadline);
+        let (top_docs, total_hits) = searcher.search(query, &collector)?;
+        Ok((top_docs, total_hits, collector.timed_out()))
+    }
+
+    /// Collect the top documents while letting Tantivy skip low-scoring blocks
+    ///
+    /// Without a hit count or score tweak, `TopDocs` can use block-max WAND to
+    /// skip documents that cannot enter the top `limit`, so document boosts are
+    /// not applied. Matches are counted in a separate pass, which needs no
+    /// scoring. A deadline forces every document to be visited, which disables
+    /// skipping.
+    fn collect_approximate(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        deadline: Option<Instant>,
+    ) -> Result<CollectedDocs> {
+        let collector = DeadlineCollector::new(TopDocs::with_limit(limit), deadline);
+        let top_docs = searcher.search(query, &collector)?;
+        let count_collector = DeadlineCollector::new(Count, deadline);
+        let total_hits = searcher.search(query, &count_collector)?;
+        let timed_out = collector.timed_out() || count_collector.timed_out();
+        Ok((top_docs, total_hits, timed_out))
+    }
+
+    /// Collect the first documents by address, approximating insertion order
+    ///
+    /// Doc ids within a segment follow insertion order. Segments are taken in the
+    /// searcher's order, which does not always match the order they were written
+    /// in, and merges can interleave documents from different segments, so the
+    /// order across segments is only approximate. Hits carry a score of zero.
+    fn collect_doc_order(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        order: &SortOrder,
+        deadline: Option<Instant>,
+    ) -> Result<CollectedDocs> {
+        let segment_ords: HashMap<_, _> = searcher
+            .segment_readers()
+            .iter()
+            .enumerate()
+            .map(|(ord, segment_reader)| (segment_reader.segment_id(), ord as u64))
+            .collect();
+        let descending = matches!(order, SortOrder::Desc);
+
+        // TopDocs keeps the highest keys, so invert the address for ascending order
+        let top_collector =
+            TopDocs::with_limit(limit).custom_score(move |segment_reader: &SegmentReader| {
+                let segment_ord = segment_ords[&segment_reader.segment_id()];
+                move |doc: DocId| {
+                    let address = (segment_ord << 32) | doc as u64;
+                    if descending {
+                        address
+                    } else {
+                        u64::MAX - address
+                    }
+                }
+            });
+        let collector = DeadlineCollector::new((top_collector, Count), deadline);
+        let (top_docs, total_hits) = searcher.search(query, &collector)?;
+
+        let top_docs = top_docs
+            .into_iter()
+            .map(|(_, doc_address)| (0.0, doc_address))
+            .collect();
+        Ok((top_docs, total_hits, collector.timed_out()))
+    }
+
+    /// Collect the documents nearest to a point, or farthest with a descending order
+    ///
+    /// Distances are computed from the geo field's fast fields for every match.
+    /// Documents without a point come last. Hits carry a score of zero.
+    fn collect_geo_distance(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        sort_field: &SortField,
+        origin: (f64, f64),
+        deadline: Option<Instant>,
+    ) -> Result<CollectedDocs> {
+        self.geo_fields(&sort_field.field)?;
+        let (lat_field, lon_field) = geo_field_names(&sort_field.field);
+        let descending = matches!(sort_field.order, SortOrder::Desc);
+
+        // TopDocs keeps the highest keys, so negate distances for ascending order
+        let top_collector =
+            TopDocs::with_limit(limit).custom_score(move |segment_reader: &SegmentReader| {
+                let fast_fields = segment_reader.fast_fields();
+                let lat = fast_fields.f64(&lat_field).ok();
+                let lon = fast_fields.f64(&lon_field).ok();
+                move |doc: DocId| {
+                    let point = lat
+                        .as_ref()
+                        .zip(lon.as_ref())
+                        .and_then(|(lat, lon)| lat.first(doc).zip(lon.first(doc)));
+                    match point {
+                        Some((lat, lon)) => {
+                            let distance = geo::haversine_km(origin.0, origin.1, lat, lon);
+                            if descending { distance } else { -distance }
+                        }
+                        None => f64::NEG_INFINITY,
+                    }
+                }
+            });
+        let collector = DeadlineCollector::new((top_collector, Count), deadline);
+        let (top_docs, total_hits) = searcher.search(query, &collector)?;
+
+        let top_docs = top_docs
+            .into_iter()
+            .map(|(_, doc_address)| (0.0, doc_address))
+            .collect();
+        Ok((top_docs, total_hits, collector.timed_out()))
+    }
+
+    /// Explain which clauses of a query prevent a document from matching
+    ///
+    /// Returns one message per failing clause; an empty list means the document
+    /// matches the query.
+    pub fn explain_no_match(&self, query: &QueryExpression, doc_id: &str) -> Result<Vec<String>> {
+        let searcher = self.collection.reader.searcher();
+        let doc_address = self.find_doc_address(&searcher, doc_id)?.ok_or_else(|| {
+            SearchEngineError::DocumentNotFound {
+                id: doc_id.to_string(),
+            }
+        })?;
+
+        let mut reasons = Vec::new();
+        self.collect_mismatches(&searcher, query, doc_address, &mut reasons)?;
+        Ok(reasons)
+    }
+
+    /// Explain how a document's score for a query is computed
+    ///
+    /// Returns Tantivy's scoring breakdown as pretty-printed JSON, including
+    /// the term frequencies, field norms and per-term BM25 contributions.
+    /// Document boosts are applied after scoring and are not part of it.
+    pub fn explain(&self, query: &SearchQuery, doc_id: &str) -> Result<String> {
+        let searcher = self.collection.reader.searcher();
+        let doc_address = self.find_doc_address(&searcher, doc_id)?.ok_or_else(|| {
+            SearchEngineError::DocumentNotFound {
+                id: doc_id.to_string(),
+            }
+        })?;
+
+        let tantivy_query = self.build_query(&query.query)?;
+        if !self.matches_doc(&searcher, tantivy_query.as_ref(), doc_address)? {
+            return Err(SearchEngineError::QueryError(format!(
+                "Document '{}' does not match the query",
+                doc_id
+            )));
+        }
+
+        let explanation = tantivy_query.explain(&searcher, doc_address)?;
+        Ok(explanation.to_pretty_json())
+    }
+
+    /// Recursively record the clauses of a query that do not match a document
+    fn collect_mismatches(
+        &self,
+        searcher: &Searcher,
+        query_expr: &QueryExpression,
+        doc_address: DocAddress,
+        reasons: &mut Vec<String>,
+    ) -> Result<()> {
+        match query_expr {
+            QueryExpression::Bool {
+                must,
+                should,
+                must_not,
+                ..
+            } => {
+                for clause in must.iter().flatten() {
+                    self.collect_mismatches(searcher, clause, doc_address, reasons)?;
+                }
+
+                for clause in must_not.iter().flatten() {
+                    let query = self.build_query(clause)?;
+                    if self.matches_doc(searcher, query.as_ref(), doc_address)? {
+                        reasons.push(format!(
+                            "must_not clause matched: {}",
+                            self.describe_clause(clause)
+                        ));
+                    }
+                }
+
+                // Should clauses only matter when there is nothing else to satisfy
+                if let Some(should) = should
+                    && !should.is_empty()
+                    && must.as_ref().is_none_or(|m| m.is_empty())
+                {
+                    let mut should_reasons = Vec::new();
+                    for clause in should {
+                        self.collect_mismatches(
+                            searcher,
+                            clause,
+                            doc_address,
+                            &mut should_reasons,
+                        )?;
+                    }
+                    if should_reasons.len() == should.len() {
+                        reasons.push("none of the should clauses matched".to_string());
+                        reasons.extend(should_reasons);
+                    }
+                }
+            }
+            QueryExpression::FieldBoost { query, .. } => {
+                self.collect_mismatches(searcher, query, doc_address, reasons)?;
+            }
+            QueryExpression::Not(inner) => {
+                let query = self.build_query(inner)?;
+                if self.matches_doc(searcher, query.as_ref(), doc_address)? {
+                    reasons.push(format!(
+                        "negated clause matched: {}",
+                        self.describe_clause(inner)
+                    ));
+                }
+            }
+            _ => {
+                let query = self.build_query(query_expr)?;
+                if !self.matches_doc(searcher, query.as_ref(), doc_address)? {
+                    reasons.push(self.describe_clause(query_expr));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Describe why a leaf clause fails to match
+    fn describe_clause(&self, query_expr: &QueryExpression) -> String {
+        match query_expr {
+            QueryExpression::FullText { field, text, .. } => {
+                format!("text '{}' not found in field '{}'", text, field)
+            }
+            QueryExpression::MultiMatch { fields, text } => {
+                let names: Vec<_> = fields.iter().map(|(field, _)| field.as_str()).collect();
+                format!("text '{}' not found in fields {:?}", text, names)
+            }
+            QueryExpression::Term { field, value } => {
+                let value = match value {
+                    FieldValue::Text(text) => text.clone(),
+                    other => format!("{:?}", other),
+                };
+                format!("term '{}' not present in field '{}'", value, field)
+            }
+            QueryExpression::Range {
+                field, min, max, ..
+            } => {
+                format!(
+                    "value of field '{}' not in range {:?}..{:?}",
+                    field, min, max
+                )
+            }
+            QueryExpression::Bool { .. } => "boolean clause did not match".to_string(),
+            QueryExpression::MatchAll => "match_all clause did not match".to_string(),
+            QueryExpression::Not(_) => "not clause did not match".to_string(),
+            QueryExpression::FieldBoost { query, .. } => self.describe_clause(query),
+            QueryExpression::Wildcard { field, pattern }
+            | QueryExpression::Regex { field, pattern } => {
+                format!("no term in field '{}' matches pattern '{}'", field, pattern)
+            }
+            QueryExpression::Exists { field } => format!("field '{}' has no value", field),
+            QueryExpression::FacetTerm { field, facet_path } => {
+                format!("field '{}' has no facet under '{}'", field, facet_path)
+            }
+            QueryExpression::GeoBoundingBox {
+                field,
+                min_lat,
+                max_lat,
+                min_lon,
+                max_lon,
+            } => format!(
+                "point in field '{}' not in box [{}, {}] x [{}, {}]",
+                field, min_lat, max_lat, min_lon, max_lon
+            ),
+            QueryExpression::GeoDistance {
+                field,
+                center_lat,
+                center_lon,
+                radius_km,
+            } => format!(
+                "point in field '{}' not within {} km of ({}, {})",
+                field, radius_km, center_lat, center_lon
+            ),
+        }
+    }
+
+    /// Find the address of a document by its ID
+    fn find_doc_address(&self, searcher: &Searcher, doc_id: &str) -> Result<Option<DocAddress>> {
+        let id_field = self.collection.schema_manager.id_field();
+        let query = TermQuery::new(
+            Term::from_field_text(id_field, doc_id),
+            tantivy::schema::IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+
+        Ok(top_docs.first().map(|(_, doc_address)| *doc_address))
+    }
+
+    /// Check whether a single document matches a query
+    fn matches_doc(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        doc_address: DocAddress,
+    ) -> Result<bool> {
+        let weight = query.weight(EnableScoring::disabled_from_searcher(searcher))?;
+        let segment_reader = searcher.segment_reader(doc_address.segment_ord);
+        let mut scorer = weight.scorer(segment_reader, 1.0)?;
+
+        // Doc sets may only seek forward
+        if scorer.doc() > doc_address.doc_id {
+            return Ok(false);
+        }
+        Ok(scorer.seek(doc_address.doc_id) == doc_address.doc_id)
+    }
+
+    /// Count the direct subfacets of `path` in a facet field over the documents
+    /// matching a query
+    fn count_facets(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        field_name: &str,
+        path: &str,
+    ) -> Result<Vec<(String, u64)>> {
+        self.facet_field(field_name)?;
+        let facet = parse_facet(path)?;
+
+        let mut collector = FacetCollector::for_field(field_name);
+        collector.add_facet(facet.clone());
+        let facet_counts = searcher.search(query, &collector)?;
+
+        Ok(facet_counts
+            .get(facet)
+            .map(|(facet, count)| (facet.to_string(), count))
+            .collect())
+    }
+
+    /// Build Tantivy query from our query expression, enforcing the clause limit
     fn build_query(&self, query_expr: &QueryExpression) -> Result<Box<dyn Query>> {
+        let clause_count = query_expr.clause_count();
+        if clause_count > self.config.max_clause_count {
+            return Err(SearchEngineError::QueryError(format!(
+                "too many clauses: {} exceeds the maximum of {}",
+                clause_count, self.config.max_clause_count
+            )));
+        }
+
+        self.build_query_node(query_expr)
+    }
+
+    /// Recursively build a Tantivy query from a query expression node
+    fn build_query_node(&self, query_expr: &QueryExpression) -> Result<Box<dyn Query>> {
+        let kind = query_expr.kind();
+        if let Some(allowed) = &self
+            .collection
+            .schema_manager
+            .schema_definition()
+            .allowed_query_types
+            && !allowed.contains(&kind)
+        {
+            return Err(SearchEngineError::QueryError(format!(
+                "query type '{}' not allowed",
+                kind.as_str()
+            )));
+        }
+
         match query_expr {
             QueryExpression::FullText { field, text, boost } => {
                 let field_obj =
                     self.collection
                         .schema_manager
                         .get_field(field)
-                        .ok_or_else(|| {
-                            SearchEngineError::QueryError(format!("Field '{}' not found", field))
+                        .ok_or_else(|| SearchEngineError::FieldNotFound {
+                            field: field.to_string(),
                         })?;
 
-                let mut query: Box<dyn Query> = Box::new(
-                    QueryParser::for_index(&self.collection.index, vec![field_obj])
-                        .parse_query(text)
-                        .map_err(|e| {
-                            SearchEngineError::QueryError(format!(
-                                "Failed to parse query '{}': {}",
-                                text, e
-                            ))
-                        })?,
-                );
+                if self.has_no_terms(field_obj, text) {
+                    return Ok(self.empty_text_query());
+                }
+
+                let mut query = self.parse_full_text(&[(field_obj, 1.0)], text)?;
 
                 if let Some(boost_value) = boost {
                     query = Box::new(BoostQuery::new(query, *boost_value));
@@ -108,16 +946,44 @@ impl SearchEngine {
                 Ok(query)
             }
 
+            QueryExpression::MultiMatch { fields, text } => {
+                if fields.is_empty() {
+                    return Err(SearchEngineError::QueryError(
+                        "multi_match query requires at least one field".to_string(),
+                    ));
+                }
+
+                let mut field_boosts = Vec::with_capacity(fields.len());
+                for (field, boost) in fields {
+                    field_boosts.push((self.indexed_text_field(field)?, *boost));
+                }
+
+                if field_boosts
+                    .iter()
+                    .all(|(field_obj, _)| self.has_no_terms(*field_obj, text))
+                {
+                    return Ok(self.empty_text_query());
+                }
+
+                self.parse_full_text(&field_boosts, text)
+            }
+
             QueryExpression::Term { field, value } => {
                 let field_obj =
                     self.collection
                         .schema_manager
                         .get_field(field)
-                        .ok_or_else(|| {
-                            SearchEngineError::QueryError(format!("Field '{}' not found", field))
+                        .ok_or_else(|| SearchEngineError::FieldNotFound {
+                            field: field.to_string(),
                         })?;
 
-                let term = self.build_term(field_obj, value)?;
+                let term = match value {
+                    FieldValue::Text(text) => match self.analyze_term(field_obj, text)? {
+                        Some(token) => tantivy::Term::from_field_text(field_obj, &token),
+                        None => return Ok(Box::new(EmptyQuery)),
+                    },
+                    _ => self.build_term(field_obj, value)?,
+                };
                 Ok(Box::new(TermQuery::new(
                     term,
                     tantivy::schema::IndexRecordOption::Basic,
@@ -134,89 +1000,59 @@ impl SearchEngine {
                     self.collection
                         .schema_manager
                         .get_field(field)
-                        .ok_or_else(|| {
-                            SearchEngineError::QueryError(format!("Field '{}' not found", field))
+                        .ok_or_else(|| SearchEngineError::FieldNotFound {
+                            field: field.to_string(),
                         })?;
 
-                match (min, max) {
-                    (Some(FieldValue::I64(min_val)), Some(FieldValue::I64(max_val))) => {
-                        // let bound = if *inclusive {
-                        //     std::ops::Bound::Included
-                        // } else {
-                        //     std::ops::Bound::Excluded
-                        // };
-
-                        let min_term = Term::from_field_i64(field_obj, *min_val);
-                        let max_term = Term::from_field_i64(field_obj, *max_val);
-                        let lower_bound = if *inclusive {
-                            std::ops::Bound::Included(min_term)
-                        } else {
-                            std::ops::Bound::Excluded(min_term)
-                        };
-                        let upper_bound = if *inclusive {
-                            std::ops::Bound::Included(max_term)
-                        } else {
-                            std::ops::Bound::Excluded(max_term)
-                        };
-
-                        Ok(Box::new(RangeQuery::new(lower_bound, upper_bound)))
-                    }
+                if let (Some(min_val), Some(max_val)) = (min, max)
+                    && std::mem::discriminant(min_val) != std::mem::discriminant(max_val)
+                {
+                    return Err(SearchEngineError::QueryError(
+                        "Range query requires min and max values of the same type".to_string(),
+                    ));
+                }
 
-                    (Some(FieldValue::F64(min_val)), Some(FieldValue::F64(max_val))) => {
-                        let min_term = Term::from_field_f64(field_obj, *min_val);
-                        let max_term = Term::from_field_f64(field_obj, *max_val);
-                        let lower_bound = if *inclusive {
-                            std::ops::Bound::Included(min_term)
-                        } else {
-                            std::ops::Bound::Excluded(min_term)
-                        };
-                        let upper_bound = if *inclusive {
-                            std::ops::Bound::Included(max_term)
-                        } else {
-                            std::ops::Bound::Excluded(max_term)
-                        };
-
-                        Ok(Box::new(RangeQuery::new(lower_bound, upper_bound)))
+                let bound = |term: Term| {
+                    if *inclusive {
+                        std::ops::Bound::Included(term)
+                    } else {
+                        std::ops::Bound::Excluded(term)
                     }
+                };
+
+                let lower_bound = match min {
+                    Some(value) => bound(self.build_range_term(field_obj, value)?),
+                    None => std::ops::Bound::Unbounded,
+                };
+                let upper_bound = match max {
+                    Some(value) => bound(self.build_range_term(field_obj, value)?),
+                    None => std::ops::Bound::Unbounded,
+                };
 
-                    (Some(FieldValue::Date(min_date)), Some(FieldValue::Date(max_date))) => {
-                        let min_dt = tantivy::DateTime::from_timestamp_secs(min_date.timestamp());
-                        let max_dt = tantivy::DateTime::from_timestamp_secs(max_date.timestamp());
-
-                        let min_term = Term::from_field_date(field_obj, min_dt);
-                        let max_term = Term::from_field_date(field_obj, max_dt);
-                        let lower_bound = if *inclusive {
-                            std::ops::Bound::Included(min_term)
-                        } else {
-                            std::ops::Bound::Excluded(min_term)
-                        };
-                        let upper_bound = if *inclusive {
-                            std::ops::Bound::Included(max_term)
-                        } else {
-                            std::ops::Bound::Excluded(max_term)
-                        };
-
-                        Ok(Box::new(RangeQuery::new(lower_bound, upper_bound)))
+                // A fully unbounded range matches every document with a value,
+                // so anchor it at the lowest value of the field's type
+                let lower_bound = match (&lower_bound, &upper_bound) {
+                    (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded) => {
+                        std::ops::Bound::Included(self.min_range_term(field, field_obj)?)
                     }
+                    _ => lower_bound,
+                };
 
-                    _ => Err(SearchEngineError::QueryError(
-                        "Range query requires min and max values of the same type".to_string(),
-                    )),
-                }
+                Ok(Box::new(RangeQuery::new(lower_bound, upper_bound)))
             }
 
             QueryExpression::Bool {
                 must,
                 should,
                 must_not,
-                minimum_should_match,
+                minimum_should_match: _,
             } => {
                 let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
 
                 // Add MUST clauses
                 if let Some(must_queries) = must {
                     for query_expr in must_queries {
-                        let sub_query = self.build_query(query_expr)?;
+                        let sub_query = self.build_query_node(query_expr)?;
                         clauses.push((Occur::Must, sub_query));
                     }
                 }
@@ -224,7 +1060,7 @@ impl SearchEngine {
                 // Add SHOULD clauses
                 if let Some(should_queries) = should {
                     for query_expr in should_queries {
-                        let sub_query = self.build_query(query_expr)?;
+                        let sub_query = self.build_query_node(query_expr)?;
                         clauses.push((Occur::Should, sub_query));
                     }
                 }
@@ -232,7 +1068,7 @@ impl SearchEngine {
                 // Add MUST_NOT clauses
                 if let Some(must_not_queries) = must_not {
                     for query_expr in must_not_queries {
-                        let sub_query = self.build_query(query_expr)?;
+                        let sub_query = self.build_query_node(query_expr)?;
                         clauses.push((Occur::MustNot, sub_query));
                     }
                 }
@@ -246,50 +1082,529 @@ impl SearchEngine {
             }
 
             QueryExpression::MatchAll => Ok(Box::new(AllQuery)),
-        }
-    }
 
-    /// Build a Tantivy term from field and value
-    fn build_term(&self, field: Field, value: &FieldValue) -> Result<tantivy::Term> {
-        let term = match value {
-            FieldValue::Text(text) => tantivy::Term::from_field_text(field, text),
-            FieldValue::I64(num) => tantivy::Term::from_field_i64(field, *num),
-            FieldValue::F64(num) => tantivy::Term::from_field_f64(field, *num),
-            FieldValue::Date(date) => {
-                let dt = tantivy::DateTime::from_timestamp_secs(date.timestamp());
-                tantivy::Term::from_field_date(field, dt)
+            // A boolean query of only MustNot clauses matches nothing, so
+            // exclude the inner query from every document
+            QueryExpression::Not(inner) => Ok(Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery) as Box<dyn Query>),
+                (Occur::MustNot, self.build_query_node(inner)?),
+            ]))),
+
+            QueryExpression::Wildcard { field, pattern } => {
+                let field_obj = self.indexed_text_field(field)?;
+                let regex = glob_to_regex(pattern);
+
+                let query =
+                    BoundedRegexQuery::from_pattern(&regex, field_obj, self.config.max_expansions)
+                        .map_err(|e| {
+                            SearchEngineError::QueryError(format!(
+                                "Invalid wildcard pattern '{}': {}",
+                                pattern, e
+                            ))
+                        })?;
+                Ok(Box::new(query))
             }
-            FieldValue::Facet(facet_str) => {
-                let facet = tantivy::schema::Facet::from_text(facet_str).map_err(|e| {
-                    SearchEngineError::QueryError(format!("Invalid facet '{}': {}", facet_str, e))
-                })?;
-                tantivy::Term::from_field_text(field, &facet.to_string())
+
+            QueryExpression::Regex { field, pattern } => {
+                let field_obj = self.indexed_text_field(field)?;
+                self.build_regex_query(field_obj, pattern)
             }
-            FieldValue::Bytes(_) => {
-                return Err(SearchEngineError::QueryError(
-                    "Bytes fields are not supported for term queries".to_string(),
-                ));
+
+            QueryExpression::Exists { field } => self.build_exists_query(field),
+
+            QueryExpression::FacetTerm { field, facet_path } => {
+                let field_obj = self.facet_field(field)?;
+                // Facets are indexed under every ancestor path, so a parent
+                // path term also matches documents in its subfacets
+                let term = tantivy::Term::from_facet(field_obj, &parse_facet(facet_path)?);
+                Ok(Box::new(TermQuery::new(
+                    term,
+                    tantivy::schema::IndexRecordOption::Basic,
+                )))
             }
-        };
 
-        Ok(term)
-    }
+            QueryExpression::GeoBoundingBox {
+                field,
+                min_lat,
+                max_lat,
+                min_lon,
+                max_lon,
+            } => self.build_geo_bounding_box_query(field, *min_lat, *max_lat, *min_lon, *max_lon),
 
-    /// Convert Tantivy search result to our format
-    fn convert_search_hit(
-        &self,
-        searcher: &Searcher,
-        doc_address: DocAddress,
+            QueryExpression::GeoDistance {
+                field,
+                center_lat,
+                center_lon,
+                radius_km,
+            } => self.build_geo_distance_query(field, *center_lat, *center_lon, *radius_km),
+
+            QueryExpression::FieldBoost {
+                query,
+                field,
+                factor,
+            } => {
+                if !factor.is_finite() {
+                    return Err(SearchEngineError::QueryError(format!(
+                        "Invalid field boost factor {}",
+                        factor
+                    )));
+                }
+                let column = match self.numeric_column(field)? {
+                    Some(column) if column != NumericColumn::Date => column,
+                    _ => {
+                        return Err(SearchEngineError::QueryError(format!(
+                            "Field '{}' must be a fast numeric field to boost by",
+                            field
+                        )));
+                    }
+                };
+                Ok(Box::new(FieldBoostQuery::new(
+                    self.build_query_node(query)?,
+                    field.clone(),
+                    column,
+                    *factor,
+                )))
+            }
+        }
+    }
+
+    /// Build a query matching geo points within a radius of a center point
+    ///
+    /// Tantivy has no geo index, so the bounding box of the circle is matched
+    /// first and the exact haversine distance is checked on those candidates.
+    fn build_geo_distance_query(
+        &self,
+        field_name: &str,
+        center_lat: f64,
+        center_lon: f64,
+        radius_km: f64,
+    ) -> Result<Box<dyn Query>> {
+        if !(radius_km >= 0.0 && radius_km.is_finite()) {
+            return Err(SearchEngineError::QueryError(format!(
+                "Invalid radius {} km",
+                radius_km
+            )));
+        }
+        if !((-90.0..=90.0).contains(&center_lat) && (-180.0..=180.0).contains(&center_lon)) {
+            return Err(SearchEngineError::QueryError(format!(
+                "Invalid center point ({}, {})",
+                center_lat, center_lon
+            )));
+        }
+
+        let (min_lat, max_lat, min_lon, max_lon) =
+            geo::bounding_box(center_lat, center_lon, radius_km);
+        let prefilter =
+            self.build_geo_bounding_box_query(field_name, min_lat, max_lat, min_lon, max_lon)?;
+        let (lat_field, lon_field) = geo_field_names(field_name);
+
+        Ok(Box::new(GeoDistanceQuery::new(
+            prefilter,
+            lat_field,
+            lon_field,
+            (center_lat, center_lon),
+            radius_km,
+        )))
+    }
+
+    /// Build a query matching geo points inside a box
+    ///
+    /// The box is the intersection of a latitude range and a longitude range
+    /// over the field's backing f64 fields. A box crossing the antimeridian
+    /// matches either side of it.
+    fn build_geo_bounding_box_query(
+        &self,
+        field_name: &str,
+        min_lat: f64,
+        max_lat: f64,
+        min_lon: f64,
+        max_lon: f64,
+    ) -> Result<Box<dyn Query>> {
+        let (lat_field, lon_field) = self.geo_fields(field_name)?;
+
+        let valid_lat = |lat: f64| (-90.0..=90.0).contains(&lat);
+        let valid_lon = |lon: f64| (-180.0..=180.0).contains(&lon);
+        if !(valid_lat(min_lat) && valid_lat(max_lat) && valid_lon(min_lon) && valid_lon(max_lon))
+            || min_lat > max_lat
+        {
+            return Err(SearchEngineError::QueryError(format!(
+                "Invalid bounding box [{}, {}] x [{}, {}]",
+                min_lat, max_lat, min_lon, max_lon
+            )));
+        }
+
+        let range = |field: Field, min: f64, max: f64| -> Box<dyn Query> {
+            Box::new(RangeQuery::new(
+                std::ops::Bound::Included(Term::from_field_f64(field, min)),
+                std::ops::Bound::Included(Term::from_field_f64(field, max)),
+            ))
+        };
+
+        let lon_query = if min_lon <= max_lon {
+            range(lon_field, min_lon, max_lon)
+        } else {
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Should, range(lon_field, min_lon, 180.0)),
+                (Occur::Should, range(lon_field, -180.0, max_lon)),
+            ]))
+        };
+
+        Ok(Box::new(BooleanQuery::new(vec![
+            (Occur::Must, range(lat_field, min_lat, max_lat)),
+            (Occur::Must, lon_query),
+        ])))
+    }
+
+    /// Look up the latitude and longitude fields of a geo field
+    fn geo_fields(&self, field_name: &str) -> Result<(Field, Field)> {
+        let schema_manager = &self.collection.schema_manager;
+        match schema_manager.schema_definition().fields.get(field_name) {
+            Some(FieldType::Geo { .. }) => {}
+            Some(_) => {
+                return Err(SearchEngineError::QueryError(format!(
+                    "Field '{}' is not a geo field",
+                    field_name
+                )));
+            }
+            None => {
+                return Err(SearchEngineError::FieldNotFound {
+                    field: field_name.to_string(),
+                });
+            }
+        }
+
+        schema_manager
+            .get_geo_fields(field_name)
+            .ok_or_else(|| SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            })
+    }
+
+    /// Fast column type of a field, or `None` if it is not a fast numeric or date field
+    fn numeric_column(&self, field_name: &str) -> Result<Option<NumericColumn>> {
+        self.collection
+            .schema_manager
+            .schema_definition()
+            .fields
+            .get(field_name)
+            .map(NumericColumn::of)
+            .ok_or_else(|| SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            })
+    }
+
+    /// Build a query matching documents with at least one indexed value in a field
+    ///
+    /// Fast fields are answered from their column. Other numeric fields use a
+    /// range covering every value, and text and facet fields match any term.
+    fn build_exists_query(&self, field_name: &str) -> Result<Box<dyn Query>> {
+        let schema_manager = &self.collection.schema_manager;
+        let field = schema_manager.get_field(field_name).ok_or_else(|| {
+            SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            }
+        })?;
+
+        match schema_manager.schema_definition().fields.get(field_name) {
+            Some(
+                FieldType::I64 {
+                    indexed: true,
+                    fast,
+                    ..
+                }
+                | FieldType::U64 {
+                    indexed: true,
+                    fast,
+                    ..
+                }
+                | FieldType::F64 {
+                    indexed: true,
+                    fast,
+                    ..
+                }
+                | FieldType::Date {
+                    indexed: true,
+                    fast,
+                    ..
+                }
+                | FieldType::Bool {
+                    indexed: true,
+                    fast,
+                    ..
+                },
+            ) => {
+                if *fast {
+                    Ok(Box::new(ExistsQuery::new(field_name.to_string(), false)))
+                } else {
+                    let min = self.min_range_term(field_name, field)?;
+                    Ok(Box::new(RangeQuery::new(
+                        std::ops::Bound::Included(min),
+                        std::ops::Bound::Unbounded,
+                    )))
+                }
+            }
+            Some(FieldType::Text { indexed: true, .. } | FieldType::Facet { .. }) => {
+                self.build_regex_query(field, ".*")
+            }
+            None if field_name == schema_manager.id_field_name() => Ok(Box::new(AllQuery)),
+            _ => Err(SearchEngineError::QueryError(format!(
+                "Field '{}' is not indexed",
+                field_name
+            ))),
+        }
+    }
+
+    /// Build a regex query over the terms of a field
+    ///
+    /// The regex is compiled to an automaton and intersected with the field's
+    /// term dictionary, so patterns with a leading wildcard such as `.*foo` must
+    /// visit every term of the field and can be slow on large indexes. A pattern
+    /// matching more than `max_expansions` terms of a segment fails the search.
+    fn build_regex_query(&self, field: Field, pattern: &str) -> Result<Box<dyn Query>> {
+        let query = BoundedRegexQuery::from_pattern(pattern, field, self.config.max_expansions)
+            .map_err(|e| {
+                SearchEngineError::QueryError(format!("Invalid regex '{}': {}", pattern, e))
+            })?;
+        Ok(Box::new(query))
+    }
+
+    /// Look up a facet field by name
+    fn facet_field(&self, field_name: &str) -> Result<Field> {
+        match self
+            .collection
+            .schema_manager
+            .schema_definition()
+            .fields
+            .get(field_name)
+        {
+            Some(FieldType::Facet { .. }) => {}
+            Some(_) => {
+                return Err(SearchEngineError::QueryError(format!(
+                    "Field '{}' is not a facet field",
+                    field_name
+                )));
+            }
+            None => {
+                return Err(SearchEngineError::FieldNotFound {
+                    field: field_name.to_string(),
+                });
+            }
+        }
+
+        self.collection
+            .schema_manager
+            .get_field(field_name)
+            .ok_or_else(|| SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            })
+    }
+
+    /// Look up a field that can be matched by term patterns
+    fn indexed_text_field(&self, field_name: &str) -> Result<Field> {
+        match self
+            .collection
+            .schema_manager
+            .schema_definition()
+            .fields
+            .get(field_name)
+        {
+            Some(FieldType::Text { indexed: true, .. }) => {}
+            Some(_) => {
+                return Err(SearchEngineError::QueryError(format!(
+                    "Field '{}' is not an indexed text field",
+                    field_name
+                )));
+            }
+            None if field_name == self.collection.schema_manager.id_field_name() => {}
+            None => {
+                return Err(SearchEngineError::FieldNotFound {
+                    field: field_name.to_string(),
+                });
+            }
+        }
+
+        self.collection
+            .schema_manager
+            .get_field(field_name)
+            .ok_or_else(|| SearchEngineError::FieldNotFound {
+                field: field_name.to_string(),
+            })
+    }
+
+    /// Query matching what `empty_query_behavior` says text with no terms matches
+    fn empty_text_query(&self) -> Box<dyn Query> {
+        match self.config.empty_query_behavior {
+            EmptyQueryBehavior::MatchAll => Box::new(AllQuery),
+            EmptyQueryBehavior::MatchNone => Box::new(EmptyQuery),
+        }
+    }
+
+    /// Parse full-text query text over boosted fields, scoring its terms with the
+    /// configured scoring mode
+    fn parse_full_text(&self, fields: &[(Field, f32)], text: &str) -> Result<Box<dyn Query>> {
+        let mut parser = QueryParser::for_index(
+            &self.collection.index,
+            fields.iter().map(|(field, _)| *field).collect(),
+        );
+        let scoring = match self.config.scoring_mode {
+            ScoringMode::Bm25 => TermScoring::Bm25 {
+                k1: self.config.bm25_k1,
+                b: self.config.bm25_b,
+            },
+            ScoringMode::TfIdf => TermScoring::TfIdf,
+        };
+        let query = if scoring.is_builtin() {
+            for &(field, boost) in fields {
+                parser.set_field_boost(field, boost);
+            }
+            parser.parse_query(text)
+        } else {
+            scoring.parse(&parser, &fields.iter().copied().collect(), text)
+        };
+        query.map_err(|e| {
+            SearchEngineError::QueryError(format!("Failed to parse query '{}': {}", text, e))
+        })
+    }
+
+    /// Check whether full-text query text yields no terms for a field
+    fn has_no_terms(&self, field: Field, text: &str) -> bool {
+        if text.trim().is_empty() {
+            return true;
+        }
+
+        match self.collection.index.tokenizer_for_field(field) {
+            Ok(mut analyzer) => !analyzer.token_stream(text).advance(),
+            Err(_) => false,
+        }
+    }
+
+    /// Build a range bound term from a numeric or date value
+    fn build_range_term(&self, field: Field, value: &FieldValue) -> Result<Term> {
+        match value {
+            FieldValue::I64(num) => Ok(Term::from_field_i64(field, *num)),
+            FieldValue::U64(num) => Ok(Term::from_field_u64(field, *num)),
+            FieldValue::F64(num) => Ok(Term::from_field_f64(field, *num)),
+            FieldValue::Date(date) => Ok(Term::from_field_date(
+                field,
+                tantivy::DateTime::from_timestamp_secs(date.timestamp()),
+            )),
+            _ => Err(SearchEngineError::QueryError(
+                "Range query requires i64, u64, f64, or date values".to_string(),
+            )),
+        }
+    }
+
+    /// Build the lowest possible range term for a field, based on its schema type
+    fn min_range_term(&self, field_name: &str, field: Field) -> Result<Term> {
+        let field_type = self
+            .collection
+            .schema_manager
+            .schema_definition()
+            .fields
+            .get(field_name);
+
+        match field_type {
+            Some(FieldType::I64 { .. }) => Ok(Term::from_field_i64(field, i64::MIN)),
+            Some(FieldType::U64 { .. }) => Ok(Term::from_field_u64(field, u64::MIN)),
+            Some(FieldType::Bool { .. }) => Ok(Term::from_field_bool(field, false)),
+            Some(FieldType::F64 { .. }) => Ok(Term::from_field_f64(field, f64::NEG_INFINITY)),
+            Some(FieldType::Date { .. }) => {
+                Ok(Term::from_field_date(field, tantivy::DateTime::MIN))
+            }
+            _ => Err(SearchEngineError::QueryError(format!(
+                "Field '{}' does not support range queries",
+                field_name
+            ))),
+        }
+    }
+
+    /// Run a term query's text through the field's tokenizer, so it matches
+    /// the token stored in the index
+    ///
+    /// Returns `None` when the tokenizer drops the text entirely, such as a
+    /// stop word. Text that splits into several tokens cannot match a single
+    /// term and is rejected. Fields without a tokenizer keep the text as is.
+    fn analyze_term(&self, field: Field, text: &str) -> Result<Option<String>> {
+        let Ok(mut analyzer) = self.collection.index.tokenizer_for_field(field) else {
+            return Ok(Some(text.to_string()));
+        };
+
+        let mut tokens = Vec::new();
+        analyzer
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.text.clone()));
+        match tokens.len() {
+            0 | 1 => Ok(tokens.pop()),
+            _ => Err(SearchEngineError::QueryError(format!(
+                "Term '{}' splits into {} tokens; use a full-text query instead",
+                text,
+                tokens.len()
+            ))),
+        }
+    }
+
+    /// Build a Tantivy term from field and value
+    fn build_term(&self, field: Field, value: &FieldValue) -> Result<tantivy::Term> {
+        let term = match value {
+            FieldValue::Text(text) => tantivy::Term::from_field_text(field, text),
+            FieldValue::I64(num) => tantivy::Term::from_field_i64(field, *num),
+            FieldValue::U64(num) => tantivy::Term::from_field_u64(field, *num),
+            FieldValue::Bool(flag) => tantivy::Term::from_field_bool(field, *flag),
+            FieldValue::F64(num) => tantivy::Term::from_field_f64(field, *num),
+            FieldValue::Date(date) => {
+                let dt = tantivy::DateTime::from_timestamp_secs(date.timestamp());
+                tantivy::Term::from_field_date(field, dt)
+            }
+            FieldValue::Facet(facet_str) => {
+                tantivy::Term::from_facet(field, &parse_facet(facet_str)?)
+            }
+            FieldValue::Bytes(bytes) => {
+                let schema = self.collection.index.schema();
+                let field_entry = schema.get_field_entry(field);
+                if !field_entry.is_indexed() {
+                    return Err(SearchEngineError::QueryError(format!(
+                        "Bytes field '{}' is not indexed",
+                        field_entry.name()
+                    )));
+                }
+                tantivy::Term::from_field_bytes(field, bytes)
+            }
+            FieldValue::PreTokenized(_) => {
+                return Err(SearchEngineError::QueryError(
+                    "Pre-tokenized text is not supported for term queries; query a single token"
+                        .to_string(),
+                ));
+            }
+            FieldValue::GeoPoint { .. } => {
+                return Err(SearchEngineError::QueryError(
+                    "Geo points are not supported for term queries; use a bounding box".to_string(),
+                ));
+            }
+            FieldValue::Json(_) => {
+                return Err(SearchEngineError::QueryError(
+                    "JSON objects are not supported for term queries; query a path with full-text search"
+                        .to_string(),
+                ));
+            }
+        };
+
+        Ok(term)
+    }
+
+    /// Convert Tantivy search result to our format
+    fn convert_search_hit(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
         score: Score,
+        projection: Option<&[String]>,
+        snippet_generators: &[(String, SnippetGenerator)],
+        term_matchers: &[TermOffsetMatcher],
     ) -> Result<SearchHit> {
         let doc: TantivyDocument = searcher.doc(doc_address)?;
 
         // Extract document ID
-        let id_field = self
-            .collection
-            .schema_manager
-            .get_field("_id")
-            .ok_or_else(|| SearchEngineError::search_error("ID field not found".to_string()))?;
+        let id_field = self.collection.schema_manager.id_field();
 
         let id = doc
             .get_first(id_field)
@@ -298,9 +1613,120 @@ impl SearchEngine {
             .to_string();
 
         // Convert document fields
-        let fields = self.collection.schema_manager.document_from_tantivy(&doc)?;
+        let fields = self
+            .collection
+            .schema_manager
+            .document_from_tantivy(&doc, projection)?;
+
+        // Highlight the query terms in the requested fields
+        let mut highlights = HashMap::new();
+        for (field_name, generator) in snippet_generators {
+            let snippet = generator.snippet_from_doc(&doc);
+            if !snippet.is_empty() {
+                highlights.insert(field_name.clone(), vec![snippet.to_html()]);
+            }
+        }
+
+        let mut matched_terms = HashMap::new();
+        for matcher in term_matchers {
+            if let Some(text) = doc.get_first(matcher.field).and_then(|v| v.as_str()) {
+                let offsets = matcher.offsets(text);
+                if !offsets.is_empty() {
+                    matched_terms.insert(matcher.field_name.clone(), offsets);
+                }
+            }
+        }
+
+        Ok(SearchHit {
+            id,
+            collection: self.collection.name.clone(),
+            score,
+            fields,
+            highlights,
+            matched_terms,
+            segment_ord: doc_address.segment_ord,
+            doc_id: doc_address.doc_id,
+        })
+    }
+
+    /// Create snippet generators for the requested highlight fields
+    ///
+    /// Only stored, indexed full-text fields can be highlighted; keyword and
+    /// non-text fields are skipped.
+    fn snippet_generators(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        search_query: &SearchQuery,
+    ) -> Result<Vec<(String, SnippetGenerator)>> {
+        let Some(highlight_fields) = &search_query.highlight_fields else {
+            return Ok(Vec::new());
+        };
+        let max_chars = search_query
+            .highlight_max_chars
+            .unwrap_or(DEFAULT_SNIPPET_MAX_CHARS);
+        let schema_manager = &self.collection.schema_manager;
+
+        let mut generators = Vec::new();
+        for field_name in highlight_fields {
+            let is_full_text = matches!(
+                schema_manager.schema_definition().fields.get(field_name),
+                Some(FieldType::Text {
+                    stored: true,
+                    indexed: true,
+                    tokenizer,
+                    ..
+                }) if tokenizer != "keyword"
+            );
+            let Some(field) = schema_manager.get_field(field_name) else {
+                continue;
+            };
+            if !is_full_text {
+                continue;
+            }
+
+            let mut generator = SnippetGenerator::create(searcher, query, field)?;
+            generator.set_max_num_chars(max_chars);
+            generators.push((field_name.clone(), generator));
+        }
+        Ok(generators)
+    }
+
+    /// Group the query's terms by field, for the stored text fields indexed with positions
+    fn term_offset_matchers(&self, query: &dyn Query) -> Result<Vec<TermOffsetMatcher>> {
+        let schema = self.collection.index.schema();
+        let mut terms_by_field: HashMap<Field, HashSet<String>> = HashMap::new();
+        query.query_terms(&mut |term, _| {
+            if let Some(text) = term.value().as_str() {
+                terms_by_field
+                    .entry(term.field())
+                    .or_default()
+                    .insert(text.to_string());
+            }
+        });
 
-        Ok(SearchHit { id, score, fields })
+        let mut matchers = Vec::new();
+        for (field, terms) in terms_by_field {
+            let entry = schema.get_field_entry(field);
+            let has_positions = matches!(
+                entry.field_type(),
+                tantivy::schema::FieldType::Str(options)
+                    if options.is_stored()
+                        && options
+                            .get_indexing_options()
+                            .is_some_and(|indexing| indexing.index_option().has_positions())
+            );
+            if !has_positions {
+                continue;
+            }
+            matchers.push(TermOffsetMatcher {
+                field_name: entry.name().to_string(),
+                field,
+                analyzer: self.collection.index.tokenizer_for_field(field)?,
+                terms,
+            });
+        }
+        Ok(matchers)
     }
 
     /// Sort search results by specified fields
@@ -310,18 +1736,23 @@ impl SearchEngine {
                 let a_value = a.fields.get(&sort_field.field);
                 let b_value = b.fields.get(&sort_field.field);
 
-                let ordering = match (a_value, b_value) {
-                    (Some(av), Some(bv)) => self.compare_field_values(av, bv),
-                    (Some(_), None) => std::cmp::Ordering::Greater,
-                    (None, Some(_)) => std::cmp::Ordering::Less,
+                // Missing values are placed before the order is applied to the rest
+                let missing_first = sort_field.missing == MissingOrder::First;
+                let final_ordering = match (a_value, b_value) {
+                    (Some(av), Some(bv)) => {
+                        let ordering = self.compare_field_values(av, bv);
+                        match sort_field.order {
+                            SortOrder::Asc => ordering,
+                            SortOrder::Desc => ordering.reverse(),
+                        }
+                    }
+                    (Some(_), None) if missing_first => std::cmp::Ordering::Greater,
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) if missing_first => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
                     (None, None) => std::cmp::Ordering::Equal,
                 };
 
-                let final_ordering = match sort_field.order {
-                    SortOrder::Asc => ordering,
-                    SortOrder::Desc => ordering.reverse(),
-                };
-
                 if final_ordering != std::cmp::Ordering::Equal {
                     return final_ordering;
                 }
@@ -341,6 +1772,8 @@ impl SearchEngine {
         match (a, b) {
             (FieldValue::Text(a), FieldValue::Text(b)) => a.cmp(b),
             (FieldValue::I64(a), FieldValue::I64(b)) => a.cmp(b),
+            (FieldValue::U64(a), FieldValue::U64(b)) => a.cmp(b),
+            (FieldValue::Bool(a), FieldValue::Bool(b)) => a.cmp(b),
             (FieldValue::F64(a), FieldValue::F64(b)) => {
                 a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
             }
@@ -352,9 +1785,2880 @@ impl SearchEngine {
     }
 }
 
+/// Parse a facet path such as `/electronics/phones`
+fn parse_facet(path: &str) -> Result<tantivy::schema::Facet> {
+    tantivy::schema::Facet::from_text(path)
+        .map_err(|e| SearchEngineError::QueryError(format!("Invalid facet '{}': {}", path, e)))
+}
+
+/// Translate a glob pattern into an anchored term regex
+///
+/// `*` and `?` become `.*` and `.`; every other character is matched literally.
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::with_capacity(pattern.len() * 2);
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '\\' | '.' | '+' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#' | '&'
+            | '-' | '~' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            _ => regex.push(c),
+        }
+    }
+    regex
+}
+
+/// Whether any document of the searcher's segments has a boost
+fn has_boosted_documents(searcher: &Searcher) -> bool {
+    searcher.segment_readers().iter().any(|segment_reader| {
+        segment_reader
+            .fast_fields()
+            .f64(BOOST_FIELD)
+            .is_ok_and(|column| column.values.num_vals() > 0)
+    })
+}
+
+/// Score tweaker multiplying each document's score by its `_boost` value
+///
+/// Documents without a boost, and collections created before boosts existed,
+/// keep a factor of 1.0.
+fn boost_score(segment_reader: &SegmentReader) -> impl Fn(DocId, Score) -> Score + use<> {
+    let boost_column = segment_reader.fast_fields().f64(BOOST_FIELD).ok();
+
+    move |doc: DocId, score: Score| {
+        let boost = boost_column
+            .as_ref()
+            .and_then(|column| column.first(doc))
+            .map_or(1.0, |boost| boost as Score);
+        score * boost
+    }
+}
+
 // Custom error for search-specific issues
 impl SearchEngineError {
     pub fn search_error(msg: impl Into<String>) -> Self {
         SearchEngineError::CustomError(format!("Search error: {}", msg.into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{IndexDocument, QueryKind};
+    use crate::{
+        EngineConfigBuilder, RustSearchEngine, create_engine_with_data_dir, schema_helpers,
+    };
+    use std::sync::{Arc, Mutex};
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, StopWordFilter, TextAnalyzer};
+    use tempfile::TempDir;
+    use tracing::Subscriber;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// Engine in `temp_dir` with an empty `products` catalog collection
+    fn products_engine(temp_dir: &TempDir) -> RustSearchEngine {
+        products_engine_with_config(
+            EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .build()
+                .unwrap(),
+        )
+    }
+
+    /// Engine with an empty `products` catalog collection
+    fn products_engine_with_config(config: EngineConfig) -> RustSearchEngine {
+        let engine = RustSearchEngine::new(config).unwrap();
+        engine
+            .create_collection(
+                "products".to_string(),
+                schema_helpers::product_catalog_schema(),
+            )
+            .unwrap();
+        engine
+    }
+
+    fn product(id: &str, name: &str, category: &str) -> IndexDocument {
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), FieldValue::Text(name.to_string()));
+        fields.insert(
+            "category".to_string(),
+            FieldValue::Facet(category.to_string()),
+        );
+        IndexDocument {
+            id: id.to_string(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_facet_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+
+        let docs = [
+            ("1", "phone", "/electronics/phones"),
+            ("2", "laptop", "/electronics/laptops"),
+            ("3", "tablet", "/electronics/tablets"),
+            ("4", "novel", "/books"),
+            ("5", "atlas", "/books"),
+            ("6", "puzzle", "/toys"),
+        ];
+        for (id, name, category) in docs {
+            engine
+                .add_document("products", product(id, name, category))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery {
+            facets: Some(vec!["category".to_string()]),
+            ..SearchQuery::new("products", QueryExpression::MatchAll)
+        };
+        let result = engine.search(query).unwrap();
+
+        let mut counts = result.facet_counts["category"].clone();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("/books".to_string(), 2),
+                ("/electronics".to_string(), 3),
+                ("/toys".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_facet_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+
+        let docs = [
+            ("1", "phone", "/electronics/phones"),
+            ("2", "charger", "/electronics/phones"),
+            ("3", "laptop", "/electronics/laptops"),
+            ("4", "novel", "/books"),
+        ];
+        for (id, name, category) in docs {
+            engine
+                .add_document("products", product(id, name, category))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery {
+            facets: Some(vec![
+                "category".to_string(),
+                "category:/electronics".to_string(),
+            ]),
+            ..SearchQuery::new("products", QueryExpression::MatchAll)
+        };
+        let result = engine.search(query).unwrap();
+
+        let mut counts = result.facet_counts["category:/electronics"].clone();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("/electronics/laptops".to_string(), 1),
+                ("/electronics/phones".to_string(), 2),
+            ]
+        );
+        assert_eq!(result.facet_counts["category"].len(), 2);
+
+        let invalid = SearchQuery {
+            facets: Some(vec!["category:electronics".to_string()]),
+            ..SearchQuery::new("products", QueryExpression::MatchAll)
+        };
+        assert!(matches!(
+            engine.search(invalid),
+            Err(SearchEngineError::QueryError(_))
+        ));
+    }
+
+    #[test]
+    fn test_facet_term_drill_down() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+
+        let docs = [
+            ("1", "phone", "/electronics/phones"),
+            ("2", "case", "/electronics/phones/accessories"),
+            ("3", "laptop", "/electronics/laptops"),
+            ("4", "novel", "/books"),
+        ];
+        for (id, name, category) in docs {
+            engine
+                .add_document("products", product(id, name, category))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let ids_under = |facet_path: &str| {
+            let query = QueryExpression::FacetTerm {
+                field: "category".to_string(),
+                facet_path: facet_path.to_string(),
+            };
+            let result = engine.search(SearchQuery::new("products", query)).unwrap();
+            let mut ids: Vec<String> = result.documents.into_iter().map(|hit| hit.id).collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids_under("/electronics"), vec!["1", "2", "3"]);
+        assert_eq!(ids_under("/electronics/phones"), vec!["1", "2"]);
+        assert_eq!(ids_under("/electronics/phones/accessories"), vec!["2"]);
+        assert!(ids_under("/toys").is_empty());
+
+        // A plain term query on a facet value matches the same way
+        let query = QueryExpression::Term {
+            field: "category".to_string(),
+            value: FieldValue::Facet("/electronics/phones".to_string()),
+        };
+        let result = engine.search(SearchQuery::new("products", query)).unwrap();
+        assert_eq!(result.total_hits, 2);
+
+        let query = QueryExpression::FacetTerm {
+            field: "name".to_string(),
+            facet_path: "/electronics".to_string(),
+        };
+        assert!(matches!(
+            engine.search(SearchQuery::new("products", query)),
+            Err(SearchEngineError::QueryError(_))
+        ));
+    }
+
+    #[test]
+    fn test_stored_facet_is_returned_and_sortable() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: true,
+                max_depth: None,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+
+        let docs = [
+            ("1", "puzzle", "/toys"),
+            ("2", "novel", "/books"),
+            ("3", "phone", "/electronics/phones"),
+        ];
+        for (id, name, category) in docs {
+            engine
+                .add_document("products", product(id, name, category))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery {
+            facets: Some(vec!["category".to_string()]),
+            sort: Some(vec![SortField {
+                field: "category".to_string(),
+                order: SortOrder::Asc,
+                distance_from: None,
+                missing: MissingOrder::Last,
+            }]),
+            ..SearchQuery::new("products", QueryExpression::MatchAll)
+        };
+        let result = engine.search(query).unwrap();
+
+        assert_eq!(result.facet_counts["category"].len(), 3);
+        let categories: Vec<_> = result
+            .documents
+            .iter()
+            .map(|hit| match hit.fields.get("category") {
+                Some(FieldValue::Facet(facet)) => facet.as_str(),
+                other => panic!("expected a stored facet, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(categories, vec!["/books", "/electronics/phones", "/toys"]);
+    }
+
+    #[test]
+    fn test_read_consistency() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let collection = engine.open_collection("products").unwrap();
+
+        // Commit through the writer directly so the cached reader is not reloaded
+        collection
+            .add_document(product("1", "phone", "/electronics"))
+            .unwrap();
+        collection.lock_writer().unwrap().commit().unwrap();
+
+        let cached = SearchQuery {
+            consistency: ReadConsistency::Cached,
+            ..SearchQuery::new("products", QueryExpression::MatchAll)
+        };
+        assert_eq!(engine.search(cached.clone()).unwrap().total_hits, 0);
+
+        let latest = SearchQuery {
+            consistency: ReadConsistency::Latest,
+            ..cached
+        };
+        assert_eq!(engine.search(latest).unwrap().total_hits, 1);
+    }
+
+    #[test]
+    fn test_searches_reuse_cached_reader() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let collection = engine.open_collection("products").unwrap();
+        engine
+            .add_document("products", product("1", "phone", "/electronics"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let generation = collection.reader.searcher().generation().generation_id();
+        for _ in 0..1000 {
+            let query = SearchQuery::new("products", QueryExpression::MatchAll);
+            assert_eq!(engine.search(query).unwrap().total_hits, 1);
+        }
+        assert_eq!(
+            collection.reader.searcher().generation().generation_id(),
+            generation
+        );
+
+        // Committing reloads the shared reader
+        engine
+            .add_document("products", product("2", "laptop", "/electronics"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+        let query = SearchQuery::new("products", QueryExpression::MatchAll);
+        assert_eq!(engine.search(query).unwrap().total_hits, 2);
+    }
+
+    fn priced_product(id: &str, price: f64) -> IndexDocument {
+        let mut doc = product(id, "item", "/misc");
+        doc.fields
+            .insert("price".to_string(), FieldValue::F64(price));
+        doc
+    }
+
+    #[test]
+    fn test_histogram() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let prices = [5.0, 99.5, 100.0, 150.0, 199.0, 420.0];
+        for (i, price) in prices.into_iter().enumerate() {
+            engine
+                .add_document("products", priced_product(&i.to_string(), price))
+                .unwrap();
+        }
+        engine
+            .add_document("products", product("unpriced", "item", "/misc"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let all = QueryExpression::MatchAll;
+        assert_eq!(
+            engine.histogram("products", &all, "price", 100.0).unwrap(),
+            vec![(0.0, 2), (100.0, 3), (400.0, 1)]
+        );
+
+        let cheap = QueryExpression::Range {
+            field: "price".to_string(),
+            min: None,
+            max: Some(FieldValue::F64(150.0)),
+            inclusive: true,
+        };
+        assert_eq!(
+            engine
+                .histogram("products", &cheap, "price", 100.0)
+                .unwrap(),
+            vec![(0.0, 2), (100.0, 2)]
+        );
+
+        assert!(matches!(
+            engine.histogram("products", &all, "price", 0.0),
+            Err(SearchEngineError::QueryError(_))
+        ));
+        assert!(matches!(
+            engine.histogram("products", &all, "name", 100.0),
+            Err(SearchEngineError::QueryError(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_ended_range_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        for (id, price) in [("1", 25.0), ("2", 50.0), ("3", 100.0), ("4", 250.0)] {
+            engine
+                .add_document("products", priced_product(id, price))
+                .unwrap();
+        }
+        engine
+            .add_document("products", product("5", "unpriced", "/misc"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let range_hits = |min: Option<f64>, max: Option<f64>| {
+            let query = SearchQuery::new(
+                "products",
+                QueryExpression::Range {
+                    field: "price".to_string(),
+                    min: min.map(FieldValue::F64),
+                    max: max.map(FieldValue::F64),
+                    inclusive: true,
+                },
+            );
+            engine.search(query).unwrap().total_hits
+        };
+
+        assert_eq!(range_hits(Some(100.0), None), 2);
+        assert_eq!(range_hits(None, Some(50.0)), 2);
+        assert_eq!(range_hits(None, None), 4);
+    }
+
+    #[test]
+    fn test_update_by_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "category".to_string(),
+            FieldType::Facet {
+                stored: true,
+                max_depth: None,
+            },
+        );
+        schema.fields.insert(
+            "on_sale".to_string(),
+            FieldType::Bool {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        for (id, price) in [("1", 25.0), ("2", 50.0), ("3", 100.0), ("4", 250.0)] {
+            engine
+                .add_document("products", priced_product(id, price))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let mid_range = QueryExpression::Range {
+            field: "price".to_string(),
+            min: Some(FieldValue::F64(40.0)),
+            max: Some(FieldValue::F64(150.0)),
+            inclusive: true,
+        };
+        let patch = HashMap::from([("on_sale".to_string(), FieldValue::Bool(true))]);
+        let updated = engine
+            .update_by_query("products", &mid_range, patch)
+            .unwrap();
+        assert_eq!(updated, 2);
+        engine.commit_collection("products").unwrap();
+
+        let on_sale = SearchQuery::new(
+            "products",
+            QueryExpression::Term {
+                field: "on_sale".to_string(),
+                value: FieldValue::Bool(true),
+            },
+        );
+        let results = engine.search(on_sale).unwrap();
+        let mut ids: Vec<_> = results
+            .documents
+            .iter()
+            .map(|hit| hit.id.as_str())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["2", "3"]);
+        // Fields outside the patch keep their values
+        let hit = &results.documents[0];
+        assert_eq!(hit.fields["name"], FieldValue::Text("item".to_string()));
+        assert!(matches!(hit.fields["price"], FieldValue::F64(_)));
+        let all = SearchQuery::new("products", QueryExpression::MatchAll);
+        assert_eq!(engine.search(all).unwrap().total_hits, 4);
+
+        // Unstored fields cannot be carried over
+        engine
+            .create_collection(
+                "catalog".to_string(),
+                schema_helpers::product_catalog_schema(),
+            )
+            .unwrap();
+        let patch = HashMap::from([("price".to_string(), FieldValue::F64(1.0))]);
+        assert!(
+            engine
+                .update_by_query("catalog", &QueryExpression::MatchAll, patch)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_explain_no_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        engine
+            .add_document("products", product("1", "python book", "/books"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let query = QueryExpression::Bool {
+            must: Some(vec![
+                QueryExpression::Term {
+                    field: "name".to_string(),
+                    value: FieldValue::Text("book".to_string()),
+                },
+                QueryExpression::Term {
+                    field: "name".to_string(),
+                    value: FieldValue::Text("rust".to_string()),
+                },
+            ]),
+            should: None,
+            must_not: None,
+            minimum_should_match: None,
+        };
+
+        let reasons = engine.explain_no_match("products", &query, "1").unwrap();
+        assert_eq!(
+            reasons,
+            vec!["term 'rust' not present in field 'name'".to_string()]
+        );
+        assert!(matches!(
+            engine.explain_no_match("products", &query, "missing"),
+            Err(SearchEngineError::DocumentNotFound { id }) if id == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_not_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        for (id, category) in [("1", "/active"), ("2", "/archived"), ("3", "/active")] {
+            engine
+                .add_document("products", product(id, "widget", category))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let query = QueryExpression::Not(Box::new(QueryExpression::Term {
+            field: "category".to_string(),
+            value: FieldValue::Facet("/archived".to_string()),
+        }));
+        let result = engine
+            .search(SearchQuery::new("products", query.clone()))
+            .unwrap();
+        let mut ids: Vec<_> = result.documents.into_iter().map(|hit| hit.id).collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "3"]);
+
+        let reasons = engine.explain_no_match("products", &query, "2").unwrap();
+        assert_eq!(reasons.len(), 1);
+        assert!(reasons[0].starts_with("negated clause matched"));
+    }
+
+    #[test]
+    fn test_field_boost_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        for (id, views) in [("quiet", 10), ("popular", 100)] {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "content".to_string(),
+                FieldValue::Text("rust guide".to_string()),
+            );
+            fields.insert("view_count".to_string(), FieldValue::I64(views));
+            engine
+                .add_document(
+                    "blog",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("blog").unwrap();
+
+        let boosted = |field: &str| {
+            engine.search(SearchQuery::new(
+                "blog",
+                QueryExpression::FieldBoost {
+                    query: Box::new(QueryExpression::FullText {
+                        field: "content".to_string(),
+                        text: "rust".to_string(),
+                        boost: None,
+                    }),
+                    field: field.to_string(),
+                    factor: 0.5,
+                },
+            ))
+        };
+
+        let hits = boosted("view_count").unwrap().documents;
+        assert_eq!(hits[0].id, "popular");
+        assert_eq!(hits[1].id, "quiet");
+        assert!((hits[0].score / hits[1].score - 10.0).abs() < 1e-4);
+
+        assert!(matches!(
+            boosted("title"),
+            Err(SearchEngineError::QueryError(msg)) if msg.contains("fast numeric field")
+        ));
+    }
+
+    #[test]
+    fn test_pre_tokenized_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        let tokens = vec![
+            ("transformers".to_string(), 0),
+            ("attention".to_string(), 1),
+        ];
+        let mut fields = HashMap::new();
+        fields.insert("content".to_string(), FieldValue::PreTokenized(tokens));
+        engine
+            .add_document(
+                "blog",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("blog").unwrap();
+
+        let term_query = |value: &str| {
+            SearchQuery::new(
+                "blog",
+                QueryExpression::Term {
+                    field: "content".to_string(),
+                    value: FieldValue::Text(value.to_string()),
+                },
+            )
+        };
+        let results = engine.search(term_query("attention")).unwrap();
+        assert_eq!(results.total_hits, 1);
+        // The stored value is the tokens joined by spaces
+        assert_eq!(
+            results.documents[0].fields["content"],
+            FieldValue::Text("transformers attention".to_string())
+        );
+        // Only whole tokens are indexed, not the words inside them
+        let results = engine.search(term_query("transform")).unwrap();
+        assert_eq!(results.total_hits, 0);
+
+        // Keyword fields are indexed without positions
+        let mut fields = HashMap::new();
+        fields.insert(
+            "author".to_string(),
+            FieldValue::PreTokenized(vec![("alice".to_string(), 0)]),
+        );
+        let result = engine.add_document(
+            "blog",
+            IndexDocument {
+                id: "2".to_string(),
+                fields,
+            },
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_return_term_offsets() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        let content = "Learning Rust is fun, and rust compiles fast";
+        let mut fields = HashMap::new();
+        fields.insert("content".to_string(), FieldValue::Text(content.to_string()));
+        fields.insert(
+            "title".to_string(),
+            FieldValue::Text("Rust notes".to_string()),
+        );
+        engine
+            .add_document(
+                "blog",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("blog").unwrap();
+
+        let mut query = SearchQuery::new(
+            "blog",
+            QueryExpression::FullText {
+                field: "content".to_string(),
+                text: "rust".to_string(),
+                boost: None,
+            },
+        );
+        let hit = &engine.search(query.clone()).unwrap().documents[0];
+        assert!(hit.matched_terms.is_empty());
+
+        query.return_term_offsets = true;
+        let hit = &engine.search(query).unwrap().documents[0];
+        let offsets = &hit.matched_terms["content"];
+        assert_eq!(offsets, &[(9, 13), (26, 30)]);
+        for (from, to) in offsets {
+            assert!(content[*from..*to].eq_ignore_ascii_case("rust"));
+        }
+        // Only fields the query has terms for are matched
+        assert!(!hit.matched_terms.contains_key("title"));
+    }
+
+    #[test]
+    fn test_term_query_uses_field_tokenizer() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        engine
+            .add_document("products", product("1", "hello world", "/books"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let term = |text: &str| {
+            let query = QueryExpression::Term {
+                field: "name".to_string(),
+                value: FieldValue::Text(text.to_string()),
+            };
+            engine.search(SearchQuery::new("products", query))
+        };
+
+        assert_eq!(term("Hello").unwrap().total_hits, 1);
+        assert_eq!(term("hello").unwrap().total_hits, 1);
+        assert_eq!(term("goodbye").unwrap().total_hits, 0);
+        assert!(matches!(
+            term("hello world"),
+            Err(SearchEngineError::QueryError(_))
+        ));
+    }
+
+    #[test]
+    fn test_explain_score() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        engine
+            .add_document("products", product("1", "rust book", "/books"))
+            .unwrap();
+        engine
+            .add_document("products", product("2", "python book", "/books"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery::new(
+            "products",
+            QueryExpression::Term {
+                field: "name".to_string(),
+                value: FieldValue::Text("rust".to_string()),
+            },
+        );
+        let explanation = engine.explain(query.clone(), "1").unwrap();
+        assert!(explanation.contains("rust"));
+
+        let parsed: serde_json::Value = serde_json::from_str(&explanation).unwrap();
+        assert!(parsed["value"].as_f64().unwrap() > 0.0);
+
+        assert!(matches!(
+            engine.explain(query.clone(), "2"),
+            Err(SearchEngineError::QueryError(_))
+        ));
+        assert!(matches!(
+            engine.explain(query, "missing"),
+            Err(SearchEngineError::DocumentNotFound { id }) if id == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_document_boost() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+
+        let mut boosted = product("2", "red shoe", "/shoes");
+        boosted
+            .fields
+            .insert(BOOST_FIELD.to_string(), FieldValue::F64(2.0));
+        engine
+            .add_document("products", product("1", "red shoe", "/shoes"))
+            .unwrap();
+        engine.add_document("products", boosted).unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery::new(
+            "products",
+            QueryExpression::FullText {
+                field: "name".to_string(),
+                text: "shoe".to_string(),
+                boost: None,
+            },
+        );
+        let result = engine.search(query).unwrap();
+
+        assert_eq!(result.documents[0].id, "2");
+        assert!((result.documents[0].score - 2.0 * result.documents[1].score).abs() < 1e-4);
+
+        // Boosts are f32, like the scores they multiply
+        let mut oversized = product("3", "red shoe", "/shoes");
+        oversized
+            .fields
+            .insert(BOOST_FIELD.to_string(), FieldValue::F64(1e39));
+        assert!(matches!(
+            engine.add_document("products", oversized),
+            Err(SearchEngineError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_query_behavior() {
+        for (behavior, expected_hits) in [
+            (EmptyQueryBehavior::MatchAll, 2),
+            (EmptyQueryBehavior::MatchNone, 0),
+        ] {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .empty_query_behavior(behavior)
+                .build()
+                .unwrap();
+            let engine = RustSearchEngine::new(config).unwrap();
+            engine
+                .create_collection(
+                    "products".to_string(),
+                    schema_helpers::product_catalog_schema(),
+                )
+                .unwrap();
+
+            // Use an analyzer with stop words for the default tokenizer
+            let collection = engine.open_collection("products").unwrap();
+            collection.index.tokenizers().register(
+                "default",
+                TextAnalyzer::builder(SimpleTokenizer::default())
+                    .filter(LowerCaser)
+                    .filter(StopWordFilter::remove(vec![
+                        "the".to_string(),
+                        "of".to_string(),
+                    ]))
+                    .build(),
+            );
+
+            engine
+                .add_document("products", product("1", "phone", "/electronics"))
+                .unwrap();
+            engine
+                .add_document("products", product("2", "book", "/books"))
+                .unwrap();
+            engine.commit_collection("products").unwrap();
+
+            for text in ["", "   \t ", "the of THE"] {
+                let query = SearchQuery::new(
+                    "products",
+                    QueryExpression::FullText {
+                        field: "name".to_string(),
+                        text: text.to_string(),
+                        boost: None,
+                    },
+                );
+                assert_eq!(engine.search(query).unwrap().total_hits, expected_hits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_stop_words() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .empty_query_behavior(EmptyQueryBehavior::MatchNone)
+            .build()
+            .unwrap();
+        let engine = RustSearchEngine::new(config).unwrap();
+        let mut schema = schema_helpers::text_collection_schema("articles", &[]);
+        schema.fields.insert(
+            "body".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "default".to_string(),
+                stop_words: Some(vec!["le".to_string(), "et".to_string()]),
+                normalize: None,
+            },
+        );
+        engine
+            .create_collection("articles".to_string(), schema)
+            .unwrap();
+
+        let mut fields = HashMap::new();
+        fields.insert(
+            "body".to_string(),
+            FieldValue::Text("Le chat et le chien".to_string()),
+        );
+        engine
+            .add_document(
+                "articles",
+                IndexDocument {
+                    id: "1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("articles").unwrap();
+
+        let hits = |text: &str| {
+            let query = SearchQuery::new(
+                "articles",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: text.to_string(),
+                    boost: None,
+                },
+            );
+            engine.search(query).unwrap().total_hits
+        };
+        assert_eq!(hits("le"), 0);
+        assert_eq!(hits("chat"), 1);
+        assert_eq!(hits("chien"), 1);
+
+        let terms = engine.top_terms("articles", "body", 10).unwrap();
+        assert!(terms.iter().all(|(term, _)| term != "le" && term != "et"));
+        assert!(terms.iter().any(|(term, _)| term == "chat"));
+    }
+
+    #[test]
+    fn test_bm25_length_normalization() {
+        let ranked_ids = |b: f32| {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .bm25_b(b)
+                .build()
+                .unwrap();
+            let engine = RustSearchEngine::new(config).unwrap();
+            let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+            engine
+                .create_collection("notes".to_string(), schema)
+                .unwrap();
+
+            let long_body = format!("rust rust {}", "filler ".repeat(20));
+            for (id, body) in [("short", "rust guide"), ("long", long_body.as_str())] {
+                let mut fields = HashMap::new();
+                fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+                engine
+                    .add_document(
+                        "notes",
+                        IndexDocument {
+                            id: id.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection("notes").unwrap();
+
+            let query = SearchQuery::new(
+                "notes",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: "rust".to_string(),
+                    boost: None,
+                },
+            );
+            let result = engine.search(query).unwrap();
+            result
+                .documents
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect::<Vec<_>>()
+        };
+
+        // Without length normalization the higher term frequency wins
+        assert_eq!(ranked_ids(0.0), vec!["long", "short"]);
+        // Full normalization penalizes the long document
+        assert_eq!(ranked_ids(1.0), vec!["short", "long"]);
+    }
+
+    #[test]
+    fn test_bm25_parameters_score_boosted_clauses() {
+        let scores = |b: f32, query: QueryExpression| {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .bm25_b(b)
+                .build()
+                .unwrap();
+            let engine = RustSearchEngine::new(config).unwrap();
+            let schema = schema_helpers::text_collection_schema(
+                "notes",
+                &[("title", true, true), ("body", true, true)],
+            );
+            engine
+                .create_collection("notes".to_string(), schema)
+                .unwrap();
+
+            let long_body = format!("rust {}", "filler ".repeat(20));
+            for (id, body) in [("short", "rust guide"), ("long", long_body.as_str())] {
+                let mut fields = HashMap::new();
+                fields.insert("title".to_string(), FieldValue::Text("notes".to_string()));
+                fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+                engine
+                    .add_document(
+                        "notes",
+                        IndexDocument {
+                            id: id.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection("notes").unwrap();
+
+            let result = engine.search(SearchQuery::new("notes", query)).unwrap();
+            let score = |id: &str| {
+                result
+                    .documents
+                    .iter()
+                    .find(|hit| hit.id == id)
+                    .map(|hit| hit.score)
+                    .unwrap()
+            };
+            (score("short"), score("long"))
+        };
+        let full_text = |text: &str| QueryExpression::FullText {
+            field: "body".to_string(),
+            text: text.to_string(),
+            boost: None,
+        };
+        let multi_match = |boost: f32| QueryExpression::MultiMatch {
+            fields: vec![("title".to_string(), 1.0), ("body".to_string(), boost)],
+            text: "rust".to_string(),
+        };
+
+        for b in [0.0, 1.0] {
+            let (short, long) = scores(b, full_text("rust"));
+            for query in [full_text("rust^2"), multi_match(2.0)] {
+                let (boosted_short, boosted_long) = scores(b, query);
+                assert!((boosted_short - 2.0 * short).abs() < 1e-4);
+                assert!((boosted_long - 2.0 * long).abs() < 1e-4);
+            }
+        }
+        // Without length normalization both documents score alike, boosted or not
+        let (short, long) = scores(0.0, full_text("rust^2"));
+        assert!((short - long).abs() < 1e-4);
+        let (short, long) = scores(0.0, multi_match(2.0));
+        assert!((short - long).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_tf_idf_scoring_mode() {
+        let ranked_hits = |mode: ScoringMode| {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .scoring_mode(mode)
+                .build()
+                .unwrap();
+            let engine = RustSearchEngine::new(config).unwrap();
+            let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+            engine
+                .create_collection("notes".to_string(), schema)
+                .unwrap();
+
+            for (id, body) in [
+                ("single", "rust"),
+                ("repeated", "rust rust rust guide"),
+                ("other", "python guide"),
+            ] {
+                let mut fields = HashMap::new();
+                fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+                engine
+                    .add_document(
+                        "notes",
+                        IndexDocument {
+                            id: id.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection("notes").unwrap();
+
+            let query = SearchQuery::new(
+                "notes",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: "rust".to_string(),
+                    boost: None,
+                },
+            );
+            engine
+                .search(query)
+                .unwrap()
+                .documents
+                .into_iter()
+                .map(|hit| (hit.id, hit.score))
+                .collect::<Vec<_>>()
+        };
+
+        for mode in [ScoringMode::Bm25, ScoringMode::TfIdf] {
+            let hits = ranked_hits(mode);
+            assert_eq!(hits.len(), 2);
+            assert!(hits.iter().all(|(_, score)| *score > 0.0));
+            assert!(hits.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        }
+
+        let ids = |mode| {
+            ranked_hits(mode)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        // BM25 rewards the repeated term; TF-IDF penalizes the longer field more
+        assert_eq!(ids(ScoringMode::Bm25), vec!["repeated", "single"]);
+        assert_eq!(ids(ScoringMode::TfIdf), vec!["single", "repeated"]);
+    }
+
+    #[test]
+    fn test_tf_idf_scores_boosted_fields() {
+        let title_score = |mode: ScoringMode, title_boost: f32| {
+            let temp_dir = TempDir::new().unwrap();
+            let config = EngineConfigBuilder::new()
+                .data_dir(temp_dir.path())
+                .scoring_mode(mode)
+                .build()
+                .unwrap();
+            let engine = RustSearchEngine::new(config).unwrap();
+            let schema = schema_helpers::text_collection_schema(
+                "notes",
+                &[("title", true, true), ("body", true, true)],
+            );
+            engine
+                .create_collection("notes".to_string(), schema)
+                .unwrap();
+
+            for (id, title, body) in [
+                ("titled", "rust guide", "an introduction"),
+                ("other", "python guide", "no match here"),
+            ] {
+                let mut fields = HashMap::new();
+                fields.insert("title".to_string(), FieldValue::Text(title.to_string()));
+                fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+                engine
+                    .add_document(
+                        "notes",
+                        IndexDocument {
+                            id: id.to_string(),
+                            fields,
+                        },
+                    )
+                    .unwrap();
+            }
+            engine.commit_collection("notes").unwrap();
+
+            let query = SearchQuery::new(
+                "notes",
+                QueryExpression::MultiMatch {
+                    fields: vec![
+                        ("title".to_string(), title_boost),
+                        ("body".to_string(), 1.0),
+                    ],
+                    text: "rust".to_string(),
+                },
+            );
+            let result = engine.search(query).unwrap();
+            assert_eq!(result.documents.len(), 1);
+            assert_eq!(result.documents[0].id, "titled");
+            result.documents[0].score
+        };
+
+        let tf_idf = title_score(ScoringMode::TfIdf, 1.0);
+        let boosted_tf_idf = title_score(ScoringMode::TfIdf, 2.0);
+        // Boosted fields are scored by TF-IDF too, not left on BM25
+        assert!((boosted_tf_idf - 2.0 * tf_idf).abs() < 1e-4);
+        assert!((boosted_tf_idf - title_score(ScoringMode::Bm25, 2.0)).abs() > 1e-4);
+    }
+
+    #[test]
+    fn test_multi_match_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let schema = schema_helpers::text_collection_schema(
+            "posts",
+            &[
+                ("title", true, true),
+                ("content", true, true),
+                ("summary", true, false),
+            ],
+        );
+        engine
+            .create_collection("posts".to_string(), schema)
+            .unwrap();
+
+        for (id, title, content) in [
+            ("1", "Rust handbook", "Systems programming"),
+            ("2", "Gardening notes", "Growing tomatoes"),
+            ("3", "Compost guide", "Soil care"),
+            ("4", "Soil care", "Compost guide"),
+        ] {
+            let mut fields = HashMap::new();
+            fields.insert("title".to_string(), FieldValue::Text(title.to_string()));
+            fields.insert("content".to_string(), FieldValue::Text(content.to_string()));
+            engine
+                .add_document(
+                    "posts",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("posts").unwrap();
+
+        let multi_match = |fields: &[(&str, f32)], text: &str| {
+            let query = SearchQuery::new(
+                "posts",
+                QueryExpression::MultiMatch {
+                    fields: fields
+                        .iter()
+                        .map(|(field, boost)| (field.to_string(), *boost))
+                        .collect(),
+                    text: text.to_string(),
+                },
+            );
+            engine.search(query)
+        };
+        let both = [("title", 2.0), ("content", 1.0)];
+
+        let ids = |text| sorted_ids(multi_match(&both, text).unwrap());
+        assert_eq!(ids("handbook"), vec!["1"]);
+        assert_eq!(ids("programming"), vec!["1"]);
+        assert_eq!(ids("handbook tomatoes"), vec!["1", "2"]);
+
+        // The boosted field's match ranks first
+        let top = |fields: &[(&str, f32)]| {
+            let result = multi_match(fields, "compost").unwrap();
+            assert_eq!(result.total_hits, 2);
+            result.documents[0].id.clone()
+        };
+        assert_eq!(top(&[("title", 10.0), ("content", 1.0)]), "3");
+        assert_eq!(top(&[("title", 1.0), ("content", 10.0)]), "4");
+
+        assert!(matches!(
+            multi_match(&[("title", 1.0), ("missing", 1.0)], "rust"),
+            Err(SearchEngineError::FieldNotFound { .. })
+        ));
+        assert!(matches!(
+            multi_match(&[("title", 1.0), ("summary", 1.0)], "rust"),
+            Err(SearchEngineError::QueryError(msg)) if msg.contains("'summary'")
+        ));
+    }
+
+    #[test]
+    fn test_hit_doc_address() {
+        let temp_dir = TempDir::new().unwrap();
+        // A single indexing thread writes every document to one segment
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let engine = RustSearchEngine::new(config).unwrap();
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+
+        for id in ["a", "b", "c"] {
+            let mut fields = HashMap::new();
+            fields.insert("body".to_string(), FieldValue::Text(format!("note {}", id)));
+            engine
+                .add_document(
+                    "notes",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("notes").unwrap();
+
+        let addresses = || {
+            let result = engine
+                .search(SearchQuery::new("notes", QueryExpression::MatchAll))
+                .unwrap();
+            let mut addresses: Vec<_> = result
+                .documents
+                .into_iter()
+                .map(|hit| (hit.id, hit.segment_ord, hit.doc_id))
+                .collect();
+            addresses.sort();
+            addresses
+        };
+
+        let first = addresses();
+        assert_eq!(
+            first,
+            vec![
+                ("a".to_string(), 0, 0),
+                ("b".to_string(), 0, 1),
+                ("c".to_string(), 0, 2),
+            ]
+        );
+        assert_eq!(addresses(), first);
+    }
+
+    #[test]
+    fn test_equal_scores_ordered_by_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+
+        // Identical bodies score the same; separate commits spread them over segments
+        for id in ["c", "a", "b"] {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "body".to_string(),
+                FieldValue::Text("rust search".to_string()),
+            );
+            engine
+                .add_document(
+                    "notes",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+            engine.commit_collection("notes").unwrap();
+        }
+
+        let query = SearchQuery::new(
+            "notes",
+            QueryExpression::FullText {
+                field: "body".to_string(),
+                text: "rust".to_string(),
+                boost: None,
+            },
+        );
+        for _ in 0..5 {
+            let results = engine.search(query.clone()).unwrap();
+            let ids: Vec<_> = results
+                .documents
+                .iter()
+                .map(|hit| hit.id.as_str())
+                .collect();
+            assert_eq!(ids, ["a", "b", "c"]);
+        }
+
+        let mut second_page = query.clone();
+        second_page.limit = Some(1);
+        second_page.offset = Some(1);
+        let results = engine.search(second_page).unwrap();
+        assert_eq!(results.documents[0].id, "b");
+
+        // The cut after the first hit falls inside the tie, so the lowest ID is kept
+        let mut first_hit = query.clone();
+        first_hit.limit = Some(1);
+        let results = engine.search(first_hit).unwrap();
+        assert_eq!(results.documents[0].id, "a");
+        assert_eq!(results.total_hits, 3);
+        assert!(results.has_more);
+    }
+
+    #[test]
+    fn test_search_after_pagination() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+
+        // Five distinct scores, each shared by twenty documents
+        for i in 0..100 {
+            let body = format!("{}filler text", "rust ".repeat(i % 5 + 1));
+            let mut fields = HashMap::new();
+            fields.insert("body".to_string(), FieldValue::Text(body));
+            engine
+                .add_document(
+                    "notes",
+                    IndexDocument {
+                        id: format!("doc{:03}", i),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("notes").unwrap();
+
+        let page = |cursor: Option<(Score, String)>| {
+            let mut query = SearchQuery::new(
+                "notes",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: "rust".to_string(),
+                    boost: None,
+                },
+            );
+            query.limit = Some(10);
+            query.search_after = cursor;
+            engine.search(query).unwrap()
+        };
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        for _ in 0..10 {
+            let result = page(cursor.take());
+            assert_eq!(result.total_hits, 100);
+            assert_eq!(result.documents.len(), 10);
+
+            let last = result.documents.last().unwrap();
+            cursor = Some((last.score, last.id.clone()));
+            seen.extend(result.documents.into_iter().map(|hit| (hit.score, hit.id)));
+        }
+
+        // Pages join up in score order, ties broken by ID
+        assert!(seen.windows(2).all(|pair| {
+            pair[0].0 > pair[1].0 || (pair[0].0 == pair[1].0 && pair[0].1 < pair[1].1)
+        }));
+        let mut ids: Vec<_> = seen.into_iter().map(|(_, id)| id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 100);
+
+        // Nothing ranks after the last hit
+        assert!(page(cursor).documents.is_empty());
+    }
+
+    #[test]
+    fn test_search_after_ties_across_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let collection = engine.open_collection("products").unwrap();
+        let id_field = collection.schema_manager.id_field();
+        assert!(
+            collection
+                .index
+                .schema()
+                .get_field_entry(id_field)
+                .is_fast()
+        );
+
+        // Every document scores the same, with IDs out of order across three segments
+        for batch in 0..3 {
+            for i in 0..10 {
+                let id = format!("p{:02}", (batch * 10 + i) * 7 % 30);
+                engine
+                    .add_document("products", product(&id, "item", "/misc"))
+                    .unwrap();
+            }
+            engine.commit_collection("products").unwrap();
+        }
+
+        let mut ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let mut query = SearchQuery::new("products", QueryExpression::MatchAll);
+            query.limit = Some(4);
+            query.search_after = cursor.take();
+            let result = engine.search(query).unwrap();
+            let Some(last) = result.documents.last() else {
+                break;
+            };
+            cursor = Some((last.score, last.id.clone()));
+            ids.extend(result.documents.into_iter().map(|hit| hit.id));
+        }
+
+        let expected: Vec<_> = (0..30).map(|i| format!("p{:02}", i)).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_pagination_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+        for i in 0..25 {
+            let mut fields = HashMap::new();
+            fields.insert("body".to_string(), FieldValue::Text("note".to_string()));
+            engine
+                .add_document(
+                    "notes",
+                    IndexDocument {
+                        id: i.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("notes").unwrap();
+
+        let page = |offset: usize| {
+            let mut query = SearchQuery::new("notes", QueryExpression::MatchAll);
+            query.limit = Some(10);
+            query.offset = Some(offset);
+            engine.search(query).unwrap()
+        };
+
+        let result = page(10);
+        assert_eq!((result.limit, result.offset), (10, 10));
+        assert_eq!(result.documents.len(), 10);
+        assert!(result.has_more);
+
+        let result = page(20);
+        assert_eq!(result.documents.len(), 5);
+        assert!(!result.has_more);
+
+        // A full last page has nothing after it either
+        let mut query = SearchQuery::new("notes", QueryExpression::MatchAll);
+        query.limit = Some(5);
+        query.offset = Some(20);
+        assert!(!engine.search(query).unwrap().has_more);
+    }
+
+    #[test]
+    fn test_normalize_scores() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let schema = schema_helpers::text_collection_schema("notes", &[("body", true, true)]);
+        engine
+            .create_collection("notes".to_string(), schema)
+            .unwrap();
+        for (id, body) in [
+            ("1", "rust"),
+            ("2", "rust and more words"),
+            ("3", "rust rust rust"),
+            ("4", "python"),
+        ] {
+            let mut fields = HashMap::new();
+            fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+            engine
+                .add_document(
+                    "notes",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("notes").unwrap();
+
+        let search = |normalize_scores: bool, offset: usize| {
+            let mut query = SearchQuery::new(
+                "notes",
+                QueryExpression::FullText {
+                    field: "body".to_string(),
+                    text: "rust".to_string(),
+                    boost: None,
+                },
+            );
+            query.normalize_scores = normalize_scores;
+            query.offset = Some(offset);
+            engine.search(query).unwrap().documents
+        };
+
+        let raw = search(false, 0);
+        let normalized = search(true, 0);
+        assert_eq!(normalized[0].score, 1.0);
+        assert_eq!(normalized[1].score, raw[1].score / raw[0].score);
+        assert!(
+            normalized
+                .iter()
+                .all(|hit| hit.score > 0.0 && hit.score <= 1.0)
+        );
+        let ids = |hits: &[SearchHit]| hits.iter().map(|hit| hit.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&raw), ids(&normalized));
+        assert!(
+            normalized
+                .windows(2)
+                .all(|pair| pair[0].score >= pair[1].score)
+        );
+
+        // Later pages are scaled by the overall best score, not their own
+        let second_page = search(true, 1);
+        assert_eq!(second_page[0].score, normalized[1].score);
+    }
+
+    #[test]
+    fn test_max_result_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .max_result_window(100)
+            .build()
+            .unwrap();
+        let engine = products_engine_with_config(config);
+
+        let window = |offset: usize, limit: usize| {
+            engine.search(SearchQuery {
+                offset: Some(offset),
+                limit: Some(limit),
+                ..SearchQuery::new("products", QueryExpression::MatchAll)
+            })
+        };
+
+        assert!(window(90, 10).is_ok());
+        assert!(matches!(
+            window(91, 10),
+            Err(SearchEngineError::QueryError(msg)) if msg.starts_with("result window is too large")
+        ));
+        // Windows that overflow are rejected rather than wrapping around
+        assert!(matches!(
+            window(1, usize::MAX),
+            Err(SearchEngineError::QueryError(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_expansions() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .max_expansions(5)
+            .build()
+            .unwrap();
+        let engine = products_engine_with_config(config);
+        for i in 0..10 {
+            let name = format!("item{}", i);
+            engine
+                .add_document("products", product(&i.to_string(), &name, "/misc"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let regex = |pattern: &str| {
+            SearchQuery::new(
+                "products",
+                QueryExpression::Regex {
+                    field: "name".to_string(),
+                    pattern: pattern.to_string(),
+                },
+            )
+        };
+        assert_eq!(engine.search(regex("item[1-5]")).unwrap().total_hits, 5);
+        assert!(matches!(
+            engine.search(regex("item.*")),
+            Err(SearchEngineError::TantivyError(e)) if e.to_string().contains("more than 5 terms")
+        ));
+    }
+
+    #[test]
+    fn test_max_clause_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .max_clause_count(10)
+            .build()
+            .unwrap();
+        let engine = products_engine_with_config(config);
+
+        let should = |n: usize| QueryExpression::Bool {
+            must: None,
+            should: Some(vec![QueryExpression::MatchAll; n]),
+            must_not: None,
+            minimum_should_match: None,
+        };
+
+        // Nine nested clauses plus the wrapping clause is exactly at the limit
+        let at_limit = QueryExpression::Bool {
+            must: Some(vec![should(9)]),
+            should: None,
+            must_not: None,
+            minimum_should_match: None,
+        };
+        assert!(
+            engine
+                .search(SearchQuery::new("products", at_limit))
+                .is_ok()
+        );
+
+        let over_limit = QueryExpression::Bool {
+            must: Some(vec![should(5), should(5)]),
+            should: None,
+            must_not: None,
+            minimum_should_match: None,
+        };
+        assert!(matches!(
+            engine.search(SearchQuery::new("products", over_limit)),
+            Err(SearchEngineError::QueryError(msg)) if msg.starts_with("too many clauses")
+        ));
+    }
+
+    #[test]
+    fn test_wildcard_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        for (id, brand) in [("1", "prod_123"), ("2", "prod_456"), ("3", "other_1")] {
+            let mut doc = product(id, "item", "/misc");
+            doc.fields
+                .insert("brand".to_string(), FieldValue::Text(brand.to_string()));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let wildcard = |field: &str, pattern: &str| {
+            engine.search(SearchQuery::new(
+                "products",
+                QueryExpression::Wildcard {
+                    field: field.to_string(),
+                    pattern: pattern.to_string(),
+                },
+            ))
+        };
+
+        let mut ids: Vec<_> = wildcard("brand", "prod_*")
+            .unwrap()
+            .documents
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["1", "2"]);
+        assert_eq!(wildcard("brand", "other_?").unwrap().total_hits, 1);
+        assert_eq!(wildcard("brand", "prod.*").unwrap().total_hits, 0);
+        assert!(wildcard("price", "1*").is_err());
+    }
+
+    #[test]
+    fn test_regex_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        for (id, brand) in [("1", "abz"), ("2", "az"), ("3", "ab")] {
+            let mut doc = product(id, "item", "/misc");
+            doc.fields
+                .insert("brand".to_string(), FieldValue::Text(brand.to_string()));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let regex = |pattern: &str| {
+            engine.search(SearchQuery::new(
+                "products",
+                QueryExpression::Regex {
+                    field: "brand".to_string(),
+                    pattern: pattern.to_string(),
+                },
+            ))
+        };
+
+        let mut ids: Vec<_> = regex("a.*z")
+            .unwrap()
+            .documents
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["1", "2"]);
+        assert!(matches!(
+            regex("a(z"),
+            Err(SearchEngineError::QueryError(msg)) if msg.starts_with("Invalid regex")
+        ));
+    }
+
+    #[test]
+    fn test_bool_term_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "is_published".to_string(),
+            FieldType::Bool {
+                stored: true,
+                indexed: true,
+                fast: false,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        for (id, published) in [("1", true), ("2", false), ("3", true)] {
+            let mut doc = product(id, "item", "/misc");
+            doc.fields
+                .insert("is_published".to_string(), FieldValue::Bool(published));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let result = engine
+            .search(SearchQuery::new(
+                "products",
+                QueryExpression::Term {
+                    field: "is_published".to_string(),
+                    value: FieldValue::Bool(true),
+                },
+            ))
+            .unwrap();
+        let mut ids: Vec<_> = result.documents.iter().map(|hit| hit.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["1", "3"]);
+        assert!(matches!(
+            result.documents[0].fields.get("is_published"),
+            Some(FieldValue::Bool(true))
+        ));
+    }
+
+    #[test]
+    fn test_bytes_term_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "digest".to_string(),
+            FieldType::Bytes {
+                stored: true,
+                indexed: true,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        let digest = |seed: u8| {
+            (0..16)
+                .map(|i| seed.wrapping_mul(31).wrapping_add(i))
+                .collect()
+        };
+        for (id, seed) in [("1", 1), ("2", 2), ("3", 3)] {
+            let mut doc = product(id, "item", "/misc");
+            doc.fields
+                .insert("digest".to_string(), FieldValue::Bytes(digest(seed)));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let result = engine
+            .search(SearchQuery::new(
+                "products",
+                QueryExpression::Term {
+                    field: "digest".to_string(),
+                    value: FieldValue::Bytes(digest(2)),
+                },
+            ))
+            .unwrap();
+        assert_eq!(sorted_ids(result), vec!["2"]);
+        let result = engine
+            .search(SearchQuery::new(
+                "products",
+                QueryExpression::Term {
+                    field: "digest".to_string(),
+                    value: FieldValue::Bytes(vec![0; 16]),
+                },
+            ))
+            .unwrap();
+        assert_eq!(result.total_hits, 0);
+    }
+
+    #[test]
+    fn test_json_field_path_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "attributes".to_string(),
+            FieldType::Json {
+                stored: true,
+                indexed: true,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+
+        for (id, attributes) in [
+            ("1", serde_json::json!({"color": "red", "size": 42})),
+            ("2", serde_json::json!({"color": "blue", "size": 40})),
+        ] {
+            let mut doc = product(id, "shirt", "/clothing");
+            doc.fields
+                .insert("attributes".to_string(), FieldValue::Json(attributes));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let search = |text: &str| {
+            let query = QueryExpression::FullText {
+                field: "attributes".to_string(),
+                text: text.to_string(),
+                boost: None,
+            };
+            engine.search(SearchQuery::new("products", query)).unwrap()
+        };
+
+        let result = search("attributes.color:red");
+        assert_eq!(result.total_hits, 1);
+        assert_eq!(result.documents[0].id, "1");
+        assert_eq!(
+            result.documents[0].fields["attributes"],
+            FieldValue::Json(serde_json::json!({"color": "red", "size": 42}))
+        );
+        assert_eq!(search("attributes.size:40").documents[0].id, "2");
+        assert_eq!(search("attributes.color:green").total_hits, 0);
+
+        // Only objects fit a JSON field
+        let mut doc = product("3", "hat", "/clothing");
+        doc.fields.insert(
+            "attributes".to_string(),
+            FieldValue::Json(serde_json::json!("red")),
+        );
+        assert!(matches!(
+            engine.add_document("products", doc),
+            Err(SearchEngineError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_u64_term_and_range_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "serial".to_string(),
+            FieldType::U64 {
+                stored: true,
+                indexed: true,
+                fast: true,
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        let big = i64::MAX as u64 + 1;
+        for (id, serial) in [("1", 7), ("2", big), ("3", u64::MAX)] {
+            let mut doc = product(id, "item", "/misc");
+            doc.fields
+                .insert("serial".to_string(), FieldValue::U64(serial));
+            engine.add_document("products", doc).unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let ids = |query| {
+            let mut ids: Vec<_> = engine
+                .search(SearchQuery::new("products", query))
+                .unwrap()
+                .documents
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let exact = ids(QueryExpression::Term {
+            field: "serial".to_string(),
+            value: FieldValue::U64(big),
+        });
+        assert_eq!(exact, vec!["2"]);
+
+        let range = ids(QueryExpression::Range {
+            field: "serial".to_string(),
+            min: Some(FieldValue::U64(big)),
+            max: None,
+            inclusive: true,
+        });
+        assert_eq!(range, vec!["2", "3"]);
+
+        let unbounded = ids(QueryExpression::Range {
+            field: "serial".to_string(),
+            min: None,
+            max: None,
+            inclusive: true,
+        });
+        assert_eq!(unbounded, vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_approximate_top_k() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+
+        // A handful of documents mention the rare term; everything else is filler
+        let num_docs = 20_000;
+        for i in 0..num_docs {
+            let name = if i % 4_000 == 0 {
+                "gadget gadget gadget widget"
+            } else {
+                "widget"
+            };
+            engine
+                .add_document("products", product(&i.to_string(), name, "/misc"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let run = |approximate| {
+            let query = SearchQuery {
+                limit: Some(5),
+                approximate,
+                ..SearchQuery::new(
+                    "products",
+                    QueryExpression::FullText {
+                        field: "name".to_string(),
+                        text: "widget gadget".to_string(),
+                        boost: None,
+                    },
+                )
+            };
+            engine.search(query).unwrap()
+        };
+        let ids = |result: &SearchResult| {
+            let mut ids: Vec<_> = result.documents.iter().map(|hit| hit.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+
+        let exact = run(false);
+        assert!(!exact.approximate);
+        assert_eq!(exact.total_hits, num_docs);
+        assert_eq!(ids(&exact), vec!["0", "12000", "16000", "4000", "8000"]);
+
+        // Skipping finds the same top hits and still counts every match
+        let approximate = run(true);
+        assert!(approximate.approximate);
+        assert_eq!(approximate.total_hits, num_docs);
+        assert_eq!(ids(&approximate), ids(&exact));
+
+        // A boosted document is ranked exactly, so its boost applies
+        let mut boosted = product("boosted", "gadget", "/misc");
+        boosted
+            .fields
+            .insert(BOOST_FIELD.to_string(), FieldValue::F64(1000.0));
+        engine.add_document("products", boosted).unwrap();
+        engine.commit_collection("products").unwrap();
+        let approximate = run(true);
+        assert!(!approximate.approximate);
+        assert_eq!(approximate.total_hits, num_docs + 1);
+        assert_eq!(approximate.documents[0].id, "boosted");
+    }
+
+    #[test]
+    fn test_highlights() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let mut doc = product("1", "wireless noise cancelling headphones", "/audio");
+        doc.fields.insert(
+            "brand".to_string(),
+            FieldValue::Text("headphones".to_string()),
+        );
+        engine.add_document("products", doc).unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery {
+            highlight_fields: Some(vec!["name".to_string(), "brand".to_string()]),
+            ..SearchQuery::new(
+                "products",
+                QueryExpression::FullText {
+                    field: "name".to_string(),
+                    text: "headphones".to_string(),
+                    boost: None,
+                },
+            )
+        };
+        let result = engine.search(query).unwrap();
+
+        let highlights = &result.documents[0].highlights;
+        assert_eq!(
+            highlights["name"],
+            vec!["wireless noise cancelling <b>headphones</b>".to_string()]
+        );
+        // Keyword fields are not eligible for highlighting
+        assert!(!highlights.contains_key("brand"));
+    }
+
+    #[test]
+    fn test_doc_order_sort() {
+        let temp_dir = TempDir::new().unwrap();
+        // A single indexing thread keeps the batch in one segment
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let engine = products_engine_with_config(config);
+        let ids: Vec<String> = ["m", "c", "x", "a", "q"].map(String::from).to_vec();
+        for id in &ids {
+            engine
+                .add_document("products", product(id, "item", "/misc"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let sorted_ids = |order| {
+            let query = SearchQuery {
+                sort: Some(vec![SortField {
+                    field: DOC_ORDER_FIELD.to_string(),
+                    order,
+                    distance_from: None,
+                    missing: MissingOrder::Last,
+                }]),
+                ..SearchQuery::new("products", QueryExpression::MatchAll)
+            };
+            let result = engine.search(query).unwrap();
+            assert_eq!(result.total_hits, 5);
+            result
+                .documents
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted_ids(SortOrder::Asc), ids);
+        let mut reversed = ids.clone();
+        reversed.reverse();
+        assert_eq!(sorted_ids(SortOrder::Desc), reversed);
+    }
+
+    #[test]
+    fn test_field_projection() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        let mut fields = HashMap::new();
+        fields.insert("title".to_string(), FieldValue::Text("Hello".to_string()));
+        fields.insert(
+            "content".to_string(),
+            FieldValue::Text("A long body that callers rarely need".to_string()),
+        );
+        engine
+            .add_document(
+                "blog",
+                IndexDocument {
+                    id: "post-1".to_string(),
+                    fields,
+                },
+            )
+            .unwrap();
+        engine.commit_collection("blog").unwrap();
+
+        let query = SearchQuery {
+            fields: Some(vec!["title".to_string()]),
+            ..SearchQuery::new("blog", QueryExpression::MatchAll)
+        };
+        let hit = &engine.search(query).unwrap().documents[0];
+        assert_eq!(hit.id, "post-1");
+        assert!(matches!(&hit.fields["title"], FieldValue::Text(title) if title == "Hello"));
+        assert!(!hit.fields.contains_key("content"));
+
+        let hit = &engine
+            .search(SearchQuery::new("blog", QueryExpression::MatchAll))
+            .unwrap()
+            .documents[0];
+        assert!(hit.fields.contains_key("content"));
+    }
+
+    #[test]
+    fn test_allowed_query_types() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.allowed_query_types = Some([QueryKind::Term, QueryKind::FullText].into());
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        let mut doc = product("1", "item", "/misc");
+        doc.fields
+            .insert("brand".to_string(), FieldValue::Text("acme".to_string()));
+        engine.add_document("products", doc).unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let regex = engine.search(SearchQuery::new(
+            "products",
+            QueryExpression::Regex {
+                field: "brand".to_string(),
+                pattern: "ac.*".to_string(),
+            },
+        ));
+        assert!(matches!(
+            regex,
+            Err(SearchEngineError::QueryError(msg)) if msg == "query type 'regex' not allowed"
+        ));
+
+        let term = engine
+            .search(SearchQuery::new(
+                "products",
+                QueryExpression::Term {
+                    field: "brand".to_string(),
+                    value: FieldValue::Text("acme".to_string()),
+                },
+            ))
+            .unwrap();
+        assert_eq!(term.total_hits, 1);
+    }
+
+    #[test]
+    fn test_count_matches_total_hits() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        for (id, name) in [("1", "red shirt"), ("2", "blue shirt"), ("3", "red hat")] {
+            engine
+                .add_document("products", product(id, name, "/clothing"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let query = SearchQuery {
+            limit: Some(1),
+            ..SearchQuery::new(
+                "products",
+                QueryExpression::FullText {
+                    field: "name".to_string(),
+                    text: "shirt".to_string(),
+                    boost: None,
+                },
+            )
+        };
+        let count = engine.count(query.clone()).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(count, engine.search(query).unwrap().total_hits);
+    }
+
+    #[test]
+    fn test_top_terms() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let names = [
+            ("1", "red shirt"),
+            ("2", "blue shirt"),
+            ("3", "red hat"),
+            ("4", "green shirt"),
+        ];
+        for (id, name) in names {
+            engine
+                .add_document("products", product(id, name, "/clothing"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let top_terms = engine.top_terms("products", "name", 2).unwrap();
+        assert_eq!(
+            top_terms,
+            vec![("shirt".to_string(), 3), ("red".to_string(), 2)]
+        );
+        assert!(engine.top_terms("products", "price", 2).is_err());
+    }
+
+    #[test]
+    fn test_lowercase_keyword_field() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::product_catalog_schema();
+        schema.fields.insert(
+            "brand".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: true,
+                tokenizer: "keyword".to_string(),
+                stop_words: None,
+                normalize: Some("lowercase".to_string()),
+            },
+        );
+        engine
+            .create_collection("products".to_string(), schema)
+            .unwrap();
+        let mut doc = product("1", "phone", "/electronics");
+        doc.fields.insert(
+            "brand".to_string(),
+            FieldValue::Text("Apple Inc".to_string()),
+        );
+        engine.add_document("products", doc).unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let brand_hits = |brand: &str| {
+            let query = SearchQuery::new(
+                "products",
+                QueryExpression::Term {
+                    field: "brand".to_string(),
+                    value: FieldValue::Text(brand.to_string()),
+                },
+            );
+            engine.search(query).unwrap()
+        };
+        for brand in ["apple inc", "APPLE INC", "Apple Inc"] {
+            assert_eq!(brand_hits(brand).total_hits, 1);
+        }
+        // The value is still matched whole, and stored as given
+        assert_eq!(brand_hits("apple").total_hits, 0);
+        assert_eq!(
+            brand_hits("apple inc").documents[0].fields["brand"],
+            FieldValue::Text("Apple Inc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_term_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let names = [
+            ("1", "red shirt"),
+            ("2", "red red hat"),
+            ("3", "blue shirt"),
+            ("4", "red scarf"),
+            ("5", "green hat"),
+        ];
+        for (id, name) in names {
+            engine
+                .add_document("products", product(id, name, "/clothing"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        let stats = engine.term_stats("products", "name", "Red").unwrap();
+        assert_eq!(
+            stats,
+            TermStats {
+                doc_freq: 3,
+                total_term_freq: 4,
+            }
+        );
+        let absent = engine.term_stats("products", "name", "purple").unwrap();
+        assert_eq!(absent, TermStats::default());
+        assert!(engine.term_stats("products", "price", "red").is_err());
+    }
+
+    #[test]
+    fn test_suggest() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        let contents = [
+            "The best restaurant in town",
+            "A restaurant review",
+            "Restaurants and cafes",
+            "Restaurant opening hours",
+            "The rest of the story",
+        ];
+        for (i, content) in contents.iter().enumerate() {
+            let mut fields = HashMap::new();
+            fields.insert("content".to_string(), FieldValue::Text(content.to_string()));
+            engine
+                .add_document(
+                    "blog",
+                    IndexDocument {
+                        id: i.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("blog").unwrap();
+
+        let suggestions = engine.suggest("blog", "content", "resturant", 2).unwrap();
+        assert_eq!(suggestions[0], ("restaurant".to_string(), 3));
+        assert!(suggestions.iter().any(|(term, _)| term == "restaurants"));
+        assert!(suggestions.iter().all(|(term, _)| term != "rest"));
+
+        // The term is analyzed first and never suggests itself
+        assert_eq!(
+            engine.suggest("blog", "content", "Restaurant", 1).unwrap(),
+            vec![("restaurants".to_string(), 1)]
+        );
+        assert!(engine.suggest("blog", "content", "resturant", 3).is_err());
+    }
+
+    #[test]
+    fn test_autocomplete() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let names = [
+            ("1", "search engine"),
+            ("2", "search box"),
+            ("3", "season pass"),
+            ("4", "search season"),
+            ("5", "sea salt"),
+            ("6", "apple"),
+        ];
+        for (id, name) in names {
+            engine
+                .add_document("products", product(id, name, "/misc"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        assert_eq!(
+            engine.autocomplete("products", "name", "sea", 10).unwrap(),
+            vec!["search", "season", "sea"]
+        );
+        assert_eq!(
+            engine.autocomplete("products", "name", "sea", 2).unwrap(),
+            vec!["search", "season"]
+        );
+        // The prefix is lowercased like the indexed terms
+        assert_eq!(
+            engine.autocomplete("products", "name", "Sea", 10).unwrap(),
+            vec!["search", "season", "sea"]
+        );
+        assert!(
+            engine
+                .autocomplete("products", "name", "zzz", 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_exists_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::blog_post_schema();
+        schema.fields.insert(
+            "notes".to_string(),
+            FieldType::Text {
+                stored: true,
+                indexed: false,
+                tokenizer: "default".to_string(),
+                stop_words: None,
+                normalize: None,
+            },
+        );
+        engine
+            .create_collection("blog".to_string(), schema)
+            .unwrap();
+        for i in 0..6 {
+            let mut fields = HashMap::new();
+            fields.insert("title".to_string(), FieldValue::Text(format!("Post {}", i)));
+            if i % 2 == 0 {
+                fields.insert("rating".to_string(), FieldValue::F64(i as f64));
+                fields.insert("author".to_string(), FieldValue::Text("ann".to_string()));
+            }
+            engine
+                .add_document(
+                    "blog",
+                    IndexDocument {
+                        id: i.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("blog").unwrap();
+
+        let exists = |field: &str| {
+            engine
+                .search(SearchQuery::new(
+                    "blog",
+                    QueryExpression::Exists {
+                        field: field.to_string(),
+                    },
+                ))
+                .map(|result| {
+                    let mut ids: Vec<_> = result.documents.into_iter().map(|hit| hit.id).collect();
+                    ids.sort();
+                    ids
+                })
+        };
+
+        assert_eq!(exists("rating").unwrap(), vec!["0", "2", "4"]);
+        assert_eq!(exists("author").unwrap(), vec!["0", "2", "4"]);
+        assert_eq!(exists("title").unwrap().len(), 6);
+        assert!(matches!(
+            exists("notes"),
+            Err(SearchEngineError::QueryError(msg)) if msg == "Field 'notes' is not indexed"
+        ));
+    }
+
+    /// Engine with a `places` collection holding the given geo points
+    fn places_engine(temp_dir: &TempDir, places: &[(&str, f64, f64)]) -> RustSearchEngine {
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        let mut schema = schema_helpers::text_collection_schema("places", &[]);
+        schema.fields.insert(
+            "location".to_string(),
+            FieldType::Geo {
+                stored: true,
+                indexed: true,
+            },
+        );
+        engine
+            .create_collection("places".to_string(), schema)
+            .unwrap();
+        for (id, lat, lon) in places {
+            let mut fields = HashMap::new();
+            fields.insert(
+                "location".to_string(),
+                FieldValue::GeoPoint {
+                    lat: *lat,
+                    lon: *lon,
+                },
+            );
+            engine
+                .add_document(
+                    "places",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("places").unwrap();
+        engine
+    }
+
+    fn sorted_ids(result: SearchResult) -> Vec<String> {
+        let mut ids: Vec<_> = result.documents.into_iter().map(|hit| hit.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_geo_bounding_box_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = places_engine(
+            &temp_dir,
+            &[
+                ("paris", 48.8566, 2.3522),
+                ("berlin", 52.52, 13.405),
+                ("new_york", 40.7128, -74.006),
+                ("fiji", -17.7134, 178.065),
+            ],
+        );
+        let bounding_box = |min_lat, max_lat, min_lon, max_lon| {
+            engine.search(SearchQuery::new(
+                "places",
+                QueryExpression::GeoBoundingBox {
+                    field: "location".to_string(),
+                    min_lat,
+                    max_lat,
+                    min_lon,
+                    max_lon,
+                },
+            ))
+        };
+
+        let europe = bounding_box(45.0, 55.0, 0.0, 15.0).unwrap();
+        assert_eq!(sorted_ids(europe), vec!["berlin", "paris"]);
+
+        // Crossing the antimeridian
+        let pacific = bounding_box(-20.0, -10.0, 170.0, -170.0).unwrap();
+        assert_eq!(sorted_ids(pacific), vec!["fiji"]);
+
+        let empty = bounding_box(0.0, 10.0, 0.0, 10.0).unwrap();
+        assert_eq!(empty.total_hits, 0);
+        assert!(bounding_box(10.0, 0.0, 0.0, 10.0).is_err());
+
+        let paris = bounding_box(48.0, 49.0, 2.0, 3.0).unwrap();
+        assert!(matches!(
+            paris.documents[0].fields["location"],
+            FieldValue::GeoPoint { lat, lon } if lat == 48.8566 && lon == 2.3522
+        ));
+    }
+
+    #[test]
+    fn test_geo_distance_query_and_sort() {
+        // Points a given distance due north or east of the center
+        let km_north = |km: f64| 48.8566 + (km / 6371.0088_f64).to_degrees();
+        let km_east =
+            |km: f64| 2.3522 + (km / 6371.0088_f64).to_degrees() / 48.8566_f64.to_radians().cos();
+        let temp_dir = TempDir::new().unwrap();
+        let engine = places_engine(
+            &temp_dir,
+            &[
+                ("paris", 48.8566, 2.3522),
+                ("north_inside", km_north(9.9), 2.3522),
+                ("north_outside", km_north(10.1), 2.3522),
+                ("east_inside", 48.8566, km_east(9.5)),
+                ("east_outside", 48.8566, km_east(10.5)),
+                ("berlin", 52.52, 13.405),
+                ("new_york", 40.7128, -74.006),
+            ],
+        );
+
+        let within = |radius_km| {
+            engine.search(SearchQuery::new(
+                "places",
+                QueryExpression::GeoDistance {
+                    field: "location".to_string(),
+                    center_lat: 48.8566,
+                    center_lon: 2.3522,
+                    radius_km,
+                },
+            ))
+        };
+        assert_eq!(
+            sorted_ids(within(10.0).unwrap()),
+            vec!["east_inside", "north_inside", "paris"]
+        );
+        assert_eq!(within(1000.0).unwrap().total_hits, 6);
+        assert!(within(-1.0).is_err());
+
+        let by_distance = |order| {
+            let query = SearchQuery {
+                sort: Some(vec![SortField {
+                    field: "location".to_string(),
+                    order,
+                    distance_from: Some((48.8566, 2.3522)),
+                    missing: MissingOrder::Last,
+                }]),
+                limit: Some(4),
+                ..SearchQuery::new("places", QueryExpression::MatchAll)
+            };
+            let result = engine.search(query).unwrap();
+            result
+                .documents
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            by_distance(SortOrder::Asc),
+            vec!["paris", "east_inside", "north_inside", "north_outside"]
+        );
+        assert_eq!(
+            by_distance(SortOrder::Desc),
+            vec!["new_york", "berlin", "east_outside", "north_outside"]
+        );
+    }
+
+    #[test]
+    fn test_sort_missing_placement() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_engine_with_data_dir(temp_dir.path()).unwrap();
+        engine
+            .create_collection("blog".to_string(), schema_helpers::blog_post_schema())
+            .unwrap();
+        for (id, views) in [("low", Some(1)), ("high", Some(3)), ("unset", None)] {
+            let mut fields = HashMap::new();
+            if let Some(views) = views {
+                fields.insert("view_count".to_string(), FieldValue::I64(views));
+            }
+            engine
+                .add_document(
+                    "blog",
+                    IndexDocument {
+                        id: id.to_string(),
+                        fields,
+                    },
+                )
+                .unwrap();
+        }
+        engine.commit_collection("blog").unwrap();
+
+        let sorted = |order, missing| {
+            let query = SearchQuery {
+                sort: Some(vec![SortField {
+                    field: "view_count".to_string(),
+                    order,
+                    distance_from: None,
+                    missing,
+                }]),
+                ..SearchQuery::new("blog", QueryExpression::MatchAll)
+            };
+            engine
+                .search(query)
+                .unwrap()
+                .documents
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(SortOrder::Asc, MissingOrder::Last),
+            ["low", "high", "unset"]
+        );
+        assert_eq!(
+            sorted(SortOrder::Asc, MissingOrder::First),
+            ["unset", "low", "high"]
+        );
+        assert_eq!(
+            sorted(SortOrder::Desc, MissingOrder::Last),
+            ["high", "low", "unset"]
+        );
+        assert_eq!(
+            sorted(SortOrder::Desc, MissingOrder::First),
+            ["unset", "high", "low"]
+        );
+    }
+
+    #[test]
+    fn test_partial_results_on_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        let num_docs = 3000;
+        for i in 0..num_docs {
+            engine
+                .add_document("products", product(&i.to_string(), "widget", "/misc"))
+                .unwrap();
+        }
+        engine.commit_collection("products").unwrap();
+
+        // A zero timeout expires before the first segment is searched
+        let expensive = |partial_on_timeout| SearchQuery {
+            timeout_ms: Some(0),
+            partial_on_timeout,
+            ..SearchQuery::new(
+                "products",
+                QueryExpression::Wildcard {
+                    field: "name".to_string(),
+                    pattern: "w*t".to_string(),
+                },
+            )
+        };
+
+        let result = engine.search(expensive(true)).unwrap();
+        assert!(result.timed_out);
+        assert!(result.documents.is_empty());
+        assert_eq!(result.total_hits, 0);
+
+        assert!(matches!(
+            engine.search(expensive(false)),
+            Err(SearchEngineError::Timeout { timeout_ms: 0 })
+        ));
+
+        let complete = engine
+            .search(SearchQuery {
+                timeout_ms: Some(60_000),
+                ..expensive(false)
+            })
+            .unwrap();
+        assert!(!complete.timed_out);
+        assert_eq!(complete.total_hits, num_docs);
+    }
+
+    #[test]
+    fn test_timeout_bounds_expensive_regex() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = products_engine(&temp_dir);
+        // Many segments of distinct terms, each expanded separately
+        let num_segments = 20;
+        let docs_per_segment = 500;
+        for segment in 0..num_segments {
+            for i in 0..docs_per_segment {
+                let id = format!("{}-{}", segment, i);
+                let name = format!("item{}x{}", segment, i);
+                engine
+                    .add_document("products", product(&id, &name, "/misc"))
+                    .unwrap();
+            }
+            engine.commit_collection("products").unwrap();
+        }
+
+        // Compiling this regex builds a large automaton, and every name matches
+        let expensive = |timeout_ms, partial_on_timeout| SearchQuery {
+            timeout_ms,
+            partial_on_timeout,
+            ..SearchQuery::new(
+                "products",
+                QueryExpression::Regex {
+                    field: "name".to_string(),
+                    pattern: "item.*|(a|b)*a(a|b){8}".to_string(),
+                },
+            )
+        };
+        let num_docs = num_segments * docs_per_segment;
+        assert_eq!(
+            engine.search(expensive(None, false)).unwrap().total_hits,
+            num_docs
+        );
+
+        assert!(matches!(
+            engine.search(expensive(Some(20), false)),
+            Err(SearchEngineError::Timeout { timeout_ms: 20 })
+        ));
+        // Segments reached after the deadline are not expanded at all
+        let partial = engine.search(expensive(Some(20), true)).unwrap();
+        assert!(partial.timed_out);
+        assert!(partial.total_hits < num_docs);
+    }
+
+    /// Records every span name and field value it sees
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<(String, String, String)>>>);
+
+    struct FieldVisitor<'a> {
+        span: &'static str,
+        records: &'a mut Vec<(String, String, String)>,
+    }
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.records.push((
+                self.span.to_string(),
+                field.name().to_string(),
+                format!("{:?}", value),
+            ));
+        }
+    }
+
+    impl<S> Layer<S> for SpanRecorder
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut records = self.0.lock().unwrap();
+            let span = attrs.metadata().name();
+            records.push((span.to_string(), String::new(), String::new()));
+            attrs.record(&mut FieldVisitor {
+                span,
+                records: &mut records,
+            });
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap().name();
+            values.record(&mut FieldVisitor {
+                span,
+                records: &mut self.0.lock().unwrap(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_query_spans() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = EngineConfigBuilder::new()
+            .data_dir(temp_dir.path())
+            .query_spans(true)
+            .build()
+            .unwrap();
+        let engine = products_engine_with_config(config);
+        engine
+            .add_document("products", product("1", "phone", "/electronics"))
+            .unwrap();
+        engine.commit_collection("products").unwrap();
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let query = SearchQuery::new("products", QueryExpression::MatchAll);
+            engine.search(query).unwrap();
+        });
+
+        let records = recorder.0.lock().unwrap();
+        let has = |span: &str, field: &str, value: &str| {
+            records
+                .iter()
+                .any(|(s, f, v)| s == span && f == field && v == value)
+        };
+        assert!(has("search", "collection", "products"));
+        assert!(has("search", "query_type", "\"match_all\""));
+        assert!(has("search", "total_hits", "1"));
+        assert!(has("build_query", "", ""));
+        assert!(has("execute", "total_hits", "1"));
+        assert!(has("convert_hits", "hits", "1"));
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use tantivy::fieldnorm::FieldNormReader;
+use tantivy::postings::{Postings, SegmentPostings};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, EmptyScorer, EnableScoring, Explanation, Occur,
+    Query, QueryParser, QueryParserError, Scorer, TermQuery, Weight,
+};
+use tantivy::query_grammar::{self, UserInputAst};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TERMINATED, TantivyError, Term};
+
+use super::column::{FieldColumn, NumericColumn};
+
+/// BM25 `k1` used by Tantivy's built-in scorer
+pub(crate) const DEFAULT_BM25_K1: f32 = 1.2;
+
+/// BM25 `b` used by Tantivy's built-in scorer
+pub(crate) const DEFAULT_BM25_B: f32 = 0.75;
+
+/// Term weighting applied by a [`TermScoreQuery`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TermScoring {
+    Bm25 { k1: f32, b: f32 },
+    TfIdf,
+}
+
+impl TermScoring {
+    /// Whether Tantivy's own term scorer already computes this weighting
+    pub(crate) fn is_builtin(&self) -> bool {
+        match *self {
+            TermScoring::Bm25 { k1, b } => k1 == DEFAULT_BM25_K1 && b == DEFAULT_BM25_B,
+            TermScoring::TfIdf => false,
+        }
+    }
+
+    /// Inverse document frequency of a term found in `doc_freq` of `num_docs` documents
+    fn idf(&self, num_docs: Score, doc_freq: Score) -> Score {
+        match self {
+            TermScoring::Bm25 { .. } => (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln(),
+            TermScoring::TfIdf => 1.0 + (num_docs / (doc_freq + 1.0)).ln(),
+        }
+    }
+
+    /// Parse query text into a query whose terms are scored by `self`
+    ///
+    /// Tantivy wraps boosted clauses, whether boosted in the text (`rust^2`) or
+    /// through a field boost, in queries that cannot be taken apart again. So
+    /// `parser` must have no field boosts set: boosts are applied here, around
+    /// the rewritten clauses, from the text and from `field_boosts`.
+    pub(crate) fn parse(
+        self,
+        parser: &QueryParser,
+        field_boosts: &HashMap<Field, Score>,
+        text: &str,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let ast = query_grammar::parse_query(text)
+            .map_err(|_| QueryParserError::SyntaxError(text.to_string()))?;
+        self.build(parser, field_boosts, ast)
+    }
+
+    fn build(
+        self,
+        parser: &QueryParser,
+        field_boosts: &HashMap<Field, Score>,
+        ast: UserInputAst,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        match ast {
+            UserInputAst::Boost(ast, boost) => Ok(Box::new(BoostQuery::new(
+                self.build(parser, field_boosts, *ast)?,
+                boost as Score,
+            ))),
+            UserInputAst::Clause(clauses) if clauses.iter().any(|(_, ast)| has_boost(ast)) => {
+                let mut subqueries = Vec::with_capacity(clauses.len());
+                for (occur, ast) in clauses {
+                    let query = self.build(parser, field_boosts, ast)?;
+                    // The parser drops clauses left without terms, such as stop words
+                    if query.downcast_ref::<EmptyQuery>().is_none() {
+                        subqueries.push((occur.unwrap_or(Occur::Should), query));
+                    }
+                }
+                if subqueries.is_empty() {
+                    return Ok(Box::new(EmptyQuery));
+                }
+                if subqueries.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+                    subqueries.push((Occur::Should, Box::new(AllQuery)));
+                }
+                Ok(Box::new(BooleanQuery::new(subqueries)))
+            }
+            ast => Ok(self.rewrite(parser.build_query_from_user_input_ast(ast)?, field_boosts)),
+        }
+    }
+
+    /// Replace the term queries in a parsed query tree with ones scored by `self`
+    ///
+    /// Boolean queries are rebuilt around their rewritten clauses; any other
+    /// query, such as a phrase, keeps Tantivy's scoring. Clauses on a field
+    /// listed in `field_boosts` are boosted by its weight.
+    fn rewrite(
+        self,
+        query: Box<dyn Query>,
+        field_boosts: &HashMap<Field, Score>,
+    ) -> Box<dyn Query> {
+        if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+            let clauses = boolean_query
+                .clauses()
+                .iter()
+                .map(|(occur, clause)| (*occur, self.rewrite(clause.box_clone(), field_boosts)))
+                .collect();
+            return Box::new(BooleanQuery::with_minimum_required_clauses(
+                clauses,
+                boolean_query.get_minimum_number_should_match(),
+            ));
+        }
+
+        let mut field = None;
+        query.query_terms(&mut |term, _| {
+            field.get_or_insert(term.field());
+        });
+        let query: Box<dyn Query> = match query.downcast_ref::<TermQuery>() {
+            Some(term_query) => Box::new(TermScoreQuery {
+                term: term_query.term().clone(),
+                scoring: self,
+            }),
+            None => query,
+        };
+        match field.and_then(|field| field_boosts.get(&field)) {
+            Some(&boost) if boost != 1.0 => Box::new(BoostQuery::new(query, boost)),
+            _ => query,
+        }
+    }
+}
+
+/// Whether query text contains a boosted clause
+fn has_boost(ast: &UserInputAst) -> bool {
+    match ast {
+        UserInputAst::Boost(..) => true,
+        UserInputAst::Clause(clauses) => clauses.iter().any(|(_, ast)| has_boost(ast)),
+        UserInputAst::Leaf(_) => false,
+    }
+}
+
+/// Single-term query scored with configurable term weighting
+#[derive(Debug, Clone)]
+pub(crate) struct TermScoreQuery {
+    term: Term,
+    scoring: TermScoring,
+}
+
+impl Query for TermScoreQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let EnableScoring::Enabled {
+            statistics_provider,
+            ..
+        } = enable_scoring
+        else {
+            return TermQuery::new(self.term.clone(), IndexRecordOption::Basic)
+                .weight(enable_scoring);
+        };
+
+        let field = self.term.field();
+        let total_num_docs = statistics_provider.total_num_docs()?;
+        let total_num_tokens = statistics_provider.total_num_tokens(field)?;
+        let doc_freq = statistics_provider.doc_freq(&self.term)?;
+
+        let average_fieldnorm = if total_num_docs == 0 {
+            0.0
+        } else {
+            total_num_tokens as Score / total_num_docs as Score
+        };
+        let (num_docs, doc_freq) = (total_num_docs as Score, doc_freq as Score);
+        let idf = self.scoring.idf(num_docs, doc_freq);
+
+        Ok(Box::new(TermScoreWeight {
+            term: self.term.clone(),
+            scoring: self.scoring,
+            idf,
+            average_fieldnorm,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        visitor(&self.term, false);
+    }
+}
+
+struct TermScoreWeight {
+    term: Term,
+    scoring: TermScoring,
+    idf: Score,
+    average_fieldnorm: Score,
+}
+
+impl TermScoreWeight {
+    fn term_scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Option<TermScorer>> {
+        let field = self.term.field();
+        let Some(postings) = reader
+            .inverted_index(field)?
+            .read_postings(&self.term, IndexRecordOption::WithFreqs)?
+        else {
+            return Ok(None);
+        };
+        let fieldnorms = reader
+            .fieldnorms_readers()
+            .get_field(field)?
+            .unwrap_or_else(|| FieldNormReader::constant(reader.max_doc(), 1));
+
+        Ok(Some(TermScorer {
+            postings,
+            fieldnorms,
+            scoring: self.scoring,
+            idf: self.idf,
+            average_fieldnorm: self.average_fieldnorm,
+            boost,
+        }))
+    }
+}
+
+impl Weight for TermScoreWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        Ok(match self.term_scorer(reader, boost)? {
+            Some(scorer) => Box::new(scorer),
+            None => Box::new(EmptyScorer),
+        })
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        if let Some(mut scorer) = self.term_scorer(reader, 1.0)?
+            && scorer.seek(doc) == doc
+        {
+            let mut explanation = Explanation::new("TermScore", scorer.score());
+            explanation.add_const("idf", self.idf);
+            explanation.add_const("term_freq", scorer.postings.term_freq() as Score);
+            explanation.add_const("fieldnorm", scorer.fieldnorms.fieldnorm(doc) as Score);
+            explanation.add_const("average_fieldnorm", self.average_fieldnorm);
+            return Ok(explanation);
+        }
+
+        Err(TantivyError::InvalidArgument(format!(
+            "Document #({}) does not match",
+            doc
+        )))
+    }
+}
+
+struct TermScorer {
+    postings: SegmentPostings,
+    fieldnorms: FieldNormReader,
+    scoring: TermScoring,
+    idf: Score,
+    average_fieldnorm: Score,
+    boost: Score,
+}
+
+impl DocSet for TermScorer {
+    fn advance(&mut self) -> DocId {
+        self.postings.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.postings.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.postings.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings.size_hint()
+    }
+}
+
+impl Scorer for TermScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.doc();
+        if doc == TERMINATED {
+            return 0.0;
+        }
+
+        let term_freq = self.postings.term_freq() as Score;
+        let fieldnorm = self.fieldnorms.fieldnorm(doc) as Score;
+        let weight = match self.scoring {
+            TermScoring::Bm25 { k1, b } => {
+                let length_ratio = if self.average_fieldnorm > 0.0 {
+                    fieldnorm / self.average_fieldnorm
+                } else {
+                    1.0
+                };
+                self.idf * term_freq * (k1 + 1.0) / (term_freq + k1 * (1.0 - b + b * length_ratio))
+            }
+            TermScoring::TfIdf => {
+                term_freq.sqrt() * self.idf * self.idf / fieldnorm.max(1.0).sqrt()
+            }
+        };
+        weight * self.boost
+    }
+}
+
+/// Query scaling the scores of an inner query by a fast field value times a factor
+///
+/// Documents without a value in the field score 0.
+#[derive(Debug)]
+pub(crate) struct FieldBoostQuery {
+    query: Box<dyn Query>,
+    field: String,
+    column: NumericColumn,
+    factor: Score,
+}
+
+impl FieldBoostQuery {
+    pub(crate) fn new(
+        query: Box<dyn Query>,
+        field: String,
+        column: NumericColumn,
+        factor: Score,
+    ) -> Self {
+        Self {
+            query,
+            field,
+            column,
+            factor,
+        }
+    }
+}
+
+impl Clone for FieldBoostQuery {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.box_clone(),
+            field: self.field.clone(),
+            column: self.column,
+            factor: self.factor,
+        }
+    }
+}
+
+impl Query for FieldBoostQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(FieldBoostWeight {
+            weight: self.query.weight(enable_scoring)?,
+            field: self.field.clone(),
+            column: self.column,
+            factor: self.factor,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.query.query_terms(visitor);
+    }
+}
+
+struct FieldBoostWeight {
+    weight: Box<dyn Weight>,
+    field: String,
+    column: NumericColumn,
+    factor: Score,
+}
+
+impl FieldBoostWeight {
+    fn multiplier(&self, reader: &SegmentReader) -> tantivy::Result<FieldMultiplier> {
+        Ok(FieldMultiplier {
+            column: FieldColumn::open(reader, &self.field, self.column)?,
+            factor: self.factor,
+        })
+    }
+}
+
+impl Weight for FieldBoostWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        Ok(Box::new(FieldBoostScorer {
+            inner: self.weight.scorer(reader, boost)?,
+            multiplier: self.multiplier(reader)?,
+        }))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let inner = self.weight.explain(reader, doc)?;
+        let multiplier = self.multiplier(reader)?;
+        let value = multiplier.column.value(doc);
+
+        let mut explanation = Explanation::new("FieldBoost", inner.value() * multiplier.of(doc));
+        explanation.add_detail(inner);
+        explanation.add_const("field_value", value.unwrap_or(0.0) as Score);
+        explanation.add_const("factor", self.factor);
+        Ok(explanation)
+    }
+}
+
+struct FieldMultiplier {
+    column: FieldColumn,
+    factor: Score,
+}
+
+impl FieldMultiplier {
+    fn of(&self, doc: DocId) -> Score {
+        self.column.value(doc).unwrap_or(0.0) as Score * self.factor
+    }
+}
+
+struct FieldBoostScorer {
+    inner: Box<dyn Scorer>,
+    multiplier: FieldMultiplier,
+}
+
+impl DocSet for FieldBoostScorer {
+    fn advance(&mut self) -> DocId {
+        self.inner.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.inner.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.inner.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.inner.size_hint()
+    }
+}
+
+impl Scorer for FieldBoostScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.doc();
+        if doc == TERMINATED {
+            return 0.0;
+        }
+        self.inner.score() * self.multiplier.of(doc)
+    }
+}
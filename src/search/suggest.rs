@@ -0,0 +1,28 @@
+use levenshtein_automata::{DFA, Distance, SINK_STATE};
+use tantivy_fst::Automaton;
+
+/// Levenshtein automaton that can walk a term dictionary
+///
+/// Matches every term within the automaton's edit distance of the term it was
+/// built for.
+pub(crate) struct LevenshteinDfa<'a>(pub(crate) &'a DFA);
+
+impl Automaton for LevenshteinDfa<'_> {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
@@ -0,0 +1,134 @@
+//! HTTP API for running Raven as a service
+
+use crate::engine::{EngineHealth, RustSearchEngine};
+use crate::error::SearchEngineError;
+use crate::types::{IndexDocument, SchemaDefinition, SearchQuery, SearchResult};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Search engine shared between request handlers
+pub type SharedEngine = Arc<RwLock<RustSearchEngine>>;
+
+/// Request body for creating a collection
+#[derive(Debug, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    pub schema: SchemaDefinition,
+}
+
+/// Error returned by a handler, rendered as a JSON body with a matching status
+pub struct ApiError(SearchEngineError);
+
+impl From<SearchEngineError> for ApiError {
+    fn from(error: SearchEngineError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            SearchEngineError::CollectionNotFound { .. }
+            | SearchEngineError::DocumentNotFound { .. } => StatusCode::NOT_FOUND,
+            SearchEngineError::SchemaError(_)
+            | SearchEngineError::CollectionError(_)
+            | SearchEngineError::FieldNotFound { .. }
+            | SearchEngineError::TypeMismatch { .. }
+            | SearchEngineError::QueryError(_)
+            | SearchEngineError::ConfigError(_) => StatusCode::BAD_REQUEST,
+            SearchEngineError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Build the HTTP routes for a shared engine
+pub fn router(engine: SharedEngine) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/collections", post(create_collection))
+        .route("/collections/{name}", delete(drop_collection))
+        .route("/collections/{name}/docs", post(add_document))
+        .route("/collections/{name}/search", post(search))
+        .with_state(engine)
+}
+
+/// Serve the HTTP API on the given port until Ctrl-C is received
+pub async fn serve(engine: SharedEngine, port: u16) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    tracing::info!("HTTP server listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, router(engine))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+}
+
+/// Run engine work that blocks on disk I/O or searching on Tokio's blocking
+/// thread pool, so it does not stall the handlers sharing the async workers
+async fn run_blocking<T: Send + 'static>(
+    engine: SharedEngine,
+    work: impl FnOnce(&RustSearchEngine) -> crate::Result<T> + Send + 'static,
+) -> Result<T, ApiError> {
+    let result = tokio::task::spawn_blocking(move || work(&engine.blocking_read()))
+        .await
+        .map_err(|e| SearchEngineError::CustomError(format!("Request task failed: {}", e)))?;
+    Ok(result?)
+}
+
+async fn health(State(engine): State<SharedEngine>) -> Result<Json<EngineHealth>, ApiError> {
+    let health = run_blocking(engine, |engine| engine.health_check()).await?;
+    Ok(Json(health))
+}
+
+async fn create_collection(
+    State(engine): State<SharedEngine>,
+    Json(request): Json<CreateCollectionRequest>,
+) -> Result<StatusCode, ApiError> {
+    run_blocking(engine, move |engine| {
+        engine.create_collection(request.name, request.schema)
+    })
+    .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn drop_collection(
+    State(engine): State<SharedEngine>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    run_blocking(engine, move |engine| engine.drop_collection(&name)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn add_document(
+    State(engine): State<SharedEngine>,
+    Path(name): Path<String>,
+    Json(document): Json<IndexDocument>,
+) -> Result<StatusCode, ApiError> {
+    engine
+        .read()
+        .await
+        .add_document_async(&name, document)
+        .await?;
+    Ok(StatusCode::CREATED)
+}
+
+/// Search a collection; the collection in the path overrides the one in the body
+async fn search(
+    State(engine): State<SharedEngine>,
+    Path(name): Path<String>,
+    Json(mut query): Json<SearchQuery>,
+) -> Result<Json<SearchResult>, ApiError> {
+    query.collection = name;
+    let result = run_blocking(engine, move |engine| engine.search(query)).await?;
+    Ok(Json(result))
+}
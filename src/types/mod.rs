@@ -1,15 +1,37 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::error::{Result, SearchEngineError};
+use crate::schema::{BOOST_FIELD, SchemaManager};
+use crate::search::{DEFAULT_BM25_B, DEFAULT_BM25_K1};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 use tantivy::Score;
+use tantivy::tokenizer::Language;
+
+/// Minimum writer memory budget per indexing thread enforced by Tantivy (15MB)
+pub const MIN_HEAP_SIZE_PER_THREAD: usize = 15_000_000;
+
+/// Maximum number of indexing threads Tantivy uses for a writer
+const MAX_NUM_THREADS: usize = 8;
 
 /// Field type definitions for schema
+///
+/// The derived serde code is wrapped by the `Serialize` and `Deserialize`
+/// impls below, which also accept schemas written before facets had options.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(remote = "Self")]
 pub enum FieldType {
     /// Text field for full-text search
     Text {
         stored: bool,
         indexed: bool,
         tokenizer: String,
+        /// Words dropped from the field's tokens, in queries as well as documents;
+        /// empty or unset means none
+        #[serde(default)]
+        stop_words: Option<Vec<String>>,
+        /// Normalization of keyword field values and terms; `lowercase` makes
+        /// matching case-insensitive
+        #[serde(default)]
+        normalize: Option<String>,
     },
     /// Integer field for numeric search
     I64 {
@@ -17,6 +39,18 @@ pub enum FieldType {
         indexed: bool,
         fast: bool, // For range queries
     },
+    /// Unsigned integer field for identifiers and counters
+    U64 {
+        stored: bool,
+        indexed: bool,
+        fast: bool,
+    },
+    /// Boolean field for flags
+    Bool {
+        stored: bool,
+        indexed: bool,
+        fast: bool,
+    },
     /// Float field for numeric search
     F64 {
         stored: bool,
@@ -30,12 +64,68 @@ pub enum FieldType {
         fast: bool,
     },
     /// Facet field for categorical data
-    Facet,
+    ///
+    /// Tantivy always keeps facet paths in a fast field for counting, so only
+    /// storage and the path depth are configurable. Stored facets are returned
+    /// on hits and can be used as sort keys. Paths are separated by `/`; a `/`
+    /// inside a component must be escaped as `\/`.
+    Facet {
+        stored: bool,
+        /// Most components a facet path may have; unset means no limit
+        #[serde(default)]
+        max_depth: Option<usize>,
+    },
     /// Binary field for raw data
     Bytes { stored: bool, indexed: bool },
-    /// Future: Geospatial field
-    #[allow(dead_code)]
+    /// Geographic point field
+    ///
+    /// Tantivy has no geo type, so the latitude and longitude are kept in two
+    /// f64 fast fields named `<field>.lat` and `<field>.lon`.
     Geo { stored: bool, indexed: bool },
+    /// JSON object field with arbitrary nested keys
+    ///
+    /// Indexed string values are tokenized with the `default` tokenizer and
+    /// can be queried by path through the query parser, e.g.
+    /// `attributes.color:red`.
+    Json { stored: bool, indexed: bool },
+}
+
+impl Serialize for FieldType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        FieldType::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldType {
+    /// Also reads the bare `"Facet"` older schemas use, as an unstored facet
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if value == "Facet" {
+            return Ok(FieldType::Facet {
+                stored: false,
+                max_depth: None,
+            });
+        }
+        FieldType::deserialize(value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FieldType {
+    /// Short name of the field type, used in errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldType::Text { .. } => "text",
+            FieldType::I64 { .. } => "i64",
+            FieldType::U64 { .. } => "u64",
+            FieldType::Bool { .. } => "bool",
+            FieldType::F64 { .. } => "f64",
+            FieldType::Date { .. } => "date",
+            FieldType::Facet { .. } => "facet",
+            FieldType::Bytes { .. } => "bytes",
+            FieldType::Geo { .. } => "geo",
+            FieldType::Json { .. } => "json",
+        }
+    }
 }
 
 /// Schema definition for a collection
@@ -44,6 +134,104 @@ pub struct SchemaDefinition {
     pub name: String,
     pub fields: HashMap<String, FieldType>,
     pub primary_key: Option<String>,
+    /// Fields every document must have a value for; the primary key always is
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Query types searches on this collection may use; all types are allowed when unset
+    #[serde(default)]
+    pub allowed_query_types: Option<HashSet<QueryKind>>,
+    /// Custom tokenizers text fields can name, registered when the collection is created or opened
+    #[serde(default)]
+    pub tokenizers: HashMap<String, TokenizerDefinition>,
+    /// Writer memory budget in bytes, overriding the engine's `default_heap_size`
+    #[serde(default)]
+    pub heap_size: Option<usize>,
+}
+
+/// Tokenizer a custom tokenizer pipeline starts from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum BaseTokenizer {
+    /// Split on non-alphanumeric characters
+    Simple,
+    /// Split on whitespace
+    Whitespace,
+    /// Keep the whole text as a single token
+    Raw,
+    /// Emit every substring of `min_gram` to `max_gram` characters, or only the prefixes
+    Ngram {
+        min_gram: usize,
+        max_gram: usize,
+        prefix_only: bool,
+    },
+}
+
+/// Filter applied to the tokens of a custom tokenizer pipeline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TokenFilterDefinition {
+    Lowercase,
+    /// Drop tokens longer than this many bytes
+    RemoveLong(usize),
+    /// Drop the listed words
+    StopWords(Vec<String>),
+    /// Reduce words to their stem in the given language
+    Stemmer(Language),
+}
+
+/// Custom tokenizer pipeline: a base tokenizer followed by filters, applied in order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenizerDefinition {
+    pub base: BaseTokenizer,
+    #[serde(default)]
+    pub filters: Vec<TokenFilterDefinition>,
+}
+
+impl TokenizerDefinition {
+    /// Start a pipeline from a base tokenizer
+    pub fn new(base: BaseTokenizer) -> Self {
+        Self {
+            base,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Start a pipeline emitting every `min_gram` to `max_gram` character substring
+    pub fn ngram(min_gram: usize, max_gram: usize) -> Self {
+        Self::new(BaseTokenizer::Ngram {
+            min_gram,
+            max_gram,
+            prefix_only: false,
+        })
+    }
+
+    /// Lowercase tokens
+    pub fn lowercase(mut self) -> Self {
+        self.filters.push(TokenFilterDefinition::Lowercase);
+        self
+    }
+
+    /// Drop tokens longer than `max_bytes`
+    pub fn remove_long(mut self, max_bytes: usize) -> Self {
+        self.filters
+            .push(TokenFilterDefinition::RemoveLong(max_bytes));
+        self
+    }
+
+    /// Drop the given stop words
+    pub fn stop_words<I, S>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let words = words.into_iter().map(Into::into).collect();
+        self.filters.push(TokenFilterDefinition::StopWords(words));
+        self
+    }
+
+    /// Stem tokens in the given language
+    pub fn stemmer(mut self, language: Language) -> Self {
+        self.filters.push(TokenFilterDefinition::Stemmer(language));
+        self
+    }
 }
 
 /// Document to be indexed
@@ -53,15 +241,264 @@ pub struct IndexDocument {
     pub fields: HashMap<String, FieldValue>,
 }
 
+impl IndexDocument {
+    /// Build a document from a JSON object, coercing each value to its field's type
+    ///
+    /// The ID is read from the schema's primary key (`_id` by default), as a
+    /// string or an integer, and a numeric `_boost` sets the document boost.
+    /// Every other key must be a declared field; null values are skipped.
+    /// Dates are RFC 3339 strings or integer Unix seconds, bytes are arrays of
+    /// integers from 0 to 255, and geo points are objects with `lat` and `lon`
+    /// numbers.
+    pub fn from_json(schema: &SchemaManager, value: serde_json::Value) -> Result<IndexDocument> {
+        let serde_json::Value::Object(object) = value else {
+            return Err(SearchEngineError::SchemaError(format!(
+                "Document must be a JSON object, got {}",
+                json_type_name(&value)
+            )));
+        };
+
+        let id_field_name = schema.id_field_name();
+        let mut id = None;
+        let mut fields = HashMap::new();
+        for (name, value) in object {
+            if name == id_field_name {
+                id = Some(match value {
+                    serde_json::Value::String(id) => id,
+                    serde_json::Value::Number(number) if number.is_u64() || number.is_i64() => {
+                        number.to_string()
+                    }
+                    other => {
+                        return Err(SearchEngineError::SchemaError(format!(
+                            "Field '{}' expects a string or integer ID, got {}",
+                            name,
+                            json_type_name(&other)
+                        )));
+                    }
+                });
+                continue;
+            }
+            if value.is_null() {
+                continue;
+            }
+
+            let field_value = if name == BOOST_FIELD {
+                FieldValue::F64(
+                    value
+                        .as_f64()
+                        .ok_or_else(|| json_mismatch(&name, "f32", &value))?,
+                )
+            } else {
+                let field_type = schema
+                    .schema_definition()
+                    .fields
+                    .get(&name)
+                    .ok_or_else(|| SearchEngineError::FieldNotFound {
+                        field: name.clone(),
+                    })?;
+                FieldValue::from_json_for_type(&name, field_type, &value)?
+            };
+            fields.insert(name, field_value);
+        }
+
+        let id = id.ok_or_else(|| {
+            SearchEngineError::SchemaError(format!(
+                "Document is missing its ID field '{}'",
+                id_field_name
+            ))
+        })?;
+        Ok(IndexDocument { id, fields })
+    }
+}
+
 /// Field value enum
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum FieldValue {
     Text(String),
+    /// Tokens and their positions, indexed as given instead of through the
+    /// field's tokenizer
+    PreTokenized(Vec<(String, u32)>),
     I64(i64),
+    U64(u64),
+    Bool(bool),
     F64(f64),
+    /// Deserialized from an RFC 3339 string or integer Unix seconds
+    #[serde(deserialize_with = "deserialize_date")]
     Date(chrono::DateTime<chrono::Utc>),
     Facet(String),
     Bytes(Vec<u8>),
+    /// Geographic point in degrees
+    GeoPoint {
+        lat: f64,
+        lon: f64,
+    },
+    /// JSON object
+    Json(serde_json::Value),
+}
+
+impl FieldValue {
+    /// Coerce a JSON value into a value of a field's declared type
+    pub fn from_json_for_type(
+        name: &str,
+        field_type: &FieldType,
+        value: &serde_json::Value,
+    ) -> Result<Self> {
+        let mismatch = || json_mismatch(name, field_type.type_name(), value);
+        match field_type {
+            FieldType::Text { .. } => value
+                .as_str()
+                .map(|text| FieldValue::Text(text.to_string()))
+                .ok_or_else(mismatch),
+            FieldType::I64 { .. } => value.as_i64().map(FieldValue::I64).ok_or_else(mismatch),
+            FieldType::U64 { .. } => value.as_u64().map(FieldValue::U64).ok_or_else(mismatch),
+            FieldType::F64 { .. } => value.as_f64().map(FieldValue::F64).ok_or_else(mismatch),
+            FieldType::Bool { .. } => value.as_bool().map(FieldValue::Bool).ok_or_else(mismatch),
+            FieldType::Date { .. } => {
+                if let Some(seconds) = value.as_i64() {
+                    return chrono::DateTime::from_timestamp(seconds, 0)
+                        .map(FieldValue::Date)
+                        .ok_or_else(|| {
+                            SearchEngineError::SchemaError(format!(
+                                "Field '{}' has an out of range timestamp {}",
+                                name, seconds
+                            ))
+                        });
+                }
+                let text = value.as_str().ok_or_else(mismatch)?;
+                let date = parse_rfc3339(text).map_err(|e| {
+                    SearchEngineError::SchemaError(format!(
+                        "Field '{}' expects an RFC 3339 date, got '{}': {}",
+                        name, text, e
+                    ))
+                })?;
+                Ok(FieldValue::Date(date))
+            }
+            FieldType::Facet { .. } => value
+                .as_str()
+                .map(|facet| FieldValue::Facet(facet.to_string()))
+                .ok_or_else(mismatch),
+            FieldType::Bytes { .. } => value
+                .as_array()
+                .and_then(|items| {
+                    items
+                        .iter()
+                        .map(|item| item.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                        .collect::<Option<Vec<u8>>>()
+                })
+                .map(FieldValue::Bytes)
+                .ok_or_else(mismatch),
+            FieldType::Geo { .. } => {
+                let coordinate = |key| value.get(key).and_then(serde_json::Value::as_f64);
+                match (coordinate("lat"), coordinate("lon")) {
+                    (Some(lat), Some(lon)) => Ok(FieldValue::GeoPoint { lat, lon }),
+                    _ => Err(mismatch()),
+                }
+            }
+            FieldType::Json { .. } if value.is_object() => Ok(FieldValue::Json(value.clone())),
+            FieldType::Json { .. } => Err(json_mismatch(name, "an object", value)),
+        }
+    }
+
+    /// Short name of the value's type, matching `FieldType::type_name`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldValue::Text(_) => "text",
+            FieldValue::PreTokenized(_) => "pre_tokenized",
+            FieldValue::I64(_) => "i64",
+            FieldValue::U64(_) => "u64",
+            FieldValue::Bool(_) => "bool",
+            FieldValue::F64(_) => "f64",
+            FieldValue::Date(_) => "date",
+            FieldValue::Facet(_) => "facet",
+            FieldValue::Bytes(_) => "bytes",
+            FieldValue::GeoPoint { .. } => "geo",
+            FieldValue::Json(_) => "json",
+        }
+    }
+}
+
+/// Date input accepted at the API boundary
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateInput {
+    Rfc3339(String),
+    Seconds(i64),
+}
+
+/// Deserialize a date from an RFC 3339 string or integer Unix seconds
+fn deserialize_date<'de, D>(
+    deserializer: D,
+) -> std::result::Result<chrono::DateTime<chrono::Utc>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    match DateInput::deserialize(deserializer)? {
+        DateInput::Rfc3339(text) => parse_rfc3339(&text)
+            .map_err(|e| D::Error::custom(format!("invalid RFC 3339 date '{}': {}", text, e))),
+        DateInput::Seconds(seconds) => chrono::DateTime::from_timestamp(seconds, 0)
+            .ok_or_else(|| D::Error::custom(format!("timestamp {} is out of range", seconds))),
+    }
+}
+
+fn parse_rfc3339(text: &str) -> chrono::ParseResult<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(text).map(|date| date.with_timezone(&chrono::Utc))
+}
+
+/// Format a UTC timestamp as RFC 3339, the way timestamps are shown in JSON and the CLI
+///
+/// Fractional seconds are only written when present, and UTC is written as `Z`.
+pub fn format_rfc3339(date: &chrono::DateTime<chrono::Utc>) -> String {
+    date.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+}
+
+/// Serde format for timestamps written with `format_rfc3339`
+mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(
+        date: &chrono::DateTime<chrono::Utc>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&super::format_rfc3339(date))
+    }
+
+    pub fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> std::result::Result<chrono::DateTime<chrono::Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let text = String::deserialize(deserializer)?;
+        super::parse_rfc3339(&text)
+            .map_err(|e| D::Error::custom(format!("invalid RFC 3339 timestamp '{}': {}", text, e)))
+    }
+}
+
+/// Schema error for a JSON value that does not fit a field's type
+fn json_mismatch(name: &str, expected: &str, value: &serde_json::Value) -> SearchEngineError {
+    SearchEngineError::SchemaError(format!(
+        "Field '{}' expects {}, got {}",
+        name,
+        expected,
+        json_type_name(value)
+    ))
+}
+
+/// Short name of a JSON value's type, used in error messages
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
 }
 
 /// Search query definition
@@ -71,7 +508,101 @@ pub struct SearchQuery {
     pub query: QueryExpression,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    /// Sort fields applied to the hits; a leading `_doc` field returns hits in
+    /// approximate insertion order instead
     pub sort: Option<Vec<SortField>>,
+    /// Stored fields to return on each hit; all stored fields when unset
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    /// Facet fields to count values for over the matching documents
+    ///
+    /// A field name counts its top-level facets; `field:/path` counts the
+    /// direct subfacets of `/path` instead. Counts are keyed by the entry.
+    #[serde(default)]
+    pub facets: Option<Vec<String>>,
+    /// Whether to reload the reader before searching
+    #[serde(default)]
+    pub consistency: ReadConsistency,
+    /// Maximum time to spend executing the query, in milliseconds
+    ///
+    /// A query that runs longer fails with `SearchEngineError::Timeout`
+    /// unless `partial_on_timeout` is set.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Return the hits collected so far instead of an error when the timeout is hit
+    #[serde(default)]
+    pub partial_on_timeout: bool,
+    /// Trade exact ranking for speed by skipping documents that cannot make the top hits
+    ///
+    /// Matches are still counted in a separate pass, so `total_hits` stays exact.
+    /// Equal scores are not ordered by ID. Skipping cannot take document boosts
+    /// into account, so collections with boosted documents are ranked exactly.
+    #[serde(default)]
+    pub approximate: bool,
+    /// Full-text fields to generate highlighted snippets for on each hit
+    #[serde(default)]
+    pub highlight_fields: Option<Vec<String>>,
+    /// Maximum length of a highlighted snippet in characters, defaulting to 150
+    #[serde(default)]
+    pub highlight_max_chars: Option<usize>,
+    /// Cursor of the last hit on the previous page, as its score and ID
+    ///
+    /// Only hits ranked after the cursor are returned, ordered by score and
+    /// then by ID, so pages can be fetched without collecting every earlier
+    /// hit. Cannot be combined with `sort`.
+    #[serde(default)]
+    pub search_after: Option<(Score, String)>,
+    /// Divide every hit's score by the best matching document's score
+    ///
+    /// The top hit then scores 1.0 and the others fall in (0, 1], on every
+    /// page. Cannot be combined with `search_after`, whose cursor holds a raw
+    /// score.
+    #[serde(default)]
+    pub normalize_scores: bool,
+    /// Attach the byte ranges of the query terms in each hit's stored text
+    ///
+    /// Only stored text fields indexed with positions are searched for terms;
+    /// see `SearchHit::matched_terms`.
+    #[serde(default)]
+    pub return_term_offsets: bool,
+}
+
+/// Read consistency level for a search
+///
+/// `Latest` reloads the collection reader before searching so every committed
+/// document is visible, at the cost of a reload on each query. `Cached` reuses
+/// the collection's current searcher, which is faster but may miss documents
+/// committed since the reader was last reloaded. The reader is reloaded on every
+/// `Collection::commit`, so `Cached` only lags behind commits made elsewhere.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReadConsistency {
+    Latest,
+    #[default]
+    Cached,
+}
+
+impl SearchQuery {
+    /// Create a query against a collection with all options left unset
+    pub fn new(collection: impl Into<String>, query: QueryExpression) -> Self {
+        Self {
+            collection: collection.into(),
+            query,
+            limit: None,
+            offset: None,
+            sort: None,
+            fields: None,
+            facets: None,
+            consistency: ReadConsistency::default(),
+            timeout_ms: None,
+            partial_on_timeout: false,
+            approximate: false,
+            highlight_fields: None,
+            highlight_max_chars: None,
+            search_after: None,
+            normalize_scores: false,
+            return_term_offsets: false,
+        }
+    }
 }
 
 /// Query expression enum
@@ -83,7 +614,18 @@ pub enum QueryExpression {
         text: String,
         boost: Option<f32>,
     },
+    /// Full-text query over several fields, each with a score boost
+    ///
+    /// A document matches when the text matches any of the fields.
+    MultiMatch {
+        fields: Vec<(String, f32)>,
+        text: String,
+    },
     /// Term query for exact match
+    ///
+    /// Text values go through the field's tokenizer first, so `Hello` matches
+    /// the token `hello` in a field using the `default` tokenizer. The text
+    /// must make a single token.
     Term { field: String, value: FieldValue },
     /// Range query for numeric fields
     Range {
@@ -101,13 +643,155 @@ pub enum QueryExpression {
     },
     /// Match all documents
     MatchAll,
+    /// Match every document the inner query does not match
+    Not(Box<QueryExpression>),
+    /// Multiply the score of each match of `query` by `field`'s value times `factor`
+    ///
+    /// The field must be a fast i64, u64 or f64 field. Documents without a
+    /// value in it score 0.
+    FieldBoost {
+        query: Box<QueryExpression>,
+        field: String,
+        factor: f32,
+    },
+    /// Glob pattern query on an indexed text field, where `*` matches any
+    /// sequence of characters and `?` matches a single character
+    Wildcard { field: String, pattern: String },
+    /// Regular expression query on an indexed text field, matched against whole terms
+    Regex { field: String, pattern: String },
+    /// Match documents with at least one indexed value in a field
+    Exists { field: String },
+    /// Match documents whose facet field holds `facet_path` or one of its subfacets
+    FacetTerm { field: String, facet_path: String },
+    /// Match geo points inside a latitude/longitude box, bounds included
+    ///
+    /// A box whose `min_lon` is greater than its `max_lon` crosses the
+    /// antimeridian.
+    GeoBoundingBox {
+        field: String,
+        min_lat: f64,
+        max_lat: f64,
+        min_lon: f64,
+        max_lon: f64,
+    },
+    /// Match geo points within `radius_km` kilometres of a center point
+    GeoDistance {
+        field: String,
+        center_lat: f64,
+        center_lon: f64,
+        radius_km: f64,
+    },
 }
 
+/// Kind of a query expression node, without its parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryKind {
+    FullText,
+    MultiMatch,
+    Term,
+    Range,
+    Bool,
+    MatchAll,
+    Not,
+    FieldBoost,
+    Wildcard,
+    Regex,
+    Exists,
+    FacetTerm,
+    GeoBoundingBox,
+    GeoDistance,
+}
+
+impl QueryKind {
+    /// Short name of the query kind, used in logs, spans and errors
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryKind::FullText => "full_text",
+            QueryKind::MultiMatch => "multi_match",
+            QueryKind::Term => "term",
+            QueryKind::Range => "range",
+            QueryKind::Bool => "bool",
+            QueryKind::MatchAll => "match_all",
+            QueryKind::Not => "not",
+            QueryKind::FieldBoost => "field_boost",
+            QueryKind::Wildcard => "wildcard",
+            QueryKind::Regex => "regex",
+            QueryKind::Exists => "exists",
+            QueryKind::FacetTerm => "facet_term",
+            QueryKind::GeoBoundingBox => "geo_bounding_box",
+            QueryKind::GeoDistance => "geo_distance",
+        }
+    }
+}
+
+impl QueryExpression {
+    /// Total number of boolean clauses in this query tree
+    pub fn clause_count(&self) -> usize {
+        match self {
+            QueryExpression::Bool {
+                must,
+                should,
+                must_not,
+                ..
+            } => [must, should, must_not]
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|clause| 1 + clause.clause_count())
+                .sum(),
+            QueryExpression::Not(inner) => 1 + inner.clause_count(),
+            QueryExpression::FieldBoost { query, .. } => query.clause_count(),
+            _ => 0,
+        }
+    }
+
+    /// Kind of this query node
+    pub fn kind(&self) -> QueryKind {
+        match self {
+            QueryExpression::FullText { .. } => QueryKind::FullText,
+            QueryExpression::MultiMatch { .. } => QueryKind::MultiMatch,
+            QueryExpression::Term { .. } => QueryKind::Term,
+            QueryExpression::Range { .. } => QueryKind::Range,
+            QueryExpression::Bool { .. } => QueryKind::Bool,
+            QueryExpression::MatchAll => QueryKind::MatchAll,
+            QueryExpression::Not(_) => QueryKind::Not,
+            QueryExpression::FieldBoost { .. } => QueryKind::FieldBoost,
+            QueryExpression::Wildcard { .. } => QueryKind::Wildcard,
+            QueryExpression::Regex { .. } => QueryKind::Regex,
+            QueryExpression::Exists { .. } => QueryKind::Exists,
+            QueryExpression::FacetTerm { .. } => QueryKind::FacetTerm,
+            QueryExpression::GeoBoundingBox { .. } => QueryKind::GeoBoundingBox,
+            QueryExpression::GeoDistance { .. } => QueryKind::GeoDistance,
+        }
+    }
+
+    /// Short name of the query type, used in logs and spans
+    pub fn type_name(&self) -> &'static str {
+        self.kind().as_str()
+    }
+}
+
+/// Sort field name that orders hits by document address, approximating insertion order
+///
+/// Only honoured as the first sort field; see `SearchQuery::sort`.
+pub const DOC_ORDER_FIELD: &str = "_doc";
+
 /// Sort field specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortField {
     pub field: String,
     pub order: SortOrder,
+    /// For a geo field, sort by distance from this `(lat, lon)` point instead
+    ///
+    /// Only honoured as the first sort field. Documents without a point sort last.
+    #[serde(default)]
+    pub distance_from: Option<(f64, f64)>,
+    /// Where hits without a value for the field go, whatever the order
+    ///
+    /// Distance sorts always put documents without a point last.
+    #[serde(default)]
+    pub missing: MissingOrder,
 }
 
 /// Sort order
@@ -117,20 +801,100 @@ pub enum SortOrder {
     Desc,
 }
 
+/// Placement of hits missing a sort field's value
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MissingOrder {
+    First,
+    #[default]
+    Last,
+}
+
 /// Search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub total_hits: usize,
     pub documents: Vec<SearchHit>,
     pub took_ms: u64,
+    /// Top-level facet values and their document counts, keyed by facet field
+    #[serde(default)]
+    pub facet_counts: HashMap<String, Vec<(String, u64)>>,
+    /// Whether the query hit its timeout and the hits and total are partial
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Whether the top hits were collected in approximate mode, skipping
+    /// documents that could not make them
+    #[serde(default)]
+    pub approximate: bool,
+    /// Maximum number of hits requested for this page
+    #[serde(default)]
+    pub limit: usize,
+    /// Number of hits skipped before this page
+    #[serde(default)]
+    pub offset: usize,
+    /// Whether more hits follow this page
+    #[serde(default)]
+    pub has_more: bool,
 }
 
 /// Individual search hit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchHit {
     pub id: String,
+    /// Collection the hit was found in
+    #[serde(default)]
+    pub collection: String,
     pub score: Score,
     pub fields: HashMap<String, FieldValue>,
+    /// HTML snippets with query terms wrapped in `<b>` tags, keyed by field
+    #[serde(default)]
+    pub highlights: HashMap<String, Vec<String>>,
+    /// Byte ranges of query terms in the stored text, keyed by field
+    ///
+    /// Only filled in when `SearchQuery::return_term_offsets` is set. Ranges
+    /// index into the field's first stored value.
+    #[serde(default)]
+    pub matched_terms: HashMap<String, Vec<(usize, usize)>>,
+    /// Ordinal of the segment the hit was read from, within the searcher
+    ///
+    /// Together with `doc_id` this locates the hit in the index. Both change
+    /// when segments are merged, so they are only meaningful for debugging.
+    #[serde(default)]
+    pub segment_ord: u32,
+    /// Document ID within its segment
+    #[serde(default)]
+    pub doc_id: u32,
+}
+
+/// Write activity of a collection since it was opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexMetrics {
+    /// Documents added or updated
+    pub documents_indexed: u64,
+    /// Commits of the index writer, soft commits included
+    pub commits: u64,
+    /// How long the last commit took, in microseconds
+    pub last_commit_micros: Option<u64>,
+}
+
+/// What a schema field supports, for clients deciding how they can query it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FieldCapabilities {
+    pub name: String,
+    /// Values are returned on hits
+    pub stored: bool,
+    /// Values can be searched
+    pub indexed: bool,
+    /// Values are kept in a fast field, so sorting and range queries are efficient
+    pub fast: bool,
+}
+
+/// Corpus statistics of a single term
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TermStats {
+    /// Documents containing the term
+    pub doc_freq: u64,
+    /// Occurrences of the term across all documents
+    pub total_term_freq: u64,
 }
 
 /// Collection statistics
@@ -139,7 +903,21 @@ pub struct CollectionStats {
     pub name: String,
     pub document_count: usize,
     pub index_size_bytes: u64,
+    /// Number of segments searched
+    #[serde(default)]
+    pub num_segments: usize,
+    /// Deleted documents still taking space until their segments are merged
+    #[serde(default)]
+    pub num_deleted_docs: usize,
+    /// Memory budget of the index writer, in bytes
+    #[serde(default)]
+    pub heap_size_bytes: usize,
+    /// Operation stamp of the last commit
+    #[serde(default)]
+    pub commit_opstamp: u64,
+    #[serde(with = "rfc3339")]
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "rfc3339")]
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -148,17 +926,114 @@ pub struct CollectionStats {
 pub struct EngineConfig {
     pub data_dir: String,
     pub default_heap_size: usize,
+    /// Number of indexing threads per collection writer
+    pub num_threads: usize,
     pub commit_interval_ms: u64,
     pub enable_compression: bool,
+    pub reopen_behavior: ReopenBehavior,
+    /// Emit `tracing` spans for each query phase
+    pub query_spans: bool,
+    /// What a full-text query with no searchable terms matches
+    pub empty_query_behavior: EmptyQueryBehavior,
+    /// Maximum number of boolean clauses in a single query tree
+    pub max_clause_count: usize,
+    /// Maximum `offset + limit` of a search, bounding the hits collected per query
+    pub max_result_window: usize,
+    /// Maximum number of terms a wildcard or regex query may match in one
+    /// segment before the search fails
+    pub max_expansions: usize,
+    /// How full-text query terms are weighted; BM25 unless set otherwise
+    pub scoring_mode: ScoringMode,
+    /// BM25 term frequency saturation for full-text queries
+    pub bm25_k1: f32,
+    /// BM25 document length normalization for full-text queries, from 0 to 1
+    pub bm25_b: f32,
+    /// Warm up every collection when the engine starts
+    pub warmup_on_start: bool,
+    /// Commit a collection once this many documents were added or updated
+    /// since its last commit, without waiting for the commit interval
+    pub commit_max_docs: Option<usize>,
+    /// Near-real-time mode: soft-commit collections with pending changes at
+    /// this interval, so new documents become searchable well before the
+    /// next full commit
+    pub nrt_refresh_ms: Option<u64>,
+    /// Commit every collection when the engine is dropped without `stop`;
+    /// when false, changes not yet committed are discarded
+    pub commit_on_drop: bool,
+}
+
+/// Term weighting used to score full-text queries
+///
+/// Only term matches are rescored; phrase matches always use BM25.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ScoringMode {
+    /// Okapi BM25, tuned by `bm25_k1` and `bm25_b`
+    #[default]
+    Bm25,
+    /// Classic TF-IDF: the square root of the term frequency, times the
+    /// squared inverse document frequency, normalized by field length
+    TfIdf,
+}
+
+/// Result of a full-text query whose text has no searchable terms
+///
+/// Applies to empty and whitespace-only text, and to text made up entirely of
+/// tokens the field's analyzer removes (such as stop words).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EmptyQueryBehavior {
+    /// Match every document, as if the query were `MatchAll`
+    #[default]
+    MatchAll,
+    /// Match no documents
+    MatchNone,
+}
+
+/// Behavior when opening a collection that is already open
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReopenBehavior {
+    /// Return the existing in-memory handle
+    #[default]
+    ReuseExisting,
+    /// Fail with a collection error
+    Error,
+}
+
+impl EngineConfig {
+    /// Number of CPUs, up to the number of threads Tantivy uses by default
+    ///
+    /// Writers use fewer threads when their heap cannot give each thread
+    /// Tantivy's minimum memory budget.
+    pub fn default_num_threads() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_NUM_THREADS)
+    }
 }
 
 impl Default for EngineConfig {
     fn default() -> Self {
+        let default_heap_size = 50_000_000; // 50MB
+
         Self {
             data_dir: "./data".to_string(),
-            default_heap_size: 50_000_000, // 50MB
-            commit_interval_ms: 1000,      // 1 second
+            default_heap_size,
+            num_threads: Self::default_num_threads(),
+            commit_interval_ms: 1000, // 1 second
             enable_compression: true,
+            reopen_behavior: ReopenBehavior::default(),
+            query_spans: false,
+            empty_query_behavior: EmptyQueryBehavior::default(),
+            max_clause_count: 1024,
+            max_result_window: 10_000,
+            max_expansions: 10_000,
+            scoring_mode: ScoringMode::default(),
+            bm25_k1: DEFAULT_BM25_K1,
+            bm25_b: DEFAULT_BM25_B,
+            warmup_on_start: false,
+            commit_max_docs: None,
+            nrt_refresh_ms: None,
+            commit_on_drop: true,
         }
     }
 }
@@ -0,0 +1,86 @@
+#![cfg(feature = "server")]
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use raven::server::{SharedEngine, router};
+use raven::{QueryExpression, SearchQuery, create_engine_with_data_dir, schema_helpers};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    let body = body.map_or_else(Body::empty, |value| Body::from(value.to_string()));
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+#[tokio::test]
+async fn test_collection_document_and_search_endpoints() {
+    let temp_dir = TempDir::new().unwrap();
+    let engine: SharedEngine = Arc::new(RwLock::new(
+        create_engine_with_data_dir(temp_dir.path()).unwrap(),
+    ));
+    let app = router(engine.clone());
+
+    let (status, health) = send(&app, Method::GET, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health["status"], "healthy");
+
+    let create = json!({
+        "name": "posts",
+        "schema": schema_helpers::blog_post_schema(),
+    });
+    let (status, _) = send(&app, Method::POST, "/collections", Some(create.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, error) = send(&app, Method::POST, "/collections", Some(create)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error["error"].as_str().unwrap().contains("already exists"));
+
+    let doc = json!({
+        "id": "post-1",
+        "fields": { "title": { "Text": "Hello from the server" } },
+    });
+    let (status, _) = send(&app, Method::POST, "/collections/posts/docs", Some(doc)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    engine.read().await.commit_collection("posts").unwrap();
+
+    let query = SearchQuery::new(
+        "ignored",
+        QueryExpression::FullText {
+            field: "title".to_string(),
+            text: "server".to_string(),
+            boost: None,
+        },
+    );
+    let (status, result) = send(
+        &app,
+        Method::POST,
+        "/collections/posts/search",
+        Some(serde_json::to_value(query).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(result["total_hits"], 1);
+    assert_eq!(result["documents"][0]["id"], "post-1");
+
+    let (status, _) = send(&app, Method::DELETE, "/collections/posts", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::DELETE, "/collections/posts", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}